@@ -1,7 +1,12 @@
 //! BMP decoder: uncompressed 24-bit and 32-bit BMP.
 
-use crate::error::PnmError;
-use crate::pixel::PixelLayout;
+use super::source;
+use crate::convert::{ConvertOptions, convert_row};
+use crate::decode::DecodeOutput;
+use crate::downscale::{DownscaleFilter, downscale};
+use crate::error::{PnmError, try_fill};
+use crate::orientation::{Orientation, StoredRows, reverse_pixels};
+use crate::pixel::{PixelLayout, PixelSliceMut};
 use alloc::vec::Vec;
 use enough::Stop;
 
@@ -44,18 +49,11 @@ pub(crate) fn parse_bmp_header(data: &[u8]) -> Result<(u32, u32, PixelLayout), P
     Ok((width, height, layout))
 }
 
-/// Decode BMP pixel data, converting BGR→RGB and handling row flipping + padding.
-pub(crate) fn decode_bmp_pixels(
-    data: &[u8],
-    width: u32,
-    height: u32,
-    layout: PixelLayout,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let (pixel_data, w, h, top_down) = validate_bmp_data(data, width, height)?;
+/// The byte order BMP stores pixels of `layout` in (BGR or BGRA).
+pub(crate) fn stored_layout(layout: PixelLayout) -> Result<PixelLayout, PnmError> {
     match layout {
-        PixelLayout::Rgb8 => decode_24bit(pixel_data, w, h, top_down, stop),
-        PixelLayout::Rgba8 => decode_32bit(pixel_data, w, h, top_down, stop),
+        PixelLayout::Rgb8 => Ok(PixelLayout::Bgr8),
+        PixelLayout::Rgba8 => Ok(PixelLayout::Bgra8),
         _ => Err(PnmError::UnsupportedVariant(alloc::format!(
             "BMP layout {:?} not supported in pixel decoder",
             layout
@@ -63,29 +61,194 @@ pub(crate) fn decode_bmp_pixels(
     }
 }
 
-/// Decode BMP pixel data in native byte order (BGR/BGRA) — no channel swizzle.
-pub(crate) fn decode_bmp_pixels_native(
+/// Decode BMP pixel data into `target`, handling row flipping + padding.
+///
+/// Replaces the contents of `out`, reusing its capacity.
+pub(crate) fn decode_bmp_as(
+    data: &[u8],
+    image_size: (u32, u32),
+    layout: PixelLayout,
+    target: PixelLayout,
+    orientation: Orientation,
+    out: &mut Vec<u8>,
+    stop: &dyn Stop,
+) -> Result<(), PnmError> {
+    let (width, height) = orientation.output_size(image_size.0, image_size.1);
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = (width as usize)
+        .checked_mul(target.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    let out_size = row_bytes
+        .checked_mul(height as usize)
+        .ok_or_else(too_large)?;
+    try_fill(out, 0, out_size)?;
+    let mut rows = PixelSliceMut::strided(out, width, height, row_bytes, target)?;
+    let origin = (0, 0);
+    decode_bmp_region_into(
+        data,
+        image_size,
+        origin,
+        layout,
+        orientation,
+        &mut rows,
+        stop,
+    )
+}
+
+/// Decode BMP pixel data into `out` (same dimensions as the header), without
+/// allocating.
+///
+/// Each stored row is converted straight into its output row, so swizzling,
+/// alpha handling and flipping happen in a single pass.
+pub(crate) fn decode_bmp_into(
+    data: &[u8],
+    layout: PixelLayout,
+    out: &mut PixelSliceMut<'_>,
+    stop: &dyn Stop,
+) -> Result<(), PnmError> {
+    let size = (out.width, out.height);
+    let identity = Orientation::Identity;
+    decode_bmp_region_into(data, size, (0, 0), layout, identity, out, stop)
+}
+
+/// Decode a region at `origin` of an `image_size` BMP into `out`, reading
+/// only the stored rows it covers. `out` has the region's size after
+/// `orientation`.
+///
+/// The caller checks that the region lies within the image.
+pub(crate) fn decode_bmp_region_into(
+    data: &[u8],
+    image_size: (u32, u32),
+    origin: (u32, u32),
+    layout: PixelLayout,
+    orientation: Orientation,
+    out: &mut PixelSliceMut<'_>,
+    stop: &dyn Stop,
+) -> Result<(), PnmError> {
+    let (width, height) = image_size;
+    let (x, y) = (origin.0 as usize, origin.1 as usize);
+    let (region_w, region_rows) = orientation.output_size(out.width, out.height);
+    let (region_w, region_rows) = (region_w as usize, region_rows as usize);
+    let (pixel_data, _, h, top_down) = validate_bmp_data(data, width, height)?;
+    let stored = stored_layout(layout)?;
+    let bpp = stored.bytes_per_pixel();
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = (width as usize).checked_mul(bpp).ok_or_else(too_large)?;
+    // 24-bit rows are padded to a multiple of 4 bytes
+    let row_stride = row_bytes
+        .checked_add(3)
+        .map(|r| r & !3)
+        .ok_or_else(too_large)?;
+    // Stored index of the region's last row in file order
+    let last_row = if top_down { y + region_rows } else { h - y };
+    let needed = if region_rows == 0 {
+        0
+    } else {
+        row_stride.checked_mul(last_row).ok_or_else(too_large)?
+    };
+    if pixel_data.len() < needed {
+        return Err(PnmError::UnexpectedEof);
+    }
+
+    // The region's first stored row, at its left edge
+    let first_row = if top_down { y } else { h - y - region_rows };
+    let rows = StoredRows {
+        data: &pixel_data[(first_row * row_stride + x * bpp).min(needed)..needed],
+        width: region_w,
+        height: region_rows,
+        bpp,
+        stride: row_stride,
+        bottom_up: !top_down,
+    };
+    let target = out.layout;
+    let dst_bpp = target.bytes_per_pixel();
+    let options = ConvertOptions::default();
+    out.for_each_band(|first, band| {
+        for i in 0..band.height as usize {
+            if i % 16 == 0 {
+                stop.check()?;
+            }
+            let dst_row = band.row_mut(i);
+            let reversed = rows.read_row(orientation, first + i, |src, first| {
+                let dst = &mut dst_row[first * dst_bpp..][..src.len() / bpp * dst_bpp];
+                convert_row(src, stored, dst, target, &options);
+            });
+            if reversed {
+                reverse_pixels(dst_row, dst_bpp);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Decode BMP pixel data as `layout` (RGB/RGBA), downscaled by `factor`
+/// one stored row at a time.
+pub(crate) fn decode_bmp_downscaled(
     data: &[u8],
     width: u32,
     height: u32,
     layout: PixelLayout,
+    factor: u32,
+    filter: DownscaleFilter,
     stop: &dyn Stop,
-) -> Result<(Vec<u8>, PixelLayout), PnmError> {
+) -> Result<Vec<u8>, PnmError> {
     let (pixel_data, w, h, top_down) = validate_bmp_data(data, width, height)?;
-    match layout {
-        PixelLayout::Rgb8 => {
-            let pixels = decode_24bit_native(pixel_data, w, h, top_down, stop)?;
-            Ok((pixels, PixelLayout::Bgr8))
-        }
-        PixelLayout::Rgba8 => {
-            let pixels = decode_32bit_native(pixel_data, w, h, top_down, stop)?;
-            Ok((pixels, PixelLayout::Bgra8))
-        }
-        _ => Err(PnmError::UnsupportedVariant(alloc::format!(
-            "BMP layout {:?} not supported in pixel decoder",
-            layout
-        ))),
+    let stored = stored_layout(layout)?;
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = w
+        .checked_mul(stored.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    // 24-bit rows are padded to a multiple of 4 bytes
+    let row_stride = row_bytes
+        .checked_add(3)
+        .map(|r| r & !3)
+        .ok_or_else(too_large)?;
+    if pixel_data.len() < row_stride.checked_mul(h).ok_or_else(too_large)? {
+        return Err(PnmError::UnexpectedEof);
     }
+    let options = ConvertOptions::default();
+    downscale(width, height, layout, factor, filter, stop, |y, row| {
+        let src_row = if top_down { y } else { h - 1 - y };
+        let start = src_row * row_stride;
+        convert_row(
+            &pixel_data[start..start + row_bytes],
+            stored,
+            row,
+            layout,
+            &options,
+        );
+    })
+}
+
+/// Borrow the stored BGR/BGRA rows of BMP data for a `layout` (RGB/RGBA)
+/// image, padded and in stored order.
+pub(crate) fn bmp_native_view(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    let (pixel_data, w, h, top_down) = validate_bmp_data(data, width, height)?;
+    let stored = stored_layout(layout)?;
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = w
+        .checked_mul(stored.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    // 24-bit rows are padded to a multiple of 4 bytes
+    let row_stride = row_bytes
+        .checked_add(3)
+        .map(|r| r & !3)
+        .ok_or_else(too_large)?;
+    let len = match h.checked_sub(1) {
+        Some(last) => row_stride
+            .checked_mul(last)
+            .and_then(|n| n.checked_add(row_bytes))
+            .ok_or_else(too_large)?,
+        None => 0,
+    };
+    let rows = pixel_data.get(..len).ok_or(PnmError::UnexpectedEof)?;
+    let view = DecodeOutput::borrowed_strided(rows, width, height, row_stride, stored, source());
+    Ok(if top_down { view } else { view.bottom_up() })
 }
 
 fn validate_bmp_data(
@@ -115,189 +278,3 @@ fn validate_bmp_data(
         top_down,
     ))
 }
-
-fn decode_24bit(
-    pixel_data: &[u8],
-    w: usize,
-    h: usize,
-    top_down: bool,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w
-        .checked_mul(3)
-        .and_then(|r| r.checked_add(3))
-        .map(|r| r & !3)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    let needed = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    if pixel_data.len() < needed {
-        return Err(PnmError::UnexpectedEof);
-    }
-
-    let out_size =
-        w.checked_mul(h)
-            .and_then(|wh| wh.checked_mul(3))
-            .ok_or(PnmError::DimensionsTooLarge {
-                width: w as u32,
-                height: h as u32,
-            })?;
-    let mut out = Vec::with_capacity(out_size);
-    for row in 0..h {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        let src_row = if top_down { row } else { h - 1 - row };
-        let row_start = src_row * row_stride;
-        for col in 0..w {
-            let off = row_start + col * 3;
-            out.push(pixel_data[off + 2]); // R
-            out.push(pixel_data[off + 1]); // G
-            out.push(pixel_data[off]); // B
-        }
-    }
-
-    Ok(out)
-}
-
-fn decode_32bit(
-    pixel_data: &[u8],
-    w: usize,
-    h: usize,
-    top_down: bool,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w.checked_mul(4).ok_or(PnmError::DimensionsTooLarge {
-        width: w as u32,
-        height: h as u32,
-    })?;
-    let needed = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    if pixel_data.len() < needed {
-        return Err(PnmError::UnexpectedEof);
-    }
-
-    let out_size =
-        w.checked_mul(h)
-            .and_then(|wh| wh.checked_mul(4))
-            .ok_or(PnmError::DimensionsTooLarge {
-                width: w as u32,
-                height: h as u32,
-            })?;
-    let mut out = Vec::with_capacity(out_size);
-    for row in 0..h {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        let src_row = if top_down { row } else { h - 1 - row };
-        let row_start = src_row * row_stride;
-        for col in 0..w {
-            let off = row_start + col * 4;
-            out.push(pixel_data[off + 2]); // R
-            out.push(pixel_data[off + 1]); // G
-            out.push(pixel_data[off]); // B
-            out.push(pixel_data[off + 3]); // A
-        }
-    }
-
-    Ok(out)
-}
-
-/// Decode 24-bit BMP preserving native BGR byte order (row flip only, no swizzle).
-fn decode_24bit_native(
-    pixel_data: &[u8],
-    w: usize,
-    h: usize,
-    top_down: bool,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w
-        .checked_mul(3)
-        .and_then(|r| r.checked_add(3))
-        .map(|r| r & !3)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    let needed = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    if pixel_data.len() < needed {
-        return Err(PnmError::UnexpectedEof);
-    }
-
-    let row_bytes = w * 3;
-    let out_size =
-        w.checked_mul(h)
-            .and_then(|wh| wh.checked_mul(3))
-            .ok_or(PnmError::DimensionsTooLarge {
-                width: w as u32,
-                height: h as u32,
-            })?;
-    let mut out = Vec::with_capacity(out_size);
-    for row in 0..h {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        let src_row = if top_down { row } else { h - 1 - row };
-        let row_start = src_row * row_stride;
-        out.extend_from_slice(&pixel_data[row_start..row_start + row_bytes]);
-    }
-
-    Ok(out)
-}
-
-/// Decode 32-bit BMP preserving native BGRA byte order (row flip only, no swizzle).
-fn decode_32bit_native(
-    pixel_data: &[u8],
-    w: usize,
-    h: usize,
-    top_down: bool,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w.checked_mul(4).ok_or(PnmError::DimensionsTooLarge {
-        width: w as u32,
-        height: h as u32,
-    })?;
-    let needed = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: w as u32,
-            height: h as u32,
-        })?;
-    if pixel_data.len() < needed {
-        return Err(PnmError::UnexpectedEof);
-    }
-
-    let out_size =
-        w.checked_mul(h)
-            .and_then(|wh| wh.checked_mul(4))
-            .ok_or(PnmError::DimensionsTooLarge {
-                width: w as u32,
-                height: h as u32,
-            })?;
-    let mut out = Vec::with_capacity(out_size);
-    for row in 0..h {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        let src_row = if top_down { row } else { h - 1 - row };
-        let row_start = src_row * row_stride;
-        out.extend_from_slice(&pixel_data[row_start..row_start + row_stride]);
-    }
-
-    Ok(out)
-}
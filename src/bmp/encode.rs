@@ -1,237 +1,143 @@
 //! BMP encoder: uncompressed 24-bit and 32-bit BMP.
 
-use crate::error::PnmError;
-use crate::pixel::PixelLayout;
+use crate::convert::{ConvertOptions, convert_row};
+use crate::error::{PnmError, try_vec};
+use crate::orientation::{Orientation, reverse_pixels};
+use crate::parallel;
+use crate::pixel::{PixelLayout, PixelSlice};
 use alloc::vec::Vec;
 use enough::Stop;
 
-/// Encode pixels to BMP format.
+/// Encode pixels to BMP format, with `orientation` applied while writing.
 pub(crate) fn encode_bmp(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    layout: PixelLayout,
+    src: &PixelSlice<'_>,
     alpha: bool,
+    options: &ConvertOptions,
+    orientation: Orientation,
     stop: &dyn Stop,
 ) -> Result<Vec<u8>, PnmError> {
-    let w = width as usize;
-    let h = height as usize;
-    let expected = w
-        .checked_mul(h)
-        .and_then(|wh| wh.checked_mul(layout.bytes_per_pixel()))
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    if pixels.len() < expected {
-        return Err(PnmError::BufferTooSmall {
-            needed: expected,
-            actual: pixels.len(),
-        });
-    }
-
-    stop.check()?;
-
-    if alpha {
-        encode_32bit(pixels, width, height, w, h, layout, stop)
-    } else {
-        encode_24bit(pixels, width, height, w, h, layout, stop)
-    }
+    // Fail before allocating when the conversion is rejected
+    options.check(src.layout, target_layout(alpha))?;
+    let (width, height) = orientation.output_size(src.width, src.height);
+    let mut out = try_vec(0u8, encoded_len(width, height, alpha)?)?;
+    encode_bmp_into(src, alpha, options, orientation, &mut out, stop)?;
+    Ok(out)
 }
 
-fn encode_24bit(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    w: usize,
-    h: usize,
-    layout: PixelLayout,
+/// Encode pixels to BMP format into `out`, returning the bytes written.
+///
+/// Does not allocate; `out` must hold [`encoded_len`] bytes of the oriented
+/// size.
+pub(crate) fn encode_bmp_into(
+    src: &PixelSlice<'_>,
+    alpha: bool,
+    options: &ConvertOptions,
+    orientation: Orientation,
+    out: &mut [u8],
     stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w
-        .checked_mul(3)
-        .and_then(|r| r.checked_add(3))
-        .map(|r| r & !3)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let pixel_data_size = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let file_size = pixel_data_size
-        .checked_add(54)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
+) -> Result<usize, PnmError> {
+    let target = target_layout(alpha);
+    options.check(src.layout, target)?;
 
-    let mut out = Vec::with_capacity(file_size);
-    write_bmp_header(&mut out, file_size, pixel_data_size, width, height, 24);
+    stop.check()?;
 
-    let pad_bytes = row_stride - w * 3;
-    let is_bgr_native = matches!(layout, PixelLayout::Bgr8);
-    let src_bpp = layout.bytes_per_pixel();
-    for row in (0..h).rev() {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        if is_bgr_native {
-            // BGR→BMP24: already in native byte order, direct copy
-            let row_start = row * w * src_bpp;
-            out.extend_from_slice(&pixels[row_start..row_start + w * 3]);
-        } else {
-            for col in 0..w {
-                let (r, g, b) = get_rgb(pixels, row * w + col, layout)?;
-                out.push(b);
-                out.push(g);
-                out.push(r);
+    let (width, height) = orientation.output_size(src.width, src.height);
+    let (w, h) = (width as usize, height as usize);
+    let file_size = encoded_len(width, height, alpha)?;
+    if out.len() < file_size {
+        return Err(PnmError::BufferTooSmall {
+            needed: file_size,
+            actual: out.len(),
+        });
+    }
+    let row_stride = padded_row_bytes(w, target);
+    let pixel_data_size = file_size - HEADER_SIZE;
+    let bpp = if alpha { 32 } else { 24 };
+    out[..HEADER_SIZE].copy_from_slice(&bmp_header(file_size, pixel_data_size, width, height, bpp));
+
+    // Bgr8/Bgra8 input is already in BMP byte order (convert_row copies it
+    // directly); Bgrx8 padding becomes 255 (opaque) and premultiplied input
+    // is stored straight.
+    let (src_bpp, dst_bpp) = (src.layout.bytes_per_pixel(), target.bytes_per_pixel());
+    let dst_row_bytes = w * dst_bpp;
+    let stored = src.stored_rows();
+    let rows = &mut out[HEADER_SIZE..file_size];
+    parallel::for_each_band(rows, row_stride, h, |first, band, band_rows| {
+        for j in 0..band_rows {
+            if j % 16 == 0 {
+                stop.check()?;
             }
+            // Stored bottom-up
+            let row = h - 1 - (first + j);
+            let (dst_row, padding) =
+                band[j * row_stride..(j + 1) * row_stride].split_at_mut(dst_row_bytes);
+            let reversed = stored.read_row(orientation, row, |run, first| {
+                let dst = &mut dst_row[first * dst_bpp..][..run.len() / src_bpp * dst_bpp];
+                convert_row(run, src.layout, dst, target, options);
+            });
+            if reversed {
+                reverse_pixels(dst_row, dst_bpp);
+            }
+            // Row padding is zero
+            padding.fill(0);
         }
-        out.extend(core::iter::repeat_n(0u8, pad_bytes));
-    }
+        Ok(())
+    })?;
 
-    Ok(out)
+    Ok(file_size)
 }
 
-fn encode_32bit(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    w: usize,
-    h: usize,
-    layout: PixelLayout,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let row_stride = w
-        .checked_mul(4)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let pixel_data_size = row_stride
-        .checked_mul(h)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let file_size = pixel_data_size
-        .checked_add(54)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
+/// Exact BMP file size for a `width` x `height` image.
+pub(crate) fn encoded_len(width: u32, height: u32, alpha: bool) -> Result<usize, PnmError> {
+    padded_row_bytes(width as usize, target_layout(alpha))
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_add(HEADER_SIZE))
+        .ok_or(PnmError::DimensionsTooLarge { width, height })
+}
 
-    let mut out = Vec::with_capacity(file_size);
-    write_bmp_header(&mut out, file_size, pixel_data_size, width, height, 32);
+/// File header plus BITMAPINFOHEADER.
+const HEADER_SIZE: usize = 54;
 
-    // Only Bgra8 can use the direct copy fast path. Bgrx8 must go through
-    // get_rgba() which forces the padding byte to 255 (opaque).
-    let is_bgra_native = matches!(layout, PixelLayout::Bgra8);
-    for row in (0..h).rev() {
-        if row % 16 == 0 {
-            stop.check()?;
-        }
-        if is_bgra_native {
-            // BGRA/BGRX→BMP32: already in native byte order, direct copy
-            let row_start = row * w * 4;
-            out.extend_from_slice(&pixels[row_start..row_start + w * 4]);
-        } else {
-            for col in 0..w {
-                let (r, g, b, a) = get_rgba(pixels, row * w + col, layout)?;
-                out.push(b);
-                out.push(g);
-                out.push(r);
-                out.push(a);
-            }
-        }
+fn target_layout(alpha: bool) -> PixelLayout {
+    if alpha {
+        PixelLayout::Bgra8
+    } else {
+        PixelLayout::Bgr8
     }
+}
 
-    Ok(out)
+/// Stored row size: 24-bit rows are padded to a multiple of 4 bytes.
+///
+/// Saturates on overflow; [`encoded_len`] then fails its checked multiply
+/// for any height that could overflow.
+fn padded_row_bytes(w: usize, layout: PixelLayout) -> usize {
+    w.saturating_mul(layout.bytes_per_pixel()).saturating_add(3) & !3
 }
 
-fn write_bmp_header(
-    out: &mut Vec<u8>,
+fn bmp_header(
     file_size: usize,
     pixel_data_size: usize,
     width: u32,
     height: u32,
     bpp: u16,
-) {
+) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
     // File header (14 bytes)
-    out.extend_from_slice(b"BM");
-    out.extend_from_slice(&(file_size as u32).to_le_bytes());
-    out.extend_from_slice(&[0u8; 4]); // reserved
-    out.extend_from_slice(&54u32.to_le_bytes()); // data offset
+    header[0..2].copy_from_slice(b"BM");
+    header[2..6].copy_from_slice(&(file_size as u32).to_le_bytes());
+    // 6..10 reserved
+    header[10..14].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes()); // data offset
 
     // DIB header (BITMAPINFOHEADER, 40 bytes)
-    out.extend_from_slice(&40u32.to_le_bytes());
-    out.extend_from_slice(&(width as i32).to_le_bytes());
-    out.extend_from_slice(&(height as i32).to_le_bytes()); // positive = bottom-up
-    out.extend_from_slice(&1u16.to_le_bytes()); // planes
-    out.extend_from_slice(&bpp.to_le_bytes());
-    out.extend_from_slice(&0u32.to_le_bytes()); // compression
-    out.extend_from_slice(&(pixel_data_size as u32).to_le_bytes());
-    out.extend_from_slice(&2835u32.to_le_bytes()); // h resolution (72 DPI)
-    out.extend_from_slice(&2835u32.to_le_bytes()); // v resolution
-    out.extend_from_slice(&0u32.to_le_bytes()); // colors used
-    out.extend_from_slice(&0u32.to_le_bytes()); // important colors
-}
-
-fn get_rgb(pixels: &[u8], idx: usize, layout: PixelLayout) -> Result<(u8, u8, u8), PnmError> {
-    Ok(match layout {
-        PixelLayout::Rgb8 => {
-            let off = idx * 3;
-            (pixels[off], pixels[off + 1], pixels[off + 2])
-        }
-        PixelLayout::Bgr8 => {
-            let off = idx * 3;
-            (pixels[off + 2], pixels[off + 1], pixels[off])
-        }
-        PixelLayout::Rgba8 => {
-            let off = idx * 4;
-            (pixels[off], pixels[off + 1], pixels[off + 2])
-        }
-        PixelLayout::Bgra8 | PixelLayout::Bgrx8 => {
-            let off = idx * 4;
-            (pixels[off + 2], pixels[off + 1], pixels[off])
-        }
-        PixelLayout::Gray8 => {
-            let g = pixels[idx];
-            (g, g, g)
-        }
-        _ => {
-            return Err(PnmError::UnsupportedVariant(alloc::format!(
-                "cannot get RGB from {:?}",
-                layout
-            )));
-        }
-    })
-}
-
-fn get_rgba(pixels: &[u8], idx: usize, layout: PixelLayout) -> Result<(u8, u8, u8, u8), PnmError> {
-    Ok(match layout {
-        PixelLayout::Rgba8 => {
-            let off = idx * 4;
-            (
-                pixels[off],
-                pixels[off + 1],
-                pixels[off + 2],
-                pixels[off + 3],
-            )
-        }
-        PixelLayout::Bgra8 => {
-            let off = idx * 4;
-            (
-                pixels[off + 2],
-                pixels[off + 1],
-                pixels[off],
-                pixels[off + 3],
-            )
-        }
-        PixelLayout::Bgrx8 => {
-            let off = idx * 4;
-            (pixels[off + 2], pixels[off + 1], pixels[off], 255)
-        }
-        PixelLayout::Rgb8 => {
-            let off = idx * 3;
-            (pixels[off], pixels[off + 1], pixels[off + 2], 255)
-        }
-        PixelLayout::Bgr8 => {
-            let off = idx * 3;
-            (pixels[off + 2], pixels[off + 1], pixels[off], 255)
-        }
-        PixelLayout::Gray8 => {
-            let g = pixels[idx];
-            (g, g, g, 255)
-        }
-        _ => {
-            return Err(PnmError::UnsupportedVariant(alloc::format!(
-                "cannot get RGBA from {:?}",
-                layout
-            )));
-        }
-    })
+    header[14..18].copy_from_slice(&40u32.to_le_bytes());
+    header[18..22].copy_from_slice(&(width as i32).to_le_bytes());
+    header[22..26].copy_from_slice(&(height as i32).to_le_bytes()); // positive = bottom-up
+    header[26..28].copy_from_slice(&1u16.to_le_bytes()); // planes
+    header[28..30].copy_from_slice(&bpp.to_le_bytes());
+    // 30..34 compression = 0
+    header[34..38].copy_from_slice(&(pixel_data_size as u32).to_le_bytes());
+    header[38..42].copy_from_slice(&2835u32.to_le_bytes()); // h resolution (72 DPI)
+    header[42..46].copy_from_slice(&2835u32.to_le_bytes()); // v resolution
+    // 46..54 colors used, important colors = 0
+    header
 }
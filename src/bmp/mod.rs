@@ -5,10 +5,14 @@
 mod decode;
 mod encode;
 
-use crate::decode::DecodeOutput;
-use crate::error::PnmError;
+use crate::convert::ConvertOptions;
+use crate::decode::{DecodeOutput, SourceFormat, SourceInfo};
+use crate::downscale::{DownscaleFilter, downscaled_size};
+use crate::error::{PnmError, try_vec};
 use crate::limits::Limits;
-use crate::pixel::PixelLayout;
+use crate::orientation::Orientation;
+use crate::pixel::{PixelLayout, PixelSlice, PixelSliceMut, check_region};
+use alloc::borrow::Cow;
 use alloc::vec::Vec;
 use enough::Stop;
 
@@ -18,11 +22,7 @@ pub(crate) fn decode<'a>(
     limits: Option<&Limits>,
     stop: &dyn Stop,
 ) -> Result<DecodeOutput<'a>, PnmError> {
-    let (width, height, layout) = decode::parse_bmp_header(data)?;
-    check_limits(limits, width, height, &layout)?;
-    stop.check()?;
-    let pixels = decode::decode_bmp_pixels(data, width, height, layout, stop)?;
-    Ok(DecodeOutput::owned(pixels, width, height, layout))
+    decode_as(data, None, limits, stop)
 }
 
 /// Decode BMP data in native byte order (BGR/BGRA — no channel swizzle).
@@ -31,12 +31,195 @@ pub(crate) fn decode_native<'a>(
     limits: Option<&Limits>,
     stop: &dyn Stop,
 ) -> Result<DecodeOutput<'a>, PnmError> {
+    let header = decode::parse_bmp_header(data)?;
+    let target = Some(decode::stored_layout(header.2)?);
+    decode_owned(data, header, target, Orientation::Identity, limits, stop)
+}
+
+/// Borrow BMP pixel data in native byte order (BGR/BGRA) and stored row
+/// order, without flipping or unpadding rows.
+pub(crate) fn decode_native_view<'a>(
+    data: &'a [u8],
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let (width, height, layout) = decode::parse_bmp_header(data)?;
+    if let Some(limits) = limits {
+        limits.check(width, height)?;
+    }
+    stop.check()?;
+    decode::bmp_native_view(data, width, height, layout)
+}
+
+/// Decode BMP data into `target`, or RGB/RGBA when `None`.
+pub(crate) fn decode_as<'a>(
+    data: &'a [u8],
+    target: Option<PixelLayout>,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    decode_oriented(data, target, Orientation::Identity, limits, stop)
+}
+
+/// Decode BMP data into `target` (or RGB/RGBA) with `orientation` applied
+/// while decoding.
+pub(crate) fn decode_oriented<'a>(
+    data: &'a [u8],
+    target: Option<PixelLayout>,
+    orientation: Orientation,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let header = decode::parse_bmp_header(data)?;
+    decode_owned(data, header, target, orientation, limits, stop)
+}
+
+/// Decode BMP data with an already parsed `header` like
+/// [`decode_oriented`].
+fn decode_owned<'a>(
+    data: &[u8],
+    header: (u32, u32, PixelLayout),
+    target: Option<PixelLayout>,
+    orientation: Orientation,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let mut pixels = Vec::new();
+    let (width, height, target) =
+        decode_into_vec(data, header, target, orientation, &mut pixels, limits, stop)?;
+    Ok(DecodeOutput::owned(pixels, width, height, target, source()))
+}
+
+/// Decode BMP data like [`decode_as`] into `buf` (reusing its capacity),
+/// borrowing the pixels from there.
+pub(crate) fn decode_reusing<'a>(
+    data: &[u8],
+    target: Option<PixelLayout>,
+    buf: &'a mut Vec<u8>,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let header = decode::parse_bmp_header(data)?;
+    let identity = Orientation::Identity;
+    let (width, height, target) =
+        decode_into_vec(data, header, target, identity, buf, limits, stop)?;
+    Ok(DecodeOutput::borrowed(buf, width, height, target, source()))
+}
+
+/// Replace the contents of `buf` with the pixels of `data`, whose parsed
+/// `header` is given, returning the (oriented) dimensions and layout.
+fn decode_into_vec(
+    data: &[u8],
+    (width, height, layout): (u32, u32, PixelLayout),
+    target: Option<PixelLayout>,
+    orientation: Orientation,
+    buf: &mut Vec<u8>,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<(u32, u32, PixelLayout), PnmError> {
+    let target = target.unwrap_or(layout);
+    let (out_width, out_height) = orientation.output_size(width, height);
+    check_limits(limits, out_width, out_height, &target)?;
+    stop.check()?;
+    let size = (width, height);
+    decode::decode_bmp_as(data, size, layout, target, orientation, buf, stop)?;
+    Ok((out_width, out_height, target))
+}
+
+/// Decode BMP data into `buf` as `target`, rows `stride` bytes apart.
+///
+/// Returns the image dimensions. Never allocates.
+pub(crate) fn decode_into(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    target: PixelLayout,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<(u32, u32), PnmError> {
     let (width, height, layout) = decode::parse_bmp_header(data)?;
+    if let Some(limits) = limits {
+        limits.check(width, height)?;
+    }
+    stop.check()?;
+    let mut out = PixelSliceMut::strided(buf, width, height, stride, target)?;
+    decode::decode_bmp_into(data, layout, &mut out, stop)?;
+    Ok((width, height))
+}
+
+/// Decode the `width` x `height` region at (`x`, `y`) as RGB/RGBA, reading
+/// only the stored rows it covers.
+///
+/// `limits` apply to the region, not the full image.
+pub(crate) fn decode_region<'a>(
+    data: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let (image_width, image_height, layout) = decode::parse_bmp_header(data)?;
+    check_region(x, y, width, height, image_width, image_height)?;
     check_limits(limits, width, height, &layout)?;
     stop.check()?;
-    let (pixels, native_layout) =
-        decode::decode_bmp_pixels_native(data, width, height, layout, stop)?;
-    Ok(DecodeOutput::owned(pixels, width, height, native_layout))
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = (width as usize)
+        .checked_mul(layout.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    let out_size = row_bytes
+        .checked_mul(height as usize)
+        .ok_or_else(too_large)?;
+    let mut pixels = try_vec(0, out_size)?;
+    let mut out = PixelSliceMut::strided(&mut pixels, width, height, row_bytes, layout)?;
+    decode::decode_bmp_region_into(
+        data,
+        (image_width, image_height),
+        (x, y),
+        layout,
+        Orientation::Identity,
+        &mut out,
+        stop,
+    )?;
+    Ok(DecodeOutput::owned(pixels, width, height, layout, source()))
+}
+
+/// Decode BMP data as RGB/RGBA downscaled by `factor`.
+///
+/// `limits` apply to the downscaled image.
+pub(crate) fn decode_downscaled(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    let (image_width, image_height, layout) = decode::parse_bmp_header(data)?;
+    let (width, height) = downscaled_size(image_width, image_height, factor)?;
+    check_limits(limits, width, height, &layout)?;
+    stop.check()?;
+    let pixels = decode::decode_bmp_downscaled(
+        data,
+        image_width,
+        image_height,
+        layout,
+        factor,
+        filter,
+        stop,
+    )?;
+    Ok(DecodeOutput::owned(pixels, width, height, layout, source()))
+}
+
+/// What every BMP the decoder accepts reports: 8-bit samples.
+fn source() -> SourceInfo<'static> {
+    SourceInfo {
+        format: SourceFormat::Bmp,
+        maxval: Some(255),
+        bit_depth: 8,
+        tuple_type: None,
+        header: Cow::Borrowed(&[]),
+    }
 }
 
 fn check_limits(
@@ -48,7 +231,10 @@ fn check_limits(
     if let Some(limits) = limits {
         limits.check(width, height)?;
     }
-    let out_bytes = width as usize * height as usize * layout.bytes_per_pixel();
+    let out_bytes = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|wh| wh.checked_mul(layout.bytes_per_pixel()))
+        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
     if let Some(limits) = limits {
         limits.check_memory(out_bytes)?;
     }
@@ -57,12 +243,37 @@ fn check_limits(
 
 /// Encode to BMP.
 pub(crate) fn encode(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    layout: PixelLayout,
+    pixels: &PixelSlice<'_>,
     alpha: bool,
+    options: &ConvertOptions,
     stop: &dyn Stop,
 ) -> Result<Vec<u8>, PnmError> {
-    encode::encode_bmp(pixels, width, height, layout, alpha, stop)
+    encode::encode_bmp(pixels, alpha, options, Orientation::Identity, stop)
+}
+
+/// Encode to BMP with `orientation` applied while writing.
+pub(crate) fn encode_oriented(
+    pixels: &PixelSlice<'_>,
+    alpha: bool,
+    orientation: Orientation,
+    stop: &dyn Stop,
+) -> Result<Vec<u8>, PnmError> {
+    let options = ConvertOptions::default();
+    encode::encode_bmp(pixels, alpha, &options, orientation, stop)
+}
+
+/// Encode to BMP into a caller buffer, returning the bytes written.
+pub(crate) fn encode_into(
+    pixels: &PixelSlice<'_>,
+    alpha: bool,
+    options: &ConvertOptions,
+    out: &mut [u8],
+    stop: &dyn Stop,
+) -> Result<usize, PnmError> {
+    encode::encode_bmp_into(pixels, alpha, options, Orientation::Identity, out, stop)
+}
+
+/// Exact encoded BMP size.
+pub(crate) fn encoded_len(width: u32, height: u32, alpha: bool) -> Result<usize, PnmError> {
+    encode::encoded_len(width, height, alpha)
 }
@@ -0,0 +1,560 @@
+//! Pixel format conversion between every [`PixelLayout`].
+//!
+//! The conversion rules are documented on [`convert`], which every other
+//! entry point follows.
+
+use alloc::vec::Vec;
+
+use crate::error::{PnmError, try_vec};
+use crate::half::{f16_to_f32, f32_to_f16};
+use crate::pixel::PixelLayout;
+use crate::simd::{self, map_chunks};
+
+/// Luma coefficients used when reducing color to gray.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LumaWeights {
+    /// Rec.601 / JPEG: 0.299 R + 0.587 G + 0.114 B.
+    #[default]
+    Rec601,
+    /// Rec.709 / sRGB primaries: 0.2126 R + 0.7152 G + 0.0722 B.
+    Rec709,
+    /// BT.2020: 0.2627 R + 0.6780 G + 0.0593 B.
+    Rec2020,
+}
+
+impl LumaWeights {
+    /// Weights scaled by 10 000, summing to exactly 10 000.
+    fn integer(self) -> [u32; 3] {
+        match self {
+            Self::Rec601 => [2990, 5870, 1140],
+            Self::Rec709 => [2126, 7152, 722],
+            Self::Rec2020 => [2627, 6780, 593],
+        }
+    }
+
+    fn float(self) -> [f32; 3] {
+        self.integer().map(|k| k as f32 / 10_000.0)
+    }
+}
+
+/// What to do with alpha when the target layout has no alpha channel.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlphaPolicy {
+    /// Drop alpha and keep the color underneath as-is.
+    #[default]
+    Discard,
+    /// Composite over an opaque RGB background (8-bit, mapped to the target
+    /// range like any other sample): `c * a + bg * (1 - a)` on the stored
+    /// (straight-alpha) values.
+    Composite([u8; 3]),
+    /// Fail with [`PnmError::UnsupportedVariant`] instead of losing alpha.
+    Reject,
+}
+
+/// Options for pixel conversion, shared by [`convert_with`] and the
+/// `encode_*_with_options` functions.
+///
+/// All fields default to the plain conversion described on [`convert`].
+#[derive(Clone, Debug, Default)]
+pub struct ConvertOptions {
+    /// Luma coefficients for color → gray.
+    pub luma: LumaWeights,
+    /// Compute luminance in linear light: samples are decoded with the sRGB
+    /// transfer function, weighted, and re-encoded. Requires the
+    /// `linear-light` feature; otherwise conversion fails with
+    /// [`PnmError::UnsupportedVariant`].
+    pub linear_light: bool,
+    /// Alpha handling when converting to a layout without alpha.
+    pub alpha: AlphaPolicy,
+}
+
+impl ConvertOptions {
+    /// Reject options this build cannot honor, and alpha loss under
+    /// [`AlphaPolicy::Reject`].
+    pub(crate) fn check(
+        &self,
+        src_layout: PixelLayout,
+        dst_layout: PixelLayout,
+    ) -> Result<(), PnmError> {
+        if self.linear_light && !cfg!(feature = "linear-light") {
+            return Err(PnmError::UnsupportedVariant(
+                "linear-light luma requires the `linear-light` feature".into(),
+            ));
+        }
+        if self.alpha == AlphaPolicy::Reject && drops_alpha(src_layout, dst_layout) {
+            return Err(PnmError::UnsupportedVariant(alloc::format!(
+                "{src_layout:?} has alpha but the output is {dst_layout:?} (AlphaPolicy::Reject)"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn drops_alpha(src_layout: PixelLayout, dst_layout: PixelLayout) -> bool {
+    src_layout.has_alpha() && !dst_layout.has_alpha()
+}
+
+/// Convert pixels from `src_layout` to `dst_layout`, allocating the output.
+///
+/// `src` must hold a whole number of `src_layout` pixels.
+///
+/// - **Alpha fill**: adding an alpha channel writes opaque alpha (255, 65535
+///   or 1.0). `Bgrx8` padding is read as opaque and written as 255.
+/// - **Alpha drop**: converting to a layout without alpha discards alpha by
+///   default; [`AlphaPolicy`] can composite over a background or reject.
+/// - **Luma**: color → gray uses Rec.601 weights (0.299, 0.587, 0.114) on the
+///   encoded (gamma-compressed) values by default. 8-bit → 8-bit uses the exact
+///   integer form `(299 R + 587 G + 114 B + 500) / 1000`. [`ConvertOptions`]
+///   selects Rec.709 or BT.2020 weights and linear-light luminance.
+/// - **Premultiplied alpha**: converting from a premultiplied layout to a
+///   straight one (or one without alpha) divides color by alpha (alpha 0
+///   gives black); the reverse multiplies. Both run at `f32` precision.
+/// - **Gray → color** replicates the gray value into R, G and B.
+/// - **Range mapping**: integer samples map to `0.0..=1.0` as `v / 255` or
+///   `v / 65535`. 8 → 16 bit is `v * 257`, 16 → 8 bit rounds `v / 257`.
+///   Float → integer clamps to `0.0..=1.0` and rounds to nearest (NaN → 0).
+///   Float → float is unclamped; half floats go through `f32` exactly.
+///
+/// Multi-byte samples are native-endian, as everywhere in this crate.
+pub fn convert(
+    src: &[u8],
+    src_layout: PixelLayout,
+    dst_layout: PixelLayout,
+) -> Result<Vec<u8>, PnmError> {
+    convert_with(src, src_layout, dst_layout, &ConvertOptions::default())
+}
+
+/// Convert pixels like [`convert`], with explicit [`ConvertOptions`].
+pub fn convert_with(
+    src: &[u8],
+    src_layout: PixelLayout,
+    dst_layout: PixelLayout,
+    options: &ConvertOptions,
+) -> Result<Vec<u8>, PnmError> {
+    options.check(src_layout, dst_layout)?;
+    let count = pixel_count(src, src_layout)?;
+    let out_size = count
+        .checked_mul(dst_layout.bytes_per_pixel())
+        .ok_or_else(|| PnmError::InvalidData("converted size overflows usize".into()))?;
+    let mut out = try_vec(0u8, out_size)?;
+    convert_row(src, src_layout, &mut out, dst_layout, options);
+    Ok(out)
+}
+
+/// Convert pixels from `src_layout` into a caller-provided `dst` buffer.
+///
+/// `dst` must hold at least as many `dst_layout` pixels as `src` holds
+/// `src_layout` pixels; any excess is left untouched.
+pub fn convert_into(
+    src: &[u8],
+    src_layout: PixelLayout,
+    dst: &mut [u8],
+    dst_layout: PixelLayout,
+) -> Result<(), PnmError> {
+    let count = pixel_count(src, src_layout)?;
+    let needed = count
+        .checked_mul(dst_layout.bytes_per_pixel())
+        .ok_or_else(|| PnmError::InvalidData("converted size overflows usize".into()))?;
+    if dst.len() < needed {
+        return Err(PnmError::BufferTooSmall {
+            needed,
+            actual: dst.len(),
+        });
+    }
+    convert_row(
+        src,
+        src_layout,
+        &mut dst[..needed],
+        dst_layout,
+        &ConvertOptions::default(),
+    );
+    Ok(())
+}
+
+/// Convert pixels in place between two layouts of the same pixel size,
+/// e.g. `Rgb8` ↔ `Bgr8`, `Rgba8` ↔ `Bgra8`, `Bgra8` → `Bgrx8` or
+/// `Gray16` → `GrayF16`.
+pub fn convert_in_place(
+    buf: &mut [u8],
+    src_layout: PixelLayout,
+    dst_layout: PixelLayout,
+) -> Result<(), PnmError> {
+    let bpp = src_layout.bytes_per_pixel();
+    if dst_layout.bytes_per_pixel() != bpp {
+        return Err(PnmError::UnsupportedVariant(alloc::format!(
+            "in-place conversion needs equal pixel sizes, {src_layout:?} is {bpp} bytes \
+             and {dst_layout:?} is {} bytes",
+            dst_layout.bytes_per_pixel()
+        )));
+    }
+    pixel_count(buf, src_layout)?;
+    if src_layout == dst_layout {
+        return Ok(());
+    }
+    let options = ConvertOptions::default();
+    let mut tmp = [0u8; 16];
+    for px in buf.chunks_exact_mut(bpp) {
+        tmp[..bpp].copy_from_slice(px);
+        convert_row(&tmp[..bpp], src_layout, px, dst_layout, &options);
+    }
+    Ok(())
+}
+
+fn pixel_count(src: &[u8], layout: PixelLayout) -> Result<usize, PnmError> {
+    let bpp = layout.bytes_per_pixel();
+    if src.len() % bpp != 0 {
+        return Err(PnmError::InvalidData(alloc::format!(
+            "{} bytes is not a whole number of {layout:?} pixels",
+            src.len()
+        )));
+    }
+    Ok(src.len() / bpp)
+}
+
+/// Convert `dst.len() / dst_bpp` pixels from `src` into `dst`.
+///
+/// Callers guarantee `src` holds at least that many pixels and have run
+/// [`ConvertOptions::check`].
+pub(crate) fn convert_row(
+    src: &[u8],
+    src_layout: PixelLayout,
+    dst: &mut [u8],
+    dst_layout: PixelLayout,
+    options: &ConvertOptions,
+) {
+    if src_layout == dst_layout {
+        dst.copy_from_slice(&src[..dst.len()]);
+        return;
+    }
+    let (src_order, src_depth) = describe(src_layout);
+    let (dst_order, dst_depth) = describe(dst_layout);
+    let src_bpp = src_layout.bytes_per_pixel();
+    let dst_bpp = dst_layout.bytes_per_pixel();
+    let luma = if src_order == Order::Gray {
+        Luma::Passthrough
+    } else if options.linear_light {
+        Luma::Linear(options.luma.float())
+    } else {
+        Luma::Weighted(options.luma)
+    };
+
+    let background = match options.alpha {
+        AlphaPolicy::Composite(bg) if drops_alpha(src_layout, dst_layout) => Some(bg),
+        _ => None,
+    };
+    let unpremultiply = src_layout.is_premultiplied() && !dst_layout.is_premultiplied();
+    let premultiply = dst_layout.is_premultiplied() && !src_layout.is_premultiplied();
+
+    if src_depth == Depth::U8
+        && dst_depth == Depth::U8
+        && !options.linear_light
+        && !unpremultiply
+        && !premultiply
+    {
+        if background.is_none() && convert_u8_fast(src, src_order, dst, dst_order, luma) {
+            return;
+        }
+        for (s, d) in src.chunks_exact(src_bpp).zip(dst.chunks_exact_mut(dst_bpp)) {
+            let mut px = read_u8(s, src_order);
+            if let Some(bg) = background {
+                px = composite_u8(px, bg);
+            }
+            write_u8(d, dst_order, px, luma);
+        }
+    } else {
+        let background = background.map(|bg| bg.map(|c| f32::from(c) / 255.0));
+        for (s, d) in src.chunks_exact(src_bpp).zip(dst.chunks_exact_mut(dst_bpp)) {
+            let mut px = read_f32(s, src_order, src_depth);
+            if unpremultiply {
+                px = unpremultiply_f32(px);
+            }
+            if let Some(bg) = background {
+                px = composite_f32(px, bg);
+            }
+            if premultiply {
+                px = premultiply_f32(px);
+            }
+            write_f32(d, dst_order, dst_depth, px, luma);
+        }
+    }
+}
+
+/// Vectorizable kernels for the common 8-bit swizzles, alpha fill/drop,
+/// gray replication and integer luma. Returns `false` for pairs without
+/// one, which take the per-pixel path; results are identical either way.
+fn convert_u8_fast(
+    src: &[u8],
+    src_order: Order,
+    dst: &mut [u8],
+    dst_order: Order,
+    luma: Luma,
+) -> bool {
+    use Order::{Bgr, Bgra, Bgrx, Gray, Rgb, Rgba};
+    let k = match luma {
+        Luma::Weighted(w) => w.integer(),
+        // Gray sources need no weights
+        Luma::Passthrough => [0; 3],
+        Luma::Linear(_) => return false,
+    };
+    simd::dispatch(|| {
+        match (src_order, dst_order) {
+            (Rgb, Bgr) | (Bgr, Rgb) => map_chunks::<3, 3>(src, dst, |[a, b, c]| [c, b, a]),
+            (Rgba, Bgra) | (Bgra, Rgba) => {
+                map_chunks::<4, 4>(src, dst, |[a, b, c, d]| [c, b, a, d])
+            }
+            (Bgra, Bgrx) | (Bgrx, Bgra) => {
+                map_chunks::<4, 4>(src, dst, |[a, b, c, _]| [a, b, c, 255])
+            }
+            (Rgba, Bgrx) | (Bgrx, Rgba) => {
+                map_chunks::<4, 4>(src, dst, |[a, b, c, _]| [c, b, a, 255])
+            }
+            (Rgb, Rgba) | (Bgr, Bgra | Bgrx) => {
+                map_chunks::<3, 4>(src, dst, |[a, b, c]| [a, b, c, 255])
+            }
+            (Rgb, Bgra | Bgrx) | (Bgr, Rgba) => {
+                map_chunks::<3, 4>(src, dst, |[a, b, c]| [c, b, a, 255])
+            }
+            (Rgba, Rgb) | (Bgra | Bgrx, Bgr) => {
+                map_chunks::<4, 3>(src, dst, |[a, b, c, _]| [a, b, c])
+            }
+            (Rgba, Bgr) | (Bgra | Bgrx, Rgb) => {
+                map_chunks::<4, 3>(src, dst, |[a, b, c, _]| [c, b, a])
+            }
+            (Gray, Rgb | Bgr) => map_chunks::<1, 3>(src, dst, |[v]| [v, v, v]),
+            (Gray, Rgba | Bgra | Bgrx) => map_chunks::<1, 4>(src, dst, |[v]| [v, v, v, 255]),
+            (Rgb, Gray) => map_chunks::<3, 1>(src, dst, |[r, g, b]| [luma_u8(r, g, b, k)]),
+            (Bgr, Gray) => map_chunks::<3, 1>(src, dst, |[b, g, r]| [luma_u8(r, g, b, k)]),
+            (Rgba, Gray) => map_chunks::<4, 1>(src, dst, |[r, g, b, _]| [luma_u8(r, g, b, k)]),
+            (Bgra | Bgrx, Gray) => {
+                map_chunks::<4, 1>(src, dst, |[b, g, r, _]| [luma_u8(r, g, b, k)])
+            }
+            _ => return false,
+        }
+        true
+    })
+}
+
+/// Integer luma with weights scaled by 10 000, rounded to nearest.
+#[inline(always)]
+fn luma_u8(r: u8, g: u8, b: u8, [kr, kg, kb]: [u32; 3]) -> u8 {
+    let (r, g, b) = (u32::from(r), u32::from(g), u32::from(b));
+    ((r * kr + g * kg + b * kb + 5000) / 10_000) as u8
+}
+
+/// How a gray output sample is derived from R, G and B.
+#[derive(Clone, Copy)]
+enum Luma {
+    /// Source is already gray (R = G = B).
+    Passthrough,
+    /// Weighted sum of the stored values.
+    Weighted(LumaWeights),
+    /// Weighted sum in linear light, re-encoded to sRGB.
+    Linear([f32; 3]),
+}
+
+impl Luma {
+    fn apply(self, r: f32, g: f32, b: f32) -> f32 {
+        match self {
+            Luma::Passthrough => r,
+            Luma::Weighted(w) => {
+                let [kr, kg, kb] = w.float();
+                kr * r + kg * g + kb * b
+            }
+            Luma::Linear([kr, kg, kb]) => {
+                let y = kr * to_linear(r) + kg * to_linear(g) + kb * to_linear(b);
+                from_linear(y)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "linear-light")]
+fn to_linear(v: f32) -> f32 {
+    linear_srgb::default::srgb_to_linear(v)
+}
+
+#[cfg(feature = "linear-light")]
+fn from_linear(v: f32) -> f32 {
+    linear_srgb::default::linear_to_srgb(v)
+}
+
+// Unreachable: `ConvertOptions::check` rejects linear light without the feature.
+#[cfg(not(feature = "linear-light"))]
+fn to_linear(v: f32) -> f32 {
+    v
+}
+
+#[cfg(not(feature = "linear-light"))]
+fn from_linear(v: f32) -> f32 {
+    v
+}
+
+/// Channel order of a layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Order {
+    Gray,
+    Rgb,
+    Rgba,
+    Bgr,
+    Bgra,
+    Bgrx,
+}
+
+/// Sample type of a layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Depth {
+    U8,
+    U16,
+    F16,
+    F32,
+}
+
+fn describe(layout: PixelLayout) -> (Order, Depth) {
+    match layout {
+        PixelLayout::Gray8 => (Order::Gray, Depth::U8),
+        PixelLayout::Gray16 => (Order::Gray, Depth::U16),
+        PixelLayout::Rgb8 => (Order::Rgb, Depth::U8),
+        PixelLayout::Rgba8 => (Order::Rgba, Depth::U8),
+        PixelLayout::Rgb16 => (Order::Rgb, Depth::U16),
+        PixelLayout::Rgba16 => (Order::Rgba, Depth::U16),
+        PixelLayout::Bgr8 => (Order::Bgr, Depth::U8),
+        PixelLayout::Bgra8 => (Order::Bgra, Depth::U8),
+        PixelLayout::Bgrx8 => (Order::Bgrx, Depth::U8),
+        PixelLayout::GrayF32 => (Order::Gray, Depth::F32),
+        PixelLayout::RgbF32 => (Order::Rgb, Depth::F32),
+        PixelLayout::RgbaF32 => (Order::Rgba, Depth::F32),
+        PixelLayout::GrayF16 => (Order::Gray, Depth::F16),
+        PixelLayout::RgbF16 => (Order::Rgb, Depth::F16),
+        PixelLayout::RgbaF16 => (Order::Rgba, Depth::F16),
+        PixelLayout::Rgba8Premul => (Order::Rgba, Depth::U8),
+        PixelLayout::Bgra8Premul => (Order::Bgra, Depth::U8),
+        PixelLayout::Rgba16Premul => (Order::Rgba, Depth::U16),
+        PixelLayout::RgbaF32Premul => (Order::Rgba, Depth::F32),
+    }
+}
+
+impl Order {
+    /// Sample index of R, G, B and (if present) A within a pixel.
+    fn positions(self) -> ([usize; 3], Option<usize>) {
+        match self {
+            Order::Gray => ([0, 0, 0], None),
+            Order::Rgb => ([0, 1, 2], None),
+            Order::Rgba => ([0, 1, 2], Some(3)),
+            Order::Bgr => ([2, 1, 0], None),
+            Order::Bgra => ([2, 1, 0], Some(3)),
+            // Padding is not alpha: read as opaque, written as 255
+            Order::Bgrx => ([2, 1, 0], None),
+        }
+    }
+}
+
+fn read_u8(px: &[u8], order: Order) -> [u8; 4] {
+    let ([r, g, b], a) = order.positions();
+    [px[r], px[g], px[b], a.map_or(255, |a| px[a])]
+}
+
+fn write_u8(px: &mut [u8], order: Order, [r, g, b, a]: [u8; 4], luma: Luma) {
+    match order {
+        Order::Gray => {
+            px[0] = match luma {
+                Luma::Weighted(w) => luma_u8(r, g, b, w.integer()),
+                // Linear light never takes the 8-bit path
+                Luma::Passthrough | Luma::Linear(_) => r,
+            };
+        }
+        Order::Rgb => px.copy_from_slice(&[r, g, b]),
+        Order::Rgba => px.copy_from_slice(&[r, g, b, a]),
+        Order::Bgr => px.copy_from_slice(&[b, g, r]),
+        Order::Bgra => px.copy_from_slice(&[b, g, r, a]),
+        Order::Bgrx => px.copy_from_slice(&[b, g, r, 255]),
+    }
+}
+
+fn composite_u8([r, g, b, a]: [u8; 4], bg: [u8; 3]) -> [u8; 4] {
+    let a16 = u16::from(a);
+    let blend =
+        |c: u8, bg: u8| ((u16::from(c) * a16 + u16::from(bg) * (255 - a16) + 127) / 255) as u8;
+    [blend(r, bg[0]), blend(g, bg[1]), blend(b, bg[2]), 255]
+}
+
+fn composite_f32([r, g, b, a]: [f32; 4], bg: [f32; 3]) -> [f32; 4] {
+    let blend = |c: f32, bg: f32| c * a + bg * (1.0 - a);
+    [blend(r, bg[0]), blend(g, bg[1]), blend(b, bg[2]), 1.0]
+}
+
+fn premultiply_f32([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    [r * a, g * a, b * a, a]
+}
+
+fn unpremultiply_f32([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    if a == 0.0 {
+        [0.0, 0.0, 0.0, 0.0]
+    } else {
+        [r / a, g / a, b / a, a]
+    }
+}
+
+fn read_sample(px: &[u8], depth: Depth, idx: usize) -> f32 {
+    match depth {
+        Depth::U8 => f32::from(px[idx]) / 255.0,
+        Depth::U16 => {
+            let o = idx * 2;
+            f32::from(u16::from_ne_bytes([px[o], px[o + 1]])) / 65535.0
+        }
+        Depth::F16 => {
+            let o = idx * 2;
+            f16_to_f32(u16::from_ne_bytes([px[o], px[o + 1]]))
+        }
+        Depth::F32 => {
+            let o = idx * 4;
+            f32::from_ne_bytes([px[o], px[o + 1], px[o + 2], px[o + 3]])
+        }
+    }
+}
+
+fn write_sample(px: &mut [u8], depth: Depth, idx: usize, v: f32) {
+    match depth {
+        Depth::U8 => px[idx] = (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+        Depth::U16 => {
+            let o = idx * 2;
+            let q = (v.clamp(0.0, 1.0) * 65535.0 + 0.5) as u16;
+            px[o..o + 2].copy_from_slice(&q.to_ne_bytes());
+        }
+        Depth::F16 => {
+            let o = idx * 2;
+            px[o..o + 2].copy_from_slice(&f32_to_f16(v).to_ne_bytes());
+        }
+        Depth::F32 => {
+            let o = idx * 4;
+            px[o..o + 4].copy_from_slice(&v.to_ne_bytes());
+        }
+    }
+}
+
+fn read_f32(px: &[u8], order: Order, depth: Depth) -> [f32; 4] {
+    let ([r, g, b], a) = order.positions();
+    [
+        read_sample(px, depth, r),
+        read_sample(px, depth, g),
+        read_sample(px, depth, b),
+        a.map_or(1.0, |a| read_sample(px, depth, a)),
+    ]
+}
+
+fn write_f32(px: &mut [u8], order: Order, depth: Depth, [r, g, b, a]: [f32; 4], luma: Luma) {
+    if order == Order::Gray {
+        write_sample(px, depth, 0, luma.apply(r, g, b));
+        return;
+    }
+    let ([ri, gi, bi], ai) = order.positions();
+    write_sample(px, depth, ri, r);
+    write_sample(px, depth, gi, g);
+    write_sample(px, depth, bi, b);
+    if let Some(ai) = ai {
+        write_sample(px, depth, ai, a);
+    } else if order == Order::Bgrx {
+        write_sample(px, depth, 3, 1.0);
+    }
+}
@@ -1,15 +1,246 @@
 use alloc::borrow::Cow;
+use alloc::string::String;
 use alloc::vec::Vec;
 
-#[cfg(feature = "rgb")]
-use rgb::AsPixels as _;
+use core::fmt;
 
+use crate::limits::Limits;
 use crate::pixel::PixelLayout;
 
+/// The format a [`DecodeOutput`] was decoded from.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceFormat {
+    /// P5 (PGM).
+    Pgm,
+    /// P6 (PPM).
+    Ppm,
+    /// P7 (PAM).
+    Pam,
+    /// PFM, with 32-bit (`Pf`/`PF`) or 16-bit (`Ph`/`PH`) samples.
+    Pfm,
+    /// BMP.
+    #[cfg(feature = "basic-bmp")]
+    Bmp,
+}
+
+/// What the file header said about the decoded image, whatever layout it was
+/// decoded into.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceInfo<'a> {
+    /// The file format.
+    pub format: SourceFormat,
+    /// Largest sample value of integer samples: the PNM maxval, or 255 for
+    /// BMP. `None` for floating-point samples.
+    pub maxval: Option<u32>,
+    /// Bits per stored sample: enough bits for `maxval` (e.g. 8 for 255, 7
+    /// for 100), or 16/32 for floating-point samples.
+    pub bit_depth: u8,
+    /// PAM `TUPLTYPE`: the values of every `TUPLTYPE` line joined by
+    /// spaces, borrowed from the header when there is just one.
+    pub tuple_type: Option<Cow<'a, str>>,
+    /// The PNM header bytes, comments included. Empty for BMP.
+    pub(crate) header: Cow<'a, [u8]>,
+}
+
+impl SourceInfo<'_> {
+    /// The `#` comments of a PNM header, in order, without the `#`, one
+    /// following space, or the line ending.
+    ///
+    /// Comments are read from the header bytes on demand, so capturing them
+    /// costs nothing. Bytes that are not UTF-8 are replaced.
+    pub fn comments(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        crate::pnm::decode::header_comments(&self.header, self.format == SourceFormat::Pam)
+            .map(String::from_utf8_lossy)
+    }
+
+    /// The lines of a PAM header other than `WIDTH`, `HEIGHT`, `DEPTH`,
+    /// `MAXVAL` and `TUPLTYPE`, in order, as `(keyword, value)` with the
+    /// value trimmed. Empty for the other formats.
+    pub fn pam_fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        let header: &[u8] = match self.format {
+            SourceFormat::Pam => &self.header,
+            _ => &[],
+        };
+        crate::pnm::decode::pam_lines(header).filter(|(keyword, _)| {
+            !matches!(
+                *keyword,
+                "WIDTH" | "HEIGHT" | "DEPTH" | "MAXVAL" | "TUPLTYPE"
+            )
+        })
+    }
+
+    /// Copy anything borrowed from the input.
+    pub fn into_owned(self) -> SourceInfo<'static> {
+        SourceInfo {
+            format: self.format,
+            maxval: self.maxval,
+            bit_depth: self.bit_depth,
+            tuple_type: self.tuple_type.map(|t| Cow::Owned(t.into_owned())),
+            header: Cow::Owned(self.header.into_owned()),
+        }
+    }
+}
+
+/// A PNM header, as [`crate::probe`] reads it without decoding any pixels.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderInfo<'a> {
+    pub width: u32,
+    pub height: u32,
+    /// The layout [`crate::decode`] returns.
+    pub layout: PixelLayout,
+    /// What else the header says.
+    pub source: SourceInfo<'a>,
+}
+
+/// How strictly [`crate::decode_with_options`] holds a file to its spec.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Decode anything the plain decode functions accept.
+    #[default]
+    Lenient,
+    /// Fail with [`crate::PnmError::Nonconforming`] on the first [`Deviation`]
+    /// [`crate::validate`] reports.
+    Strict,
+}
+
+/// What the PNM `_with_options` decode functions do with integer samples
+/// larger than the file's maxval. The other decode functions clamp them.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplePolicy {
+    /// Read them as maxval, the top of the output range.
+    #[default]
+    Clamp,
+    /// Fail with [`crate::PnmError::SampleOutOfRange`] at the first one.
+    Error,
+    /// Skip maxval scaling and return every sample as stored, out-of-range
+    /// ones included; [`SourceInfo::maxval`] still gives the intended range.
+    PassThrough,
+}
+
+/// Options for [`crate::decode_with_options`] and the other PNM
+/// `_with_options` decode functions.
+///
+/// Start from [`Default`] and set what you need with the `with_*` methods.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+    /// How strictly the file is held to its spec; lenient by default.
+    pub strictness: Strictness,
+    /// Handling of samples above maxval.
+    pub samples: SamplePolicy,
+    /// Resource limits; the default imposes none.
+    pub limits: Limits,
+}
+
+impl DecodeOptions {
+    /// Set [`strictness`](Self::strictness).
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Set the [`samples`](Self::samples) policy.
+    pub fn with_samples(mut self, samples: SamplePolicy) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Set the resource [`limits`](Self::limits).
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+/// A way a PNM file departs from its format spec, as [`crate::validate`]
+/// reports it. Offsets count bytes from the start of the file.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Deviation {
+    /// Whitespace the spec does not allow at `offset`: a P5/P6 header
+    /// ending in something other than a single whitespace byte, padding or
+    /// a carriage return on a PAM header line, or a carriage return in a
+    /// PFM header.
+    UnusualWhitespace { offset: usize },
+    /// A PAM header line at `offset` whose keyword PAM does not define.
+    UnknownPamField { offset: usize },
+    /// A maxval outside 1-65535. Decoding rejects such files.
+    MaxvalOutOfRange { maxval: u32 },
+    /// The first sample larger than the header's maxval, at column `x`,
+    /// row `y` of the image.
+    SampleExceedsMaxval {
+        x: u32,
+        y: u32,
+        channel: u32,
+        value: u32,
+    },
+    /// The pixel data ends after `rows` of the `expected` complete rows.
+    TruncatedRows { rows: u32, expected: u32 },
+    /// `bytes` of data after the last row.
+    TrailingData { bytes: usize },
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnusualWhitespace { offset } => {
+                write!(f, "unusual whitespace at byte {offset}")
+            }
+            Self::UnknownPamField { offset } => {
+                write!(f, "unknown PAM header field at byte {offset}")
+            }
+            Self::MaxvalOutOfRange { maxval } => {
+                write!(f, "maxval {maxval} outside 1-65535")
+            }
+            Self::SampleExceedsMaxval {
+                x,
+                y,
+                channel,
+                value,
+            } => write!(
+                f,
+                "sample {value} at ({x}, {y}) channel {channel} exceeds maxval"
+            ),
+            Self::TruncatedRows { rows, expected } => {
+                write!(f, "pixel data ends after {rows} of {expected} rows")
+            }
+            Self::TrailingData { bytes } => {
+                write!(f, "{bytes} bytes of data after the last row")
+            }
+        }
+    }
+}
+
+/// Order of the rows in [`DecodeOutput::pixels`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RowOrder {
+    /// The first row in memory is the top of the image.
+    #[default]
+    TopDown,
+    /// The first row in memory is the bottom of the image, as BMP and PFM
+    /// store them.
+    BottomUp,
+}
+
 /// Decoded image output. Pixels may be borrowed (zero-copy) or owned.
+///
+/// Rows are [`stride`](Self::stride) bytes apart. That is the packed row
+/// size for everything except zero-copy [`crate::decode_region`] output,
+/// which borrows its rows from the full image, and padded BMP views. Rows
+/// run top-down except for the zero-copy views of [`crate::decode_view`]
+/// and `decode_bmp_native_view`, whose [`row_order`](Self::row_order) may be
+/// [`RowOrder::BottomUp`]; [`row`](Self::row) hides the difference.
 #[derive(Clone, Debug)]
 pub struct DecodeOutput<'a> {
     pixels: Cow<'a, [u8]>,
+    stride: usize,
+    row_order: RowOrder,
+    source: SourceInfo<'a>,
     pub width: u32,
     pub height: u32,
     pub layout: PixelLayout,
@@ -17,17 +248,90 @@ pub struct DecodeOutput<'a> {
 
 impl<'a> DecodeOutput<'a> {
     /// Access the pixel data.
+    ///
+    /// For strided output the slice runs from the first pixel of the first
+    /// row in memory to the last pixel of the last one, with the rest of
+    /// each source row in between. Bottom-up output starts with the bottom
+    /// row.
     pub fn pixels(&self) -> &[u8] {
         &self.pixels
     }
 
-    /// Take ownership of the pixel data (copies if borrowed).
+    /// Bytes from the start of one row in memory to the start of the next.
+    ///
+    /// For [`RowOrder::BottomUp`] output, image row `y + 1` starts `stride`
+    /// bytes *before* row `y`.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Order of the rows in [`pixels`](Self::pixels).
+    pub fn row_order(&self) -> RowOrder {
+        self.row_order
+    }
+
+    /// Row `y` of the image, counted from the top whatever the row order,
+    /// without stride padding. `None` if `y` is out of range.
+    pub fn row(&self, y: usize) -> Option<&[u8]> {
+        let height = self.height as usize;
+        if y >= height {
+            return None;
+        }
+        let stored = match self.row_order {
+            RowOrder::TopDown => y,
+            RowOrder::BottomUp => height - 1 - y,
+        };
+        let start = stored.checked_mul(self.stride)?;
+        self.pixels.get(start..start.checked_add(self.row_bytes())?)
+    }
+
+    /// Iterate over the rows of the image from the top, as [`row`](Self::row)
+    /// returns them.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[u8]> + ExactSizeIterator {
+        (0..self.height as usize).map(|y| self.row(y).unwrap_or_default())
+    }
+
+    /// What the file header said about the image.
+    pub fn source(&self) -> &SourceInfo<'a> {
+        &self.source
+    }
+
+    /// Take ownership of the pixel data (copies if borrowed). Strided and
+    /// bottom-up rows are packed top-down.
     pub fn into_owned(self) -> DecodeOutput<'static> {
-        DecodeOutput {
-            pixels: Cow::Owned(self.pixels.into_owned()),
-            width: self.width,
-            height: self.height,
-            layout: self.layout,
+        let (width, height, layout) = (self.width, self.height, self.layout);
+        let source = self.source.clone().into_owned();
+        let pixels = if self.is_packed() {
+            self.pixels.into_owned()
+        } else {
+            let mut pixels = Vec::with_capacity(self.row_bytes() * height as usize);
+            for row in self.rows() {
+                pixels.extend_from_slice(row);
+            }
+            pixels
+        };
+        DecodeOutput::owned(pixels, width, height, layout, source)
+    }
+
+    /// Like [`into_owned`](Self::into_owned), but returns
+    /// [`crate::PnmError::AllocationFailed`] instead of aborting when the
+    /// copy cannot be allocated.
+    pub fn try_into_owned(self) -> Result<DecodeOutput<'static>, crate::PnmError> {
+        let (width, height, layout) = (self.width, self.height, self.layout);
+        let source = self.source.clone().into_owned();
+        let pixels = self.into_vec()?;
+        Ok(DecodeOutput::owned(pixels, width, height, layout, source))
+    }
+
+    /// The pixels as packed top-down rows. Owned packed data is returned as
+    /// is; anything else is copied.
+    ///
+    /// Returns [`crate::PnmError::AllocationFailed`] if the copy cannot be
+    /// allocated.
+    pub fn into_vec(self) -> Result<Vec<u8>, crate::PnmError> {
+        match self.pixels {
+            Cow::Owned(pixels) if self.is_packed() => Ok(pixels),
+            _ => Ok(self.packed()?.into_owned()),
         }
     }
 
@@ -36,54 +340,212 @@ impl<'a> DecodeOutput<'a> {
         matches!(self.pixels, Cow::Borrowed(_))
     }
 
-    pub(crate) fn borrowed(data: &'a [u8], width: u32, height: u32, layout: PixelLayout) -> Self {
+    /// Premultiply color by alpha, switching to the matching premultiplied
+    /// layout (e.g. `Rgba8` → `Rgba8Premul`).
+    ///
+    /// Layouts without alpha, already-premultiplied layouts and `RgbaF16`
+    /// (which has no premultiplied counterpart) are returned unchanged,
+    /// still borrowed if they were.
+    ///
+    /// Returns [`crate::PnmError::AllocationFailed`] if the premultiplied
+    /// pixels cannot be allocated.
+    pub fn into_premultiplied(self) -> Result<DecodeOutput<'a>, crate::PnmError> {
+        let target = match self.layout.premultiplied() {
+            Some(target) if target != self.layout => target,
+            _ => return Ok(self),
+        };
+        let packed = self.packed()?;
+        let mut out = crate::error::try_vec(0u8, packed.len())?;
+        crate::convert::convert_row(
+            &packed,
+            self.layout,
+            &mut out,
+            target,
+            &crate::convert::ConvertOptions::default(),
+        );
+        Ok(Self::owned(
+            out,
+            self.width,
+            self.height,
+            target,
+            self.source,
+        ))
+    }
+
+    pub(crate) fn borrowed(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        layout: PixelLayout,
+        source: SourceInfo<'a>,
+    ) -> Self {
+        let stride = width as usize * layout.bytes_per_pixel();
+        Self::borrowed_strided(data, width, height, stride, layout, source)
+    }
+
+    /// Borrowed rows `stride` bytes apart; `data` ends with the last row.
+    pub(crate) fn borrowed_strided(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        layout: PixelLayout,
+        source: SourceInfo<'a>,
+    ) -> Self {
         Self {
             pixels: Cow::Borrowed(data),
+            stride,
+            row_order: RowOrder::TopDown,
+            source,
             width,
             height,
             layout,
         }
     }
 
-    pub(crate) fn owned(data: Vec<u8>, width: u32, height: u32, layout: PixelLayout) -> Self {
+    pub(crate) fn owned(
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+        layout: PixelLayout,
+        source: SourceInfo<'a>,
+    ) -> Self {
         Self {
             pixels: Cow::Owned(data),
+            stride: width as usize * layout.bytes_per_pixel(),
+            row_order: RowOrder::TopDown,
+            source,
             width,
             height,
             layout,
         }
     }
 
+    /// Mark the rows as stored bottom-up.
+    pub(crate) fn bottom_up(mut self) -> Self {
+        self.row_order = RowOrder::BottomUp;
+        self
+    }
+
+    fn row_bytes(&self) -> usize {
+        self.width as usize * self.layout.bytes_per_pixel()
+    }
+
+    /// Whether the pixels are top-down rows packed back to back.
+    fn is_packed(&self) -> bool {
+        self.stride == self.row_bytes() && self.row_order == RowOrder::TopDown
+    }
+
+    /// The pixels as packed top-down rows (borrowed if already packed).
+    fn packed(&self) -> Result<Cow<'_, [u8]>, crate::PnmError> {
+        if self.is_packed() {
+            return Ok(Cow::Borrowed(&self.pixels));
+        }
+        let row_bytes = self.row_bytes();
+        let mut out = crate::error::try_vec(0u8, row_bytes * self.height as usize)?;
+        for y in 0..self.height as usize {
+            if let Some(src) = self.row(y) {
+                out[y * row_bytes..][..row_bytes].copy_from_slice(src);
+            }
+        }
+        Ok(Cow::Owned(out))
+    }
+
     /// Reinterpret pixel data as typed pixel slice.
     ///
+    /// Returns [`crate::PnmError::LayoutMismatch`] if the pixel layout doesn't match `P`,
+    /// or [`crate::PnmError::InvalidData`] if borrowed data is not aligned for a
+    /// multi-byte sample type or its rows are strided or bottom-up (use
+    /// [`to_imgvec`](Self::to_imgvec) to copy instead).
+    #[cfg(feature = "rgb")]
+    pub fn as_pixels<P: crate::DecodePixel>(&self) -> Result<&[P], crate::PnmError> {
+        self.check_layout::<P>()?;
+        if !self.is_packed() {
+            return Err(crate::PnmError::InvalidData(
+                "pixel rows are strided or bottom-up, not a contiguous slice".into(),
+            ));
+        }
+        rgb::bytemuck::try_cast_slice(self.pixels()).map_err(|_| {
+            crate::PnmError::InvalidData(alloc::format!(
+                "pixel data is not aligned for {:?}",
+                P::layout()
+            ))
+        })
+    }
+
+    /// Convert to typed pixels, borrowing from the input when possible.
+    ///
+    /// Zero-copy output stays borrowed as long as it is aligned for `P`,
+    /// which 8-bit pixel types always are. Owned, misaligned, strided or
+    /// bottom-up data is copied (as packed top-down rows).
+    ///
     /// Returns [`crate::PnmError::LayoutMismatch`] if the pixel layout doesn't match `P`.
     #[cfg(feature = "rgb")]
-    pub fn as_pixels<P: crate::DecodePixel>(&self) -> Result<&[P], crate::PnmError>
-    where
-        [u8]: rgb::AsPixels<P>,
-    {
-        if !self.layout.is_memory_compatible(P::layout()) {
-            return Err(crate::PnmError::LayoutMismatch {
-                expected: P::layout(),
-                actual: self.layout,
-            });
+    pub fn into_pixels<P: crate::DecodePixel>(self) -> Result<Cow<'a, [P]>, crate::PnmError> {
+        self.check_layout::<P>()?;
+        if !self.is_packed() {
+            return Ok(Cow::Owned(crate::pixel_traits::pixels_from_bytes(
+                &self.packed()?,
+            )?));
+        }
+        Ok(match self.pixels {
+            Cow::Borrowed(bytes) => match rgb::bytemuck::try_cast_slice(bytes) {
+                Ok(pixels) => Cow::Borrowed(pixels),
+                Err(_) => Cow::Owned(crate::pixel_traits::pixels_from_bytes(bytes)?),
+            },
+            Cow::Owned(bytes) => Cow::Owned(crate::pixel_traits::pixels_from_bytes(&bytes)?),
+        })
+    }
+
+    /// Convert to an [`imgref::Img`] over [`into_pixels`](Self::into_pixels),
+    /// borrowing from the input when possible.
+    ///
+    /// Strided zero-copy output (from [`crate::decode_region`]) stays
+    /// borrowed too, as an image with the source stride.
+    /// [`imgref::ImgExt::as_ref`] on the result gives an [`imgref::ImgRef`].
+    #[cfg(feature = "imgref")]
+    pub fn into_img<P: crate::DecodePixel>(
+        self,
+    ) -> Result<imgref::Img<Cow<'a, [P]>>, crate::PnmError> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if self.stride != self.row_bytes() && self.row_order == RowOrder::TopDown {
+            if let Cow::Borrowed(bytes) = self.pixels {
+                self.check_layout::<P>()?;
+                if let Ok(pixels) = rgb::bytemuck::try_cast_slice(bytes) {
+                    let stride = self.stride / core::mem::size_of::<P>();
+                    return Ok(imgref::Img::new_stride(
+                        Cow::Borrowed(pixels),
+                        width,
+                        height,
+                        stride,
+                    ));
+                }
+            }
         }
-        Ok(self.pixels().as_pixels())
+        Ok(imgref::Img::new(self.into_pixels()?, width, height))
     }
 
     /// Convert to an [`imgref::ImgVec`] of typed pixels.
     ///
     /// Returns [`crate::PnmError::LayoutMismatch`] if the pixel layout doesn't match `P`.
     #[cfg(feature = "imgref")]
-    pub fn to_imgvec<P: crate::DecodePixel>(&self) -> Result<imgref::ImgVec<P>, crate::PnmError>
-    where
-        [u8]: rgb::AsPixels<P>,
-    {
-        let pixels: &[P] = self.as_pixels()?;
+    pub fn to_imgvec<P: crate::DecodePixel>(&self) -> Result<imgref::ImgVec<P>, crate::PnmError> {
+        self.check_layout::<P>()?;
         Ok(imgref::ImgVec::new(
-            pixels.to_vec(),
+            crate::pixel_traits::pixels_from_bytes(&self.packed()?)?,
             self.width as usize,
             self.height as usize,
         ))
     }
+
+    #[cfg(feature = "rgb")]
+    fn check_layout<P: crate::DecodePixel>(&self) -> Result<(), crate::PnmError> {
+        if !self.layout.is_memory_compatible(P::layout()) {
+            return Err(crate::PnmError::LayoutMismatch {
+                expected: P::layout(),
+                actual: self.layout,
+            });
+        }
+        Ok(())
+    }
 }
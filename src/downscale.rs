@@ -0,0 +1,183 @@
+//! Integer-factor downscaling during decode.
+//!
+//! Source rows are decoded one at a time, in the image's own layout, and
+//! folded into the output row they belong to. Peak memory is one source row,
+//! one row of sums and the output image, however large the source is.
+//!
+//! Box sums run in `f64`, which is exact for integer samples. Color is
+//! weighted by alpha, so fully transparent pixels do not bleed their
+//! (invisible) color into the result; a block that is transparent throughout
+//! comes out black, as premultiplied alpha 0 does in [`crate::convert`].
+
+use alloc::vec::Vec;
+
+use crate::error::{PnmError, try_vec};
+use crate::half::{f16_to_f32, f32_to_f16};
+use crate::pixel::PixelLayout;
+use enough::Stop;
+
+/// How each `factor` x `factor` block of the source becomes one pixel.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DownscaleFilter {
+    /// Average of the block, with color weighted by alpha.
+    #[default]
+    Box,
+    /// The pixel at the center of the block. Only one source row per output
+    /// row is decoded.
+    Nearest,
+}
+
+/// Output size for `factor`: blocks at the right and bottom edges may be
+/// partial, so every source pixel lands in some output pixel.
+pub(crate) fn downscaled_size(
+    width: u32,
+    height: u32,
+    factor: u32,
+) -> Result<(u32, u32), PnmError> {
+    if factor == 0 {
+        return Err(PnmError::InvalidData(
+            "downscale factor must be at least 1".into(),
+        ));
+    }
+    Ok((width.div_ceil(factor), height.div_ceil(factor)))
+}
+
+/// Downscale a `width` x `height` image in `layout` by `factor`.
+///
+/// `read_row(y, row)` fills `row` with source row `y` (top to bottom) in
+/// `layout`, native-endian. Rows are requested in increasing order.
+pub(crate) fn downscale(
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    factor: u32,
+    filter: DownscaleFilter,
+    stop: &dyn Stop,
+    mut read_row: impl FnMut(usize, &mut [u8]),
+) -> Result<Vec<u8>, PnmError> {
+    let (out_w, out_h) = downscaled_size(width, height, factor)?;
+    let (w, h, f) = (width as usize, height as usize, factor as usize);
+    let (out_w, out_h) = (out_w as usize, out_h as usize);
+    let bpp = layout.bytes_per_pixel();
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let out_row_bytes = out_w.checked_mul(bpp).ok_or_else(too_large)?;
+    let out_size = out_row_bytes.checked_mul(out_h).ok_or_else(too_large)?;
+    let mut out = try_vec(0u8, out_size)?;
+    let mut row = try_vec(0u8, w.checked_mul(bpp).ok_or_else(too_large)?)?;
+    let sample = Sample::of(layout);
+    let channels = layout.channels();
+    // Block `i` covers source pixels `i * f..min((i + 1) * f, len)`
+    let block = |i: usize, len: usize| i * f..((i + 1) * f).min(len);
+
+    match filter {
+        DownscaleFilter::Nearest => {
+            for (oy, dst) in out.chunks_exact_mut(out_row_bytes).enumerate() {
+                if oy % 16 == 0 {
+                    stop.check()?;
+                }
+                let ys = block(oy, h);
+                read_row(ys.start + ys.len() / 2, &mut row);
+                for (ox, px) in dst.chunks_exact_mut(bpp).enumerate() {
+                    let xs = block(ox, w);
+                    let sx = xs.start + xs.len() / 2;
+                    px.copy_from_slice(&row[sx * bpp..(sx + 1) * bpp]);
+                }
+            }
+        }
+        DownscaleFilter::Box => {
+            // Per output pixel: a sum per channel (color weighted by alpha),
+            // then the sum of the weights
+            let stride = channels + 1;
+            let mut acc = try_vec(0f64, out_w.checked_mul(stride).ok_or_else(too_large)?)?;
+            let alpha = layout.has_alpha().then_some(channels - 1);
+            let mut rows_read = 0usize;
+            for (oy, dst) in out.chunks_exact_mut(out_row_bytes).enumerate() {
+                acc.fill(0.0);
+                let ys = block(oy, h);
+                let rows = ys.len();
+                for y in ys {
+                    if rows_read % 16 == 0 {
+                        stop.check()?;
+                    }
+                    rows_read += 1;
+                    read_row(y, &mut row);
+                    for (ox, sums) in acc.chunks_exact_mut(stride).enumerate() {
+                        let xs = block(ox, w);
+                        for px in row[xs.start * bpp..xs.end * bpp].chunks_exact(bpp) {
+                            let weight = alpha.map_or(1.0, |a| sample.read(px, a));
+                            for (c, sum) in sums[..channels].iter_mut().enumerate() {
+                                let v = sample.read(px, c);
+                                *sum += if Some(c) == alpha { v } else { v * weight };
+                            }
+                            sums[channels] += weight;
+                        }
+                    }
+                }
+                for (ox, (px, sums)) in dst
+                    .chunks_exact_mut(bpp)
+                    .zip(acc.chunks_exact(stride))
+                    .enumerate()
+                {
+                    let n = (rows * block(ox, w).len()) as f64;
+                    let weight = sums[channels];
+                    for (c, &sum) in sums[..channels].iter().enumerate() {
+                        let v = match alpha {
+                            Some(a) if a != c => {
+                                // A fully transparent block comes out black
+                                if weight > 0.0 { sum / weight } else { 0.0 }
+                            }
+                            _ => sum / n,
+                        };
+                        sample.write(px, c, v);
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Sample encoding of a layout.
+#[derive(Clone, Copy)]
+enum Sample {
+    U8,
+    U16,
+    F16,
+    F32,
+}
+
+impl Sample {
+    fn of(layout: PixelLayout) -> Self {
+        match (layout.bytes_per_sample(), layout.is_float()) {
+            (1, _) => Self::U8,
+            (2, false) => Self::U16,
+            (2, true) => Self::F16,
+            _ => Self::F32,
+        }
+    }
+
+    /// Channel `c` of pixel `px`; integers in their own range, floats as-is.
+    fn read(self, px: &[u8], c: usize) -> f64 {
+        match self {
+            Self::U8 => f64::from(px[c]),
+            Self::U16 => f64::from(u16::from_ne_bytes([px[2 * c], px[2 * c + 1]])),
+            Self::F16 => f64::from(f16_to_f32(u16::from_ne_bytes([px[2 * c], px[2 * c + 1]]))),
+            Self::F32 => {
+                let b = &px[4 * c..4 * c + 4];
+                f64::from(f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            }
+        }
+    }
+
+    /// Store `v` in channel `c` of `px`, rounding and clamping integers.
+    fn write(self, px: &mut [u8], c: usize, v: f64) {
+        match self {
+            Self::U8 => px[c] = (v.clamp(0.0, 255.0) + 0.5) as u8,
+            Self::U16 => px[2 * c..2 * c + 2]
+                .copy_from_slice(&((v.clamp(0.0, 65535.0) + 0.5) as u16).to_ne_bytes()),
+            Self::F16 => px[2 * c..2 * c + 2].copy_from_slice(&f32_to_f16(v as f32).to_ne_bytes()),
+            Self::F32 => px[4 * c..4 * c + 4].copy_from_slice(&(v as f32).to_ne_bytes()),
+        }
+    }
+}
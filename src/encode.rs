@@ -0,0 +1,187 @@
+//! Output formats, exact encoded sizes and oriented encoding.
+
+use crate::error::PnmError;
+use crate::orientation::Orientation;
+use crate::pixel::{PixelLayout, PixelSlice};
+use crate::pnm::{self, PnmFormat};
+use enough::Stop;
+
+/// Encoded output format, matching the `encode_*` functions.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeFormat {
+    /// P5, as written by `encode_pgm`.
+    Pgm,
+    /// P6, as written by `encode_ppm`.
+    Ppm,
+    /// P7, as written by `encode_pam`.
+    Pam,
+    /// PFM, as written by `encode_pfm`.
+    Pfm,
+    /// 24-bit BMP, as written by `encode_bmp`.
+    #[cfg(feature = "basic-bmp")]
+    Bmp,
+    /// 32-bit BMP, as written by `encode_bmp_rgba`.
+    #[cfg(feature = "basic-bmp")]
+    BmpRgba,
+}
+
+/// Extra content for PNM headers, written by [`encode_with_header`].
+///
+/// The whole header, this content included, must fit in
+/// [`MAX_HEADER_BYTES`].
+#[derive(Clone, Debug, Default)]
+pub struct HeaderOptions<'a> {
+    /// Comment lines, each written as `# comment` right after the magic
+    /// number. They must not contain line breaks.
+    pub comments: &'a [&'a str],
+    /// Extra PAM header lines, each written as `KEYWORD value` just before
+    /// `ENDHDR`. Keywords must be non-empty, free of whitespace, not start
+    /// with `#` and not be one PAM itself defines; values must not contain
+    /// line breaks. Only PAM has room for them.
+    pub pam_fields: &'a [(&'a str, &'a str)],
+}
+
+/// Largest header [`encode_with_header`] writes, in bytes.
+pub const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+impl HeaderOptions<'_> {
+    /// Reject content that would break a `format` header.
+    pub(crate) fn check(&self, format: PnmFormat) -> Result<(), PnmError> {
+        let line_break = |s: &str| s.contains(['\n', '\r']);
+        if self.comments.iter().any(|c| line_break(c)) {
+            return Err(PnmError::InvalidData(
+                "header comments must not contain line breaks".into(),
+            ));
+        }
+        if !self.pam_fields.is_empty() && format != PnmFormat::Pam {
+            return Err(PnmError::UnsupportedVariant(
+                "custom header fields need PAM".into(),
+            ));
+        }
+        for &(keyword, value) in self.pam_fields {
+            let reserved = matches!(
+                keyword,
+                "WIDTH" | "HEIGHT" | "DEPTH" | "MAXVAL" | "TUPLTYPE" | "ENDHDR"
+            );
+            if keyword.is_empty()
+                || keyword.starts_with('#')
+                || keyword.contains(|c: char| c.is_whitespace())
+                || reserved
+            {
+                return Err(PnmError::InvalidData(alloc::format!(
+                    "invalid PAM header keyword {keyword:?}"
+                )));
+            }
+            if line_break(value) {
+                return Err(PnmError::InvalidData(alloc::format!(
+                    "PAM header field {keyword} must not contain line breaks"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exact size in bytes of a `width` x `height` image in `layout` encoded as
+/// `format`, header included.
+///
+/// This is what the matching `encode_*` function returns and what its
+/// `encode_*_into` counterpart writes, so it can size a reusable or
+/// preallocated buffer. [`crate::ConvertOptions`] never change the size.
+///
+/// Returns [`PnmError::DimensionsTooLarge`] if the size overflows `usize`.
+pub fn encoded_len(
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    format: EncodeFormat,
+) -> Result<usize, PnmError> {
+    let format = match format {
+        EncodeFormat::Pgm => PnmFormat::Pgm,
+        EncodeFormat::Ppm => PnmFormat::Ppm,
+        EncodeFormat::Pam => PnmFormat::Pam,
+        EncodeFormat::Pfm => PnmFormat::Pfm,
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::Bmp => return crate::bmp::encoded_len(width, height, false),
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::BmpRgba => return crate::bmp::encoded_len(width, height, true),
+    };
+    pnm::encoded_len(width, height, layout, format)
+}
+
+/// Encode pixels as `format` with `orientation` applied.
+///
+/// Each output row is converted straight from the source row or column it
+/// comes from, so the flip or rotation costs no extra pass. The file gets
+/// the oriented dimensions; [`Orientation::Identity`] writes exactly what the
+/// matching `encode_*` function does.
+pub fn encode_oriented(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    format: EncodeFormat,
+    orientation: Orientation,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    let pixels = PixelSlice::contiguous(pixels, width, height, layout)?;
+    let format = match format {
+        EncodeFormat::Pgm => PnmFormat::Pgm,
+        EncodeFormat::Ppm => PnmFormat::Ppm,
+        EncodeFormat::Pam => PnmFormat::Pam,
+        EncodeFormat::Pfm => PnmFormat::Pfm,
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::Bmp => {
+            return crate::bmp::encode_oriented(&pixels, false, orientation, &stop);
+        }
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::BmpRgba => {
+            return crate::bmp::encode_oriented(&pixels, true, orientation, &stop);
+        }
+    };
+    pnm::encode_oriented(&pixels, format, orientation, &stop)
+}
+
+/// Encode pixels as `format` with the comments and PAM fields of `header` in
+/// the file header.
+///
+/// Returns [`PnmError::InvalidData`] for content that would break the header
+/// (see [`HeaderOptions`]) and [`PnmError::LimitExceeded`] for a header
+/// larger than [`MAX_HEADER_BYTES`]. Only PAM takes custom fields, and BMP
+/// takes nothing: other formats fail with [`PnmError::UnsupportedVariant`]
+/// instead.
+pub fn encode_with_header(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    format: EncodeFormat,
+    header: &HeaderOptions<'_>,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    let pixels = PixelSlice::contiguous(pixels, width, height, layout)?;
+    let format = match format {
+        EncodeFormat::Pgm => PnmFormat::Pgm,
+        EncodeFormat::Ppm => PnmFormat::Ppm,
+        EncodeFormat::Pam => PnmFormat::Pam,
+        EncodeFormat::Pfm => PnmFormat::Pfm,
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::Bmp | EncodeFormat::BmpRgba
+            if !header.comments.is_empty() || !header.pam_fields.is_empty() =>
+        {
+            return Err(PnmError::UnsupportedVariant(
+                "BMP headers cannot carry comments or fields".into(),
+            ));
+        }
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::Bmp => {
+            return crate::bmp::encode_oriented(&pixels, false, Orientation::Identity, &stop);
+        }
+        #[cfg(feature = "basic-bmp")]
+        EncodeFormat::BmpRgba => {
+            return crate::bmp::encode_oriented(&pixels, true, Orientation::Identity, &stop);
+        }
+    };
+    pnm::encode_with_header(&pixels, format, header, &stop)
+}
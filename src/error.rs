@@ -1,4 +1,5 @@
 use alloc::string::String;
+use alloc::vec::Vec;
 use enough::StopReason;
 
 /// Errors from PNM/BMP decoding and encoding.
@@ -26,6 +27,18 @@ pub enum PnmError {
     #[error("unexpected end of input")]
     UnexpectedEof,
 
+    #[error("sample {value} at ({x}, {y}) channel {channel} exceeds maxval {maxval}")]
+    SampleOutOfRange {
+        x: u32,
+        y: u32,
+        channel: u32,
+        value: u32,
+        maxval: u32,
+    },
+
+    #[error("nonconforming file: {0}")]
+    Nonconforming(crate::Deviation),
+
     #[error("pixel layout mismatch: expected {expected:?}, got {actual:?}")]
     LayoutMismatch {
         expected: crate::PixelLayout,
@@ -35,6 +48,9 @@ pub enum PnmError {
     #[error("buffer too small: need {needed} bytes, got {actual}")]
     BufferTooSmall { needed: usize, actual: usize },
 
+    #[error("failed to allocate {bytes} bytes")]
+    AllocationFailed { bytes: usize },
+
     #[error("operation cancelled")]
     Cancelled(StopReason),
 }
@@ -44,3 +60,23 @@ impl From<StopReason> for PnmError {
         PnmError::Cancelled(r)
     }
 }
+
+/// Allocate `len` copies of `value`, returning
+/// [`PnmError::AllocationFailed`] instead of aborting when memory runs out.
+pub(crate) fn try_vec<T: Clone>(value: T, len: usize) -> Result<Vec<T>, PnmError> {
+    let mut out = Vec::new();
+    try_fill(&mut out, value, len)?;
+    Ok(out)
+}
+
+/// Replace the contents of `out` with `len` copies of `value`, reusing its
+/// capacity and growing it fallibly like [`try_vec`].
+pub(crate) fn try_fill<T: Clone>(out: &mut Vec<T>, value: T, len: usize) -> Result<(), PnmError> {
+    out.clear();
+    out.try_reserve_exact(len)
+        .map_err(|_| PnmError::AllocationFailed {
+            bytes: len.saturating_mul(core::mem::size_of::<T>()),
+        })?;
+    out.resize(len, value);
+    Ok(())
+}
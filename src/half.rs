@@ -0,0 +1,171 @@
+//! IEEE 754 half-precision (binary16) conversions.
+//!
+//! Pure bit manipulation — no `std`, no lookup tables.
+
+use alloc::vec::Vec;
+
+use crate::error::{PnmError, try_vec};
+use crate::pixel::PixelLayout;
+
+/// Convert IEEE 754 half-precision bits to `f32` (exact).
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exp = u32::from((bits >> 10) & 0x1f);
+    let man = u32::from(bits & 0x3ff);
+
+    if exp == 0 {
+        if man == 0 {
+            return f32::from_bits(sign);
+        }
+        // Subnormal: normalize the mantissa into an f32 normal.
+        let mut e = 127 - 15 + 1;
+        let mut m = man;
+        while m & 0x400 == 0 {
+            m <<= 1;
+            e -= 1;
+        }
+        return f32::from_bits(sign | (e << 23) | ((m & 0x3ff) << 13));
+    }
+    if exp == 0x1f {
+        // Infinity or NaN (payload preserved)
+        return f32::from_bits(sign | 0x7f80_0000 | (man << 13));
+    }
+    f32::from_bits(sign | ((exp + 127 - 15) << 23) | (man << 13))
+}
+
+/// Convert `f32` to IEEE 754 half-precision bits, rounding to nearest even.
+///
+/// Values beyond the half range become infinity; NaN stays NaN.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let man = x & 0x7f_ffff;
+
+    if exp == 0xff {
+        let nan = if man != 0 {
+            0x200 | (man >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        // Subnormal half: shift the full 24-bit significand into place.
+        let m = man | 0x80_0000;
+        let shift = (14 - e) as u32;
+        let mut h = m >> shift;
+        let rem = m & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if rem > halfway || (rem == halfway && h & 1 != 0) {
+            h += 1;
+        }
+        return sign | h as u16;
+    }
+
+    let mut h = ((e as u32) << 10) | (man >> 13);
+    let rem = man & 0x1fff;
+    // A carry out of the mantissa correctly bumps the exponent (up to infinity).
+    if rem > 0x1000 || (rem == 0x1000 && h & 1 != 0) {
+        h += 1;
+    }
+    sign | h as u16
+}
+
+/// Widen half-float pixels (`GrayF16`/`RgbF16`/`RgbaF16`) to the matching
+/// `f32` layout. Returns the converted bytes and the new layout.
+pub fn widen_f16(pixels: &[u8], layout: PixelLayout) -> Result<(Vec<u8>, PixelLayout), PnmError> {
+    let target = match layout {
+        PixelLayout::GrayF16 => PixelLayout::GrayF32,
+        PixelLayout::RgbF16 => PixelLayout::RgbF32,
+        PixelLayout::RgbaF16 => PixelLayout::RgbaF32,
+        _ => {
+            return Err(PnmError::UnsupportedVariant(alloc::format!(
+                "widen_f16 requires a half-float layout, got {layout:?}"
+            )));
+        }
+    };
+    let mut out = try_vec(0u8, pixels.len() / 2 * 4)?;
+    for (c, o) in pixels.chunks_exact(2).zip(out.chunks_exact_mut(4)) {
+        let v = f16_to_f32(u16::from_ne_bytes([c[0], c[1]]));
+        o.copy_from_slice(&v.to_ne_bytes());
+    }
+    Ok((out, target))
+}
+
+/// Narrow `f32` pixels (`GrayF32`/`RgbF32`/`RgbaF32`) to the matching
+/// half-float layout. Returns the converted bytes and the new layout.
+pub fn narrow_to_f16(
+    pixels: &[u8],
+    layout: PixelLayout,
+) -> Result<(Vec<u8>, PixelLayout), PnmError> {
+    let target = match layout {
+        PixelLayout::GrayF32 => PixelLayout::GrayF16,
+        PixelLayout::RgbF32 => PixelLayout::RgbF16,
+        PixelLayout::RgbaF32 => PixelLayout::RgbaF16,
+        _ => {
+            return Err(PnmError::UnsupportedVariant(alloc::format!(
+                "narrow_to_f16 requires an f32 layout, got {layout:?}"
+            )));
+        }
+    };
+    let mut out = try_vec(0u8, pixels.len() / 4 * 2)?;
+    for (c, o) in pixels.chunks_exact(4).zip(out.chunks_exact_mut(2)) {
+        let v = f32_to_f16(f32::from_ne_bytes([c[0], c[1], c[2], c[3]]));
+        o.copy_from_slice(&v.to_ne_bytes());
+    }
+    Ok((out, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_values_roundtrip() {
+        for v in [
+            0.0f32,
+            -0.0,
+            1.0,
+            -2.5,
+            0.5,
+            65504.0,
+            6.1035156e-5,
+            5.9604645e-8,
+        ] {
+            let h = f32_to_f16(v);
+            assert_eq!(f16_to_f32(h).to_bits(), v.to_bits(), "{v}");
+        }
+    }
+
+    #[test]
+    fn every_half_roundtrips_through_f32() {
+        for bits in 0..=u16::MAX {
+            let f = f16_to_f32(bits);
+            if f.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(f)).is_nan());
+            } else {
+                assert_eq!(f32_to_f16(f), bits, "bits {bits:#06x}");
+            }
+        }
+    }
+
+    #[test]
+    fn rounding_and_overflow() {
+        // 1 + 2^-11 is exactly halfway between 1.0 and the next half; ties to even.
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert_eq!(f32_to_f16(-1.0e6), 0xfc00);
+        assert_eq!(f32_to_f16(1.0e-10), 0);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+    }
+}
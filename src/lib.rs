@@ -6,15 +6,78 @@
 //!
 //! For PNM files with maxval=255 (the common case), decoding returns a borrowed
 //! slice into the input buffer — no allocation or copy needed. Formats that
-//! require transformation (16-bit downscaling, PFM byte reordering) allocate.
+//! require transformation (16-bit byte order, maxval scaling, PFM byte
+//! reordering) allocate. 16-bit grayscale decodes to `Gray16` with its
+//! samples as stored, and 16-bit RGB and RGBA narrow to `Rgb8` / `Rgba8`;
+//! [`decode_as`] with `Gray16` / `Rgb16` / `Rgba16` keeps all 16 bits.
+//! Every decode keeps 16-bit samples as stored in 16-bit layouts and scales
+//! them from maxval only when narrowing to 8 bits or widening to float.
+//!
+//! [`decode_as`] (and `decode_bmp_as`) decode straight into a requested
+//! [`PixelLayout`], doing maxval scaling, channel conversion and row flipping
+//! in one pass per row. It still borrows when the layout already matches.
+//! [`decode_into_buffer`] writes the same rows into a caller buffer at any
+//! stride without allocating. [`decode_reusing`] decodes into a reusable
+//! `Vec`, so a stream of same-sized images allocates once. [`decode_region`]
+//! decodes a crop, reading only the rows it covers, and [`decode_downscaled`]
+//! an integer-factor thumbnail in memory proportional to its size.
+//! [`decode_view`] (and `decode_bmp_native_view`) borrow PFM and BMP rows in
+//! their stored bottom-up order; [`DecodeOutput::row`] reads them top-down.
+//! [`DecodeOutput::source`] reports the file's format, maxval, bit depth and
+//! PAM tuple type, whatever layout it was decoded into, and
+//! [`SourceInfo::comments`] and [`SourceInfo::pam_fields`] its header
+//! comments and extra PAM lines; [`probe`] reads the same from the header
+//! alone.
+//! [`validate`] lists the ways a file departs from its spec (stray
+//! whitespace, unknown PAM fields, samples above maxval, truncated rows,
+//! trailing data), and [`decode_with_options`] in [`Strictness::Strict`]
+//! mode refuses such files. Its [`SamplePolicy`] clamps, rejects or passes
+//! through samples above the file's maxval. [`decode_as_with_options`],
+//! [`decode_region_with_options`] and the other `_with_options` decode
+//! functions take the same [`DecodeOptions`]; the rest clamp.
+//!
+//! On the encode side, [`encoded_len`] gives the exact output size for an
+//! [`EncodeFormat`], and the `encode_*_into` functions write into a caller
+//! buffer of that size without allocating. [`encode_with_header`] adds
+//! comment lines, and custom PAM fields, to the header.
+//!
+//! [`decode_oriented`] and [`encode_oriented`] apply any of the eight EXIF
+//! [`Orientation`]s inside the row loop, on top of the BMP and PFM row flips
+//! the codecs already do, so no separate pass over the image is needed.
 //!
 //! ## Supported Formats
 //!
 //! ### PNM family (always available)
 //! - **P5** (PGM binary) — grayscale, 8-bit and 16-bit
 //! - **P6** (PPM binary) — RGB, 8-bit and 16-bit
-//! - **P7** (PAM) — arbitrary channels (grayscale, RGB, RGBA), 8-bit and 16-bit
-//! - **PFM** — floating-point grayscale and RGB (32-bit float per channel)
+//! - **P7** (PAM) — arbitrary channels (grayscale, RGB, RGBA), 8-bit and 16-bit,
+//!   plus float `FLOAT_GRAYSCALE` / `FLOAT_RGB` / `FLOAT_RGBA` tuple types
+//!   (and `FLOAT16_*` for half-precision)
+//! - **PFM** — floating-point grayscale and RGB (32-bit float per channel),
+//!   plus the de facto 4-channel `PF4` (RGBA) variant and the half-precision
+//!   `Ph` / `PH` / `PH4` variants
+//!
+//! Half-float data uses the `GrayF16` / `RgbF16` / `RgbaF16` layouts (PFM
+//! `Ph`/`PH`/`PH4`, PAM `FLOAT16_*` tuple types). [`widen_f16`] and
+//! [`narrow_to_f16`] convert to and from the `F32` layouts.
+//!
+//! ## Pixel Conversion
+//!
+//! [`convert`], [`convert_into`] and [`convert_in_place`] convert between every
+//! [`PixelLayout`] (gray/RGB/RGBA/BGR/BGRA/BGRX at 8-bit, 16-bit, f16 and f32).
+//! Encoders accept any layout and convert to what the target format stores,
+//! so any decode can feed any encoder. Alpha fill, luma weights and range
+//! mapping are documented on [`convert`].
+//!
+//! [`ConvertOptions`] selects the color → gray conversion ([`LumaWeights`]
+//! Rec.601 / Rec.709 / BT.2020, optionally in linear light with the
+//! `linear-light` feature) and what happens to alpha when the target has
+//! none ([`AlphaPolicy`]: discard, composite over a background, or reject)
+//! for [`convert_with`] and the `encode_*_with_options` functions.
+//!
+//! Premultiplied alpha has its own layouts (`Rgba8Premul`, `Bgra8Premul`,
+//! `Rgba16Premul`, `RgbaF32Premul`). Encoders accept them and store straight
+//! alpha; [`DecodeOutput::into_premultiplied`] premultiplies after decode.
 //!
 //! ### Basic BMP (`basic-bmp` feature, opt-in)
 //! - Uncompressed 24-bit (RGB) and 32-bit (RGBA) only
@@ -48,13 +111,17 @@
 
 extern crate alloc;
 
-#[cfg(feature = "rgb")]
-use rgb::{AsPixels as _, ComponentBytes as _};
-
+mod convert;
 mod decode;
+mod downscale;
+mod encode;
 mod error;
+mod half;
 mod limits;
+mod orientation;
+mod parallel;
 mod pixel;
+mod simd;
 
 mod pnm;
 
@@ -67,11 +134,24 @@ mod pixel_traits;
 #[cfg(feature = "zencodec")]
 mod zencodec;
 
-pub use decode::DecodeOutput;
+pub use convert::{
+    AlphaPolicy, ConvertOptions, LumaWeights, convert, convert_in_place, convert_into, convert_with,
+};
+pub use decode::{
+    DecodeOptions, DecodeOutput, Deviation, HeaderInfo, RowOrder, SamplePolicy, SourceFormat,
+    SourceInfo, Strictness,
+};
+pub use downscale::DownscaleFilter;
+pub use encode::{
+    EncodeFormat, HeaderOptions, MAX_HEADER_BYTES, encode_oriented, encode_with_header, encoded_len,
+};
 pub use enough::{Stop, Unstoppable};
 pub use error::PnmError;
+pub use half::{f16_to_f32, f32_to_f16, narrow_to_f16, widen_f16};
 pub use limits::Limits;
+pub use orientation::Orientation;
 pub use pixel::PixelLayout;
+use pixel::PixelSlice;
 
 #[cfg(feature = "rgb")]
 pub use pixel_traits::{DecodePixel, EncodePixel};
@@ -81,6 +161,8 @@ pub use zencodec::{PnmDecoding, PnmEncoding};
 
 // Re-export rgb pixel types for convenience
 #[cfg(feature = "rgb")]
+pub use rgb::Gray;
+#[cfg(feature = "rgb")]
 pub use rgb::RGB as Rgb;
 #[cfg(feature = "rgb")]
 pub use rgb::RGBA as Rgba;
@@ -101,6 +183,18 @@ pub type BGR8 = rgb::alt::BGR<u8>;
 /// 8-bit BGRA pixel.
 #[cfg(feature = "rgb")]
 pub type BGRA8 = rgb::alt::BGRA<u8>;
+/// 8-bit grayscale pixel.
+#[cfg(feature = "rgb")]
+pub type GRAY8 = rgb::Gray<u8>;
+/// 16-bit grayscale pixel (native endian).
+#[cfg(feature = "rgb")]
+pub type GRAY16 = rgb::Gray<u16>;
+/// 16-bit RGB pixel (native endian).
+#[cfg(feature = "rgb")]
+pub type RGB16 = rgb::RGB<u16>;
+/// 16-bit RGBA pixel (native endian).
+#[cfg(feature = "rgb")]
+pub type RGBA16 = rgb::RGBA<u16>;
 
 // ── PNM decode (auto-detects P5/P6/P7/PFM from magic bytes) ─────────
 
@@ -123,9 +217,348 @@ pub fn decode_with_limits<'a>(
     pnm::decode(data, Some(limits), &stop)
 }
 
+/// Decode any PNM format under `options`.
+///
+/// With [`Strictness::Strict`], a file [`validate`] finds any [`Deviation`]
+/// in fails with [`PnmError::Nonconforming`] carrying the first one.
+/// Integer samples above the file's maxval are handled by the
+/// [`SamplePolicy`]: clamped, rejected with [`PnmError::SampleOutOfRange`],
+/// or passed through unscaled. Otherwise this decodes as
+/// [`decode_with_limits`] does.
+pub fn decode_with_options<'a>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_as(data, None, options, &stop)
+}
+
+/// Decode any PNM format directly into `layout`.
+///
+/// Maxval scaling, byte-order fixup, channel expansion or reduction, swizzle
+/// and PFM row flipping all happen in a single pass per row, with no
+/// intermediate image. When `layout` is what [`decode`] would return for a
+/// maxval-255 file, the output still borrows from `data`. 16-bit samples
+/// stay as stored (clamped to maxval) when `layout` has 16-bit samples, as
+/// [`decode`] returns them, so this is the way to decode 16-bit color at full
+/// precision; other integer samples are scaled from maxval to the range of
+/// `layout`.
+pub fn decode_as(
+    data: &[u8],
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    pnm::decode_as(data, Some(layout), &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format into `layout` with resource limits.
+///
+/// The memory limit applies to the output in `layout`.
+pub fn decode_as_with_limits<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_as(data, Some(layout), &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format into `layout` under `options`.
+///
+/// See [`decode_with_options`] for what the options do; the memory limit
+/// applies to the output in `layout`.
+pub fn decode_as_with_options<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_as(data, Some(layout), options, &stop)
+}
+
+/// Decode any PNM format into `layout` with `orientation` applied.
+///
+/// The flip or rotation happens in the same per-row pass as [`decode_as`]:
+/// rotated orientations read each output row from a stored column. The
+/// output has the oriented dimensions (width and height swap for
+/// rotations by 90° and 270° and the transposes). [`Orientation::Identity`]
+/// behaves exactly like [`decode_as`].
+pub fn decode_oriented(
+    data: &[u8],
+    layout: PixelLayout,
+    orientation: Orientation,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    pnm::decode_oriented(data, layout, orientation, &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format into `layout` with `orientation` applied and
+/// resource limits.
+pub fn decode_oriented_with_limits<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    orientation: Orientation,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_oriented(data, layout, orientation, &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format into `layout` with `orientation` applied, under
+/// `options` (see [`decode_with_options`]).
+pub fn decode_oriented_with_options<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    orientation: Orientation,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_oriented(data, layout, orientation, options, &stop)
+}
+
+/// Decode any PNM format into a caller-provided buffer as `layout`.
+///
+/// Row `y` is written at `buf[y * stride..]`, and only the `width` pixels of
+/// each row are touched, so row padding (or the rest of a larger image) is
+/// left alone. The last row needs no padding. The buffer is checked against
+/// the header before anything is written. Returns `(width, height)`.
+///
+/// Rows are decoded and converted straight into `buf`: no heap allocation
+/// happens for any PNM variant.
+pub fn decode_into_buffer(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<(u32, u32), PnmError> {
+    pnm::decode_into(data, buf, stride, layout, &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format into a caller-provided buffer with resource limits.
+pub fn decode_into_buffer_with_limits(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    layout: PixelLayout,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<(u32, u32), PnmError> {
+    pnm::decode_into(data, buf, stride, layout, &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format into a caller-provided buffer under `options`
+/// (see [`decode_with_options`]).
+///
+/// Samples are checked against maxval before any row is written, so a
+/// rejected file leaves `buf` untouched.
+pub fn decode_into_buffer_with_options(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    layout: PixelLayout,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<(u32, u32), PnmError> {
+    pnm::decode_into(data, buf, stride, layout, options, &stop)
+}
+
+/// Decode any PNM format into a reusable buffer.
+///
+/// Formats that need transformation (16-bit, maxval other than 255, PFM)
+/// are decoded into `buf`, replacing its contents and reusing its capacity,
+/// and the returned [`DecodeOutput`] borrows from it. Decoding many
+/// same-sized images with one `buf` allocates only for the first. Maxval-255
+/// files still borrow from `data` and leave `buf` untouched.
+pub fn decode_reusing<'a>(
+    data: &'a [u8],
+    buf: &'a mut alloc::vec::Vec<u8>,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_reusing(data, None, buf, &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format into a reusable buffer with resource limits.
+pub fn decode_reusing_with_limits<'a>(
+    data: &'a [u8],
+    buf: &'a mut alloc::vec::Vec<u8>,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_reusing(data, None, buf, &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format into a reusable buffer under `options` (see
+/// [`decode_with_options`]).
+pub fn decode_reusing_with_options<'a>(
+    data: &'a [u8],
+    buf: &'a mut alloc::vec::Vec<u8>,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_reusing(data, None, buf, options, &stop)
+}
+
+/// Read the header of any PNM format without decoding pixels: dimensions,
+/// layout, and the [`SourceInfo`] a decode would report, comments included.
+pub fn probe(data: &[u8]) -> Result<HeaderInfo<'_>, PnmError> {
+    pnm::probe(data, None)
+}
+
+/// Read a PNM header with resource limits.
+pub fn probe_with_limits<'a>(data: &'a [u8], limits: &Limits) -> Result<HeaderInfo<'a>, PnmError> {
+    pnm::probe(data, Some(limits))
+}
+
+/// List the ways a PNM file departs from its format spec, in file order,
+/// without decoding it.
+///
+/// Lenient decoding accepts every deviation reported here except
+/// [`Deviation::TruncatedRows`]. Fails only when the header cannot be parsed,
+/// or with [`PnmError::DimensionsTooLarge`] when the image it describes is
+/// too large to address.
+pub fn validate(data: &[u8]) -> Result<alloc::vec::Vec<Deviation>, PnmError> {
+    pnm::validate(data)
+}
+
+/// Decode any PNM format, borrowing native-endian PFM data without
+/// flipping its rows.
+///
+/// PFM stores rows bottom to top. When the file's byte order matches the
+/// target's and its scale is 1, the output borrows the stored rows with
+/// [`RowOrder::BottomUp`]; use [`DecodeOutput::row`] to read them top-down.
+/// Everything else decodes as [`decode`] does.
+pub fn decode_view(data: &[u8], stop: impl Stop) -> Result<DecodeOutput<'_>, PnmError> {
+    pnm::decode_view(data, &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format like [`decode_view`] with resource limits.
+pub fn decode_view_with_limits<'a>(
+    data: &'a [u8],
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_view(data, &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format like [`decode_view`] under `options` (see
+/// [`decode_with_options`]).
+pub fn decode_view_with_options<'a>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_view(data, options, &stop)
+}
+
+/// Decode the `width` x `height` region of a PNM image whose top-left
+/// corner is at (`x`, `y`).
+///
+/// Only the stored rows the region covers are read, so data past them may
+/// be missing. Maxval-255 files borrow the rows from `data`: the output's
+/// [`stride`](DecodeOutput::stride) is then the full image row. Returns
+/// [`PnmError::InvalidData`] if the region does not lie within the image.
+pub fn decode_region(
+    data: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    pnm::decode_region(data, x, y, width, height, &DecodeOptions::default(), &stop)
+}
+
+/// Decode a region of a PNM image with resource limits.
+///
+/// The limits apply to the region, not the full image.
+pub fn decode_region_with_limits<'a>(
+    data: &'a [u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_region(data, x, y, width, height, &limits_options(limits), &stop)
+}
+
+/// Decode a region of a PNM image under `options` (see
+/// [`decode_with_options`]).
+///
+/// The limits apply to the region, and only the region's samples are
+/// checked against maxval; [`PnmError::SampleOutOfRange`] gives the
+/// position in the full image. [`Strictness::Strict`] still checks the
+/// whole file.
+pub fn decode_region_with_options<'a>(
+    data: &'a [u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    pnm::decode_region(data, x, y, width, height, options, &stop)
+}
+
+/// Decode any PNM format downscaled by an integer `factor`, for thumbnails
+/// and previews.
+///
+/// The output is `ceil(width / factor)` x `ceil(height / factor)` in the
+/// file's own layout (as [`decode`] returns it); blocks at the right and
+/// bottom edges may be partial. Rows are decoded and reduced one at a time,
+/// so memory stays proportional to the output however large the file is.
+/// See [`DownscaleFilter`] for how blocks are reduced. A `factor` of 0
+/// returns [`PnmError::InvalidData`].
+pub fn decode_downscaled(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    pnm::decode_downscaled(data, factor, filter, &DecodeOptions::default(), &stop)
+}
+
+/// Decode any PNM format downscaled by `factor`, with resource limits.
+///
+/// The limits apply to the downscaled image, not the full one.
+pub fn decode_downscaled_with_limits(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    pnm::decode_downscaled(data, factor, filter, &limits_options(limits), &stop)
+}
+
+/// Decode any PNM format downscaled by `factor`, under `options` (see
+/// [`decode_with_options`]).
+///
+/// The limits apply to the downscaled image, not the full one.
+pub fn decode_downscaled_with_options(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    pnm::decode_downscaled(data, factor, filter, options, &stop)
+}
+
+/// Options that only set `limits`, for the `_with_limits` functions.
+fn limits_options(limits: &Limits) -> DecodeOptions {
+    DecodeOptions::default().with_limits(limits.clone())
+}
+
 // ── PNM encode ───────────────────────────────────────────────────────
 
 /// Encode pixels as PPM (P6, binary RGB).
+///
+/// Accepts any [`PixelLayout`]: 16-bit input is written with maxval 65535,
+/// everything else as 8-bit. Gray is replicated, alpha is dropped.
 pub fn encode_ppm(
     pixels: &[u8],
     width: u32,
@@ -133,10 +566,17 @@ pub fn encode_ppm(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    pnm::encode(pixels, width, height, layout, pnm::PnmFormat::Ppm, &stop)
+    pnm::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Ppm,
+        &stop,
+    )
 }
 
 /// Encode pixels as PGM (P5, binary grayscale).
+///
+/// Accepts any [`PixelLayout`]: color is reduced to luma (Rec.601), 16-bit
+/// input is written with maxval 65535, everything else as 8-bit.
 pub fn encode_pgm(
     pixels: &[u8],
     width: u32,
@@ -144,10 +584,56 @@ pub fn encode_pgm(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    pnm::encode(pixels, width, height, layout, pnm::PnmFormat::Pgm, &stop)
+    pnm::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pgm,
+        &stop,
+    )
+}
+
+/// Encode pixels as PPM (P6) with explicit [`ConvertOptions`].
+///
+/// [`ConvertOptions::alpha`] decides what happens to alpha input.
+pub fn encode_ppm_with_options(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    options: &ConvertOptions,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode_with_options(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Ppm,
+        options,
+        &stop,
+    )
+}
+
+/// Encode pixels as PGM (P5) with explicit [`ConvertOptions`].
+///
+/// Use this to pick the luma weights (or linear-light luminance) applied
+/// to color input, and the [`AlphaPolicy`] for alpha input.
+pub fn encode_pgm_with_options(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    options: &ConvertOptions,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode_with_options(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pgm,
+        options,
+        &stop,
+    )
 }
 
 /// Encode pixels as PAM (P7, arbitrary channels).
+///
+/// Stores gray, RGB and RGBA at 8-bit, 16-bit, f16 and f32 as-is; BGR
+/// orders are reordered to RGB and `Bgrx8` padding is dropped.
 pub fn encode_pam(
     pixels: &[u8],
     width: u32,
@@ -155,10 +641,17 @@ pub fn encode_pam(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    pnm::encode(pixels, width, height, layout, pnm::PnmFormat::Pam, &stop)
+    pnm::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pam,
+        &stop,
+    )
 }
 
 /// Encode pixels as PFM (floating-point).
+///
+/// Float layouts are stored as-is; integer input is mapped to `0.0..=1.0`
+/// (`GrayF32`, `RgbF32`, or `RgbaF32` as `PF4` when it has alpha).
 pub fn encode_pfm(
     pixels: &[u8],
     width: u32,
@@ -166,7 +659,156 @@ pub fn encode_pfm(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    pnm::encode(pixels, width, height, layout, pnm::PnmFormat::Pfm, &stop)
+    pnm::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pfm,
+        &stop,
+    )
+}
+
+/// Encode pixels as PPM (P6) from rows `stride` bytes apart.
+///
+/// Rows may carry padding (framebuffers) or belong to a larger image
+/// (sub-images); the last row only needs `width` pixels. Rows are read in
+/// place, with no intermediate copy.
+pub fn encode_ppm_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        pnm::PnmFormat::Ppm,
+        &stop,
+    )
+}
+
+/// Encode pixels as PGM (P5) from rows `stride` bytes apart.
+pub fn encode_pgm_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        pnm::PnmFormat::Pgm,
+        &stop,
+    )
+}
+
+/// Encode pixels as PAM (P7) from rows `stride` bytes apart.
+pub fn encode_pam_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        pnm::PnmFormat::Pam,
+        &stop,
+    )
+}
+
+/// Encode pixels as PFM from rows `stride` bytes apart.
+pub fn encode_pfm_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        pnm::PnmFormat::Pfm,
+        &stop,
+    )
+}
+
+/// Encode pixels as PPM (P6) into a caller-provided buffer.
+///
+/// `out` must hold at least [`encoded_len`] bytes for
+/// [`EncodeFormat::Ppm`]; a shorter buffer fails with
+/// [`PnmError::BufferTooSmall`] before anything is written. Returns the
+/// number of bytes written. No heap allocation happens.
+pub fn encode_ppm_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    pnm::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Ppm,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
+}
+
+/// Encode pixels as PGM (P5) into a caller-provided buffer. See [`encode_ppm_into`].
+pub fn encode_pgm_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    pnm::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pgm,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
+}
+
+/// Encode pixels as PAM (P7) into a caller-provided buffer. See [`encode_ppm_into`].
+pub fn encode_pam_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    pnm::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pam,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
+}
+
+/// Encode pixels as PFM into a caller-provided buffer. See [`encode_ppm_into`].
+pub fn encode_pfm_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    pnm::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        pnm::PnmFormat::Pfm,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
 }
 
 // ── BMP (explicit only, not auto-detected) ───────────────────────────
@@ -209,6 +851,177 @@ pub fn decode_bmp_native_with_limits<'a>(
     bmp::decode_native(data, Some(limits), &stop)
 }
 
+/// Borrow BMP pixel data in native byte order without copying.
+///
+/// Rows keep their stored padding and order: the output's
+/// [`stride`](DecodeOutput::stride) is the padded row, and bottom-up files
+/// (the usual kind) give [`RowOrder::BottomUp`]. Use [`DecodeOutput::row`]
+/// to read rows top-down, or [`DecodeOutput::into_owned`] to pack them.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_native_view(data: &[u8], stop: impl Stop) -> Result<DecodeOutput<'_>, PnmError> {
+    bmp::decode_native_view(data, None, &stop)
+}
+
+/// Borrow BMP pixel data in native byte order with resource limits.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_native_view_with_limits<'a>(
+    data: &'a [u8],
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    bmp::decode_native_view(data, Some(limits), &stop)
+}
+
+/// Decode BMP data directly into `layout`.
+///
+/// Swizzle, alpha fill or drop and row flipping happen in a single pass per
+/// row. `Bgr8`/`Bgra8` match the stored order and are plain row copies.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_as(
+    data: &[u8],
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    bmp::decode_as(data, Some(layout), None, &stop)
+}
+
+/// Decode BMP into `layout` with resource limits.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_as_with_limits<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    bmp::decode_as(data, Some(layout), Some(limits), &stop)
+}
+
+/// Decode BMP into `layout` with `orientation` applied. See
+/// [`decode_oriented`].
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_oriented(
+    data: &[u8],
+    layout: PixelLayout,
+    orientation: Orientation,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'_>, PnmError> {
+    bmp::decode_oriented(data, Some(layout), orientation, None, &stop)
+}
+
+/// Decode BMP into `layout` with `orientation` applied and resource limits.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_oriented_with_limits<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    orientation: Orientation,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    bmp::decode_oriented(data, Some(layout), orientation, Some(limits), &stop)
+}
+
+/// Decode BMP into a caller-provided buffer as `layout`, rows `stride`
+/// bytes apart. See [`decode_into_buffer`]; this never allocates either.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_into_buffer(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<(u32, u32), PnmError> {
+    bmp::decode_into(data, buf, stride, layout, None, &stop)
+}
+
+/// Decode BMP into a caller-provided buffer with resource limits.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_into_buffer_with_limits(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    layout: PixelLayout,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<(u32, u32), PnmError> {
+    bmp::decode_into(data, buf, stride, layout, Some(limits), &stop)
+}
+
+/// Decode BMP into a reusable buffer, replacing its contents and reusing
+/// its capacity. The returned [`DecodeOutput`] borrows from `buf`. See
+/// [`decode_reusing`].
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_reusing<'a>(
+    data: &[u8],
+    buf: &'a mut alloc::vec::Vec<u8>,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    bmp::decode_reusing(data, None, buf, None, &stop)
+}
+
+/// Decode BMP into a reusable buffer with resource limits.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_reusing_with_limits<'a>(
+    data: &[u8],
+    buf: &'a mut alloc::vec::Vec<u8>,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    bmp::decode_reusing(data, None, buf, Some(limits), &stop)
+}
+
+/// Decode the `width` x `height` region of a BMP image at (`x`, `y`),
+/// reading only the stored rows it covers. See [`decode_region`].
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_region(
+    data: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    bmp::decode_region(data, x, y, width, height, None, &stop)
+}
+
+/// Decode a region of a BMP image with resource limits applied to the
+/// region.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_region_with_limits(
+    data: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    bmp::decode_region(data, x, y, width, height, Some(limits), &stop)
+}
+
+/// Decode BMP downscaled by an integer `factor`. See [`decode_downscaled`].
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_downscaled(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    bmp::decode_downscaled(data, factor, filter, None, &stop)
+}
+
+/// Decode BMP downscaled by `factor`, with resource limits applied to the
+/// downscaled image.
+#[cfg(feature = "basic-bmp")]
+pub fn decode_bmp_downscaled_with_limits(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    bmp::decode_downscaled(data, factor, filter, Some(limits), &stop)
+}
+
 /// Encode pixels as 24-bit BMP (RGB, no alpha).
 #[cfg(feature = "basic-bmp")]
 pub fn encode_bmp(
@@ -218,7 +1031,32 @@ pub fn encode_bmp(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    bmp::encode(pixels, width, height, layout, false, &stop)
+    bmp::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        false,
+        &ConvertOptions::default(),
+        &stop,
+    )
+}
+
+/// Encode pixels as 24-bit BMP with explicit [`ConvertOptions`].
+///
+/// [`ConvertOptions::alpha`] decides what happens to alpha input.
+#[cfg(feature = "basic-bmp")]
+pub fn encode_bmp_with_options(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    options: &ConvertOptions,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    bmp::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        false,
+        options,
+        &stop,
+    )
 }
 
 /// Encode pixels as 32-bit BMP (RGBA with alpha).
@@ -230,21 +1068,107 @@ pub fn encode_bmp_rgba(
     layout: PixelLayout,
     stop: impl Stop,
 ) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    bmp::encode(pixels, width, height, layout, true, &stop)
+    bmp::encode(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        true,
+        &ConvertOptions::default(),
+        &stop,
+    )
+}
+
+/// Encode pixels as 24-bit BMP from rows `stride` bytes apart.
+#[cfg(feature = "basic-bmp")]
+pub fn encode_bmp_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    bmp::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        false,
+        &ConvertOptions::default(),
+        &stop,
+    )
+}
+
+/// Encode pixels as 32-bit BMP (RGBA) from rows `stride` bytes apart.
+#[cfg(feature = "basic-bmp")]
+pub fn encode_bmp_rgba_strided(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+    stop: impl Stop,
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    bmp::encode(
+        &PixelSlice::strided(pixels, width, height, stride, layout)?,
+        true,
+        &ConvertOptions::default(),
+        &stop,
+    )
+}
+
+/// Encode pixels as 24-bit BMP into a caller-provided buffer. See [`encode_ppm_into`].
+#[cfg(feature = "basic-bmp")]
+pub fn encode_bmp_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    bmp::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        false,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
+}
+
+/// Encode pixels as 32-bit BMP (RGBA) into a caller-provided buffer. See
+/// [`encode_ppm_into`].
+#[cfg(feature = "basic-bmp")]
+pub fn encode_bmp_rgba_into(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    out: &mut [u8],
+    stop: impl Stop,
+) -> Result<usize, PnmError> {
+    bmp::encode_into(
+        &PixelSlice::contiguous(pixels, width, height, layout)?,
+        true,
+        &ConvertOptions::default(),
+        out,
+        &stop,
+    )
 }
 
 // ── Typed pixel API (rgb feature) ────────────────────────────────────
+//
+// Pixel types: `Rgb`/`Rgba`/`Bgr`/`Bgra` of `u8`, `Gray` of `u8`/`u16`/`f32`,
+// `Rgb`/`Rgba` of `u16`/`f32`. Samples are native-endian; decoding copies into
+// an aligned buffer, so input alignment never matters. The `_cow` variants
+// borrow from the input instead when it is zero-copy and suitably aligned.
 
 /// Decode any PNM format to typed pixels.
+///
+/// Pixel types matching the file's stored samples get them as stored, so
+/// `Rgb<u16>` reads 16-bit PPM without narrowing it to 8 bits, with the same
+/// samples [`decode_as`] returns for `Rgb16`.
 #[cfg(feature = "rgb")]
 pub fn decode_pixels<P: DecodePixel>(
     data: &[u8],
     stop: impl Stop,
-) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
-    let decoded = decode(data, stop)?;
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), &DecodeOptions::default(), &stop)?;
     decoded_to_pixels(decoded)
 }
 
@@ -254,23 +1178,71 @@ pub fn decode_pixels_with_limits<P: DecodePixel>(
     data: &[u8],
     limits: &Limits,
     stop: impl Stop,
-) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
-    let decoded = decode_with_limits(data, limits, stop)?;
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), &limits_options(limits), &stop)?;
+    decoded_to_pixels(decoded)
+}
+
+/// Decode any PNM format to typed pixels under `options` (see
+/// [`decode_with_options`]).
+#[cfg(feature = "rgb")]
+pub fn decode_pixels_with_options<P: DecodePixel>(
+    data: &[u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), options, &stop)?;
     decoded_to_pixels(decoded)
 }
 
+/// Decode any PNM format to typed pixels, borrowing from `data` when possible.
+///
+/// Maxval-255 PNM whose layout matches `P` is returned as
+/// [`Cow::Borrowed`](alloc::borrow::Cow::Borrowed) without copying (8-bit
+/// pixel types are always aligned); anything else is decoded into an owned
+/// buffer. See [`DecodeOutput::into_pixels`].
+#[cfg(feature = "rgb")]
+pub fn decode_pixels_cow<P: DecodePixel>(
+    data: &[u8],
+    stop: impl Stop,
+) -> Result<(alloc::borrow::Cow<'_, [P]>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), &DecodeOptions::default(), &stop)?;
+    let (w, h) = (decoded.width, decoded.height);
+    Ok((decoded.into_pixels()?, w, h))
+}
+
+/// Decode any PNM format to typed pixels with resource limits, borrowing
+/// from `data` when possible.
+#[cfg(feature = "rgb")]
+pub fn decode_pixels_cow_with_limits<'a, P: DecodePixel>(
+    data: &'a [u8],
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<(alloc::borrow::Cow<'a, [P]>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), &limits_options(limits), &stop)?;
+    let (w, h) = (decoded.width, decoded.height);
+    Ok((decoded.into_pixels()?, w, h))
+}
+
+/// Decode any PNM format to typed pixels under `options` (see
+/// [`decode_with_options`]), borrowing from `data` when possible.
+#[cfg(feature = "rgb")]
+pub fn decode_pixels_cow_with_options<'a, P: DecodePixel>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<(alloc::borrow::Cow<'a, [P]>, u32, u32), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), options, &stop)?;
+    let (w, h) = (decoded.width, decoded.height);
+    Ok((decoded.into_pixels()?, w, h))
+}
+
 /// Decode BMP to typed pixels.
 #[cfg(all(feature = "basic-bmp", feature = "rgb"))]
 pub fn decode_bmp_pixels<P: DecodePixel>(
     data: &[u8],
     stop: impl Stop,
-) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
     let decoded = decode_bmp(data, stop)?;
     decoded_to_pixels(decoded)
 }
@@ -281,10 +1253,7 @@ pub fn decode_bmp_pixels_with_limits<P: DecodePixel>(
     data: &[u8],
     limits: &Limits,
     stop: impl Stop,
-) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
     let decoded = decode_bmp_with_limits(data, limits, stop)?;
     decoded_to_pixels(decoded)
 }
@@ -292,18 +1261,15 @@ where
 #[cfg(feature = "rgb")]
 fn decoded_to_pixels<P: DecodePixel>(
     decoded: DecodeOutput<'_>,
-) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<(alloc::vec::Vec<P>, u32, u32), PnmError> {
     if !decoded.layout.is_memory_compatible(P::layout()) {
         return Err(PnmError::LayoutMismatch {
             expected: P::layout(),
             actual: decoded.layout,
         });
     }
-    let pixels: &[P] = decoded.pixels().as_pixels();
-    Ok((pixels.to_vec(), decoded.width, decoded.height))
+    let pixels = pixel_traits::pixels_from_bytes(decoded.pixels())?;
+    Ok((pixels, decoded.width, decoded.height))
 }
 
 // ── Typed pixel encode (rgb feature) ─────────────────────────────────
@@ -315,11 +1281,14 @@ pub fn encode_ppm_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_ppm(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_ppm(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 /// Encode typed pixels as PGM (P5).
@@ -329,11 +1298,14 @@ pub fn encode_pgm_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_pgm(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_pgm(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 /// Encode typed pixels as PAM (P7).
@@ -343,11 +1315,14 @@ pub fn encode_pam_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_pam(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_pam(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 /// Encode typed pixels as PFM (floating-point).
@@ -357,11 +1332,14 @@ pub fn encode_pfm_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_pfm(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_pfm(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 /// Encode typed pixels as 24-bit BMP.
@@ -371,11 +1349,14 @@ pub fn encode_bmp_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_bmp(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_bmp(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 /// Encode typed pixels as 32-bit BMP (RGBA).
@@ -385,11 +1366,14 @@ pub fn encode_bmp_rgba_pixels<P: EncodePixel>(
     width: u32,
     height: u32,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    encode_bmp_rgba(pixels.as_bytes(), width, height, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    encode_bmp_rgba(
+        pixel_traits::pixels_as_bytes(pixels),
+        width,
+        height,
+        P::layout(),
+        stop,
+    )
 }
 
 // ── ImgVec/ImgRef API (imgref feature) ───────────────────────────────
@@ -399,10 +1383,7 @@ where
 pub fn decode_img<P: DecodePixel>(
     data: &[u8],
     stop: impl Stop,
-) -> Result<imgref::ImgVec<P>, PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<imgref::ImgVec<P>, PnmError> {
     let (pixels, w, h) = decode_pixels::<P>(data, stop)?;
     Ok(imgref::ImgVec::new(pixels, w as usize, h as usize))
 }
@@ -413,23 +1394,64 @@ pub fn decode_img_with_limits<P: DecodePixel>(
     data: &[u8],
     limits: &Limits,
     stop: impl Stop,
-) -> Result<imgref::ImgVec<P>, PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<imgref::ImgVec<P>, PnmError> {
     let (pixels, w, h) = decode_pixels_with_limits::<P>(data, limits, stop)?;
     Ok(imgref::ImgVec::new(pixels, w as usize, h as usize))
 }
 
+/// Decode any PNM format to an [`imgref::ImgVec`] under `options` (see
+/// [`decode_with_options`]).
+#[cfg(feature = "imgref")]
+pub fn decode_img_with_options<P: DecodePixel>(
+    data: &[u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<imgref::ImgVec<P>, PnmError> {
+    let (pixels, w, h) = decode_pixels_with_options::<P>(data, options, stop)?;
+    Ok(imgref::ImgVec::new(pixels, w as usize, h as usize))
+}
+
+/// Decode any PNM format to an [`imgref::Img`] borrowing from `data` when
+/// possible (see [`decode_pixels_cow`]).
+///
+/// [`imgref::ImgExt::as_ref`] gives an [`imgref::ImgRef`]; `.into_owned()`
+/// gives an [`imgref::ImgVec`].
+#[cfg(feature = "imgref")]
+pub fn decode_img_cow<P: DecodePixel>(
+    data: &[u8],
+    stop: impl Stop,
+) -> Result<imgref::Img<alloc::borrow::Cow<'_, [P]>>, PnmError> {
+    pnm::decode_typed(data, P::layout(), &DecodeOptions::default(), &stop)?.into_img()
+}
+
+/// Decode any PNM format to an [`imgref::Img`] with resource limits,
+/// borrowing from `data` when possible.
+#[cfg(feature = "imgref")]
+pub fn decode_img_cow_with_limits<'a, P: DecodePixel>(
+    data: &'a [u8],
+    limits: &Limits,
+    stop: impl Stop,
+) -> Result<imgref::Img<alloc::borrow::Cow<'a, [P]>>, PnmError> {
+    pnm::decode_typed(data, P::layout(), &limits_options(limits), &stop)?.into_img()
+}
+
+/// Decode any PNM format to an [`imgref::Img`] under `options` (see
+/// [`decode_with_options`]), borrowing from `data` when possible.
+#[cfg(feature = "imgref")]
+pub fn decode_img_cow_with_options<'a, P: DecodePixel>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<imgref::Img<alloc::borrow::Cow<'a, [P]>>, PnmError> {
+    pnm::decode_typed(data, P::layout(), options, &stop)?.into_img()
+}
+
 /// Decode BMP to an [`imgref::ImgVec`].
 #[cfg(all(feature = "basic-bmp", feature = "imgref"))]
 pub fn decode_bmp_img<P: DecodePixel>(
     data: &[u8],
     stop: impl Stop,
-) -> Result<imgref::ImgVec<P>, PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<imgref::ImgVec<P>, PnmError> {
     let (pixels, w, h) = decode_bmp_pixels::<P>(data, stop)?;
     Ok(imgref::ImgVec::new(pixels, w as usize, h as usize))
 }
@@ -440,10 +1462,7 @@ pub fn decode_bmp_img_with_limits<P: DecodePixel>(
     data: &[u8],
     limits: &Limits,
     stop: impl Stop,
-) -> Result<imgref::ImgVec<P>, PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<imgref::ImgVec<P>, PnmError> {
     let (pixels, w, h) = decode_bmp_pixels_with_limits::<P>(data, limits, stop)?;
     Ok(imgref::ImgVec::new(pixels, w as usize, h as usize))
 }
@@ -457,11 +1476,21 @@ pub fn decode_into<P: DecodePixel>(
     data: &[u8],
     output: imgref::ImgRefMut<'_, P>,
     stop: impl Stop,
-) -> Result<(), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
-    let decoded = decode(data, stop)?;
+) -> Result<(), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), &DecodeOptions::default(), &stop)?;
+    copy_decoded_into(decoded, output)
+}
+
+/// Decode PNM into an existing [`imgref::ImgRefMut`] buffer under
+/// `options` (see [`decode_with_options`]).
+#[cfg(feature = "imgref")]
+pub fn decode_into_with_options<P: DecodePixel>(
+    data: &[u8],
+    output: imgref::ImgRefMut<'_, P>,
+    options: &DecodeOptions,
+    stop: impl Stop,
+) -> Result<(), PnmError> {
+    let decoded = pnm::decode_typed(data, P::layout(), options, &stop)?;
     copy_decoded_into(decoded, output)
 }
 
@@ -471,10 +1500,7 @@ pub fn decode_bmp_into<P: DecodePixel>(
     data: &[u8],
     output: imgref::ImgRefMut<'_, P>,
     stop: impl Stop,
-) -> Result<(), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<(), PnmError> {
     let decoded = decode_bmp(data, stop)?;
     copy_decoded_into(decoded, output)
 }
@@ -483,10 +1509,7 @@ where
 fn copy_decoded_into<P: DecodePixel>(
     decoded: DecodeOutput<'_>,
     mut output: imgref::ImgRefMut<'_, P>,
-) -> Result<(), PnmError>
-where
-    [u8]: rgb::AsPixels<P>,
-{
+) -> Result<(), PnmError> {
     if !decoded.layout.is_memory_compatible(P::layout()) {
         return Err(PnmError::LayoutMismatch {
             expected: P::layout(),
@@ -504,26 +1527,28 @@ where
             out_h
         )));
     }
-    let src_pixels: &[P] = decoded.pixels().as_pixels();
-    for (src_row, dst_row) in src_pixels.chunks_exact(out_w).zip(output.rows_mut()) {
-        <[P]>::copy_from_slice(dst_row, src_row);
+    let row_bytes = out_w * P::layout().bytes_per_pixel();
+    if row_bytes > 0 {
+        for (src_row, dst_row) in decoded
+            .pixels()
+            .chunks_exact(row_bytes)
+            .zip(output.rows_mut())
+        {
+            pixel_traits::copy_bytes_into(src_row, dst_row);
+        }
     }
     Ok(())
 }
 
 /// Encode an [`imgref::ImgRef`] as PPM (P6).
 ///
-/// Handles arbitrary stride by copying row-by-row when needed.
+/// Rows are read in place at the image stride, so sub-images need no copy.
 #[cfg(feature = "imgref")]
 pub fn encode_ppm_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_ppm(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(&img_pixel_slice(img)?, pnm::PnmFormat::Ppm, &stop)
 }
 
 /// Encode an [`imgref::ImgRef`] as PGM (P5).
@@ -531,12 +1556,8 @@ where
 pub fn encode_pgm_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_pgm(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(&img_pixel_slice(img)?, pnm::PnmFormat::Pgm, &stop)
 }
 
 /// Encode an [`imgref::ImgRef`] as PAM (P7).
@@ -544,12 +1565,8 @@ where
 pub fn encode_pam_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_pam(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(&img_pixel_slice(img)?, pnm::PnmFormat::Pam, &stop)
 }
 
 /// Encode an [`imgref::ImgRef`] as PFM.
@@ -557,12 +1574,8 @@ where
 pub fn encode_pfm_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_pfm(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    pnm::encode(&img_pixel_slice(img)?, pnm::PnmFormat::Pfm, &stop)
 }
 
 /// Encode an [`imgref::ImgRef`] as 24-bit BMP.
@@ -570,12 +1583,13 @@ where
 pub fn encode_bmp_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_bmp(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    bmp::encode(
+        &img_pixel_slice(img)?,
+        false,
+        &ConvertOptions::default(),
+        &stop,
+    )
 }
 
 /// Encode an [`imgref::ImgRef`] as 32-bit BMP (RGBA).
@@ -583,23 +1597,31 @@ where
 pub fn encode_bmp_rgba_img<P: EncodePixel>(
     img: imgref::ImgRef<'_, P>,
     stop: impl Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError>
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let (bytes, w, h) = collect_img_bytes(img);
-    encode_bmp_rgba(&bytes, w, h, P::layout(), stop)
+) -> Result<alloc::vec::Vec<u8>, PnmError> {
+    bmp::encode(
+        &img_pixel_slice(img)?,
+        true,
+        &ConvertOptions::default(),
+        &stop,
+    )
 }
 
-/// Collect image rows into contiguous bytes, handling arbitrary stride.
+/// View an image's rows in place; encoders read them at the image stride.
 #[cfg(feature = "imgref")]
-fn collect_img_bytes<P: EncodePixel>(img: imgref::ImgRef<'_, P>) -> (alloc::vec::Vec<u8>, u32, u32)
-where
-    [P]: rgb::ComponentBytes<u8>,
-{
-    let w = img.width() as u32;
-    let h = img.height() as u32;
-    let pixels: alloc::vec::Vec<P> = img.rows().flat_map(|row| row.iter().copied()).collect();
-    let bytes = pixels.as_bytes().to_vec();
-    (bytes, w, h)
+fn img_pixel_slice<P: EncodePixel>(img: imgref::ImgRef<'_, P>) -> Result<PixelSlice<'_>, PnmError> {
+    let saturate = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+    let too_large = |_| PnmError::DimensionsTooLarge {
+        width: saturate(img.width()),
+        height: saturate(img.height()),
+    };
+    let width = u32::try_from(img.width()).map_err(too_large)?;
+    let height = u32::try_from(img.height()).map_err(too_large)?;
+    let stride = img.stride() * core::mem::size_of::<P>();
+    PixelSlice::strided(
+        pixel_traits::pixels_as_bytes(img.into_buf()),
+        width,
+        height,
+        stride,
+        P::layout(),
+    )
 }
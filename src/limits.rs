@@ -9,9 +9,41 @@ pub struct Limits {
     pub max_pixels: Option<u64>,
     /// Maximum memory bytes for output buffer allocation.
     pub max_memory_bytes: Option<u64>,
+    /// Maximum total bytes of PNM header comments.
+    pub max_comment_bytes: Option<u64>,
 }
 
 impl Limits {
+    /// Set [`max_width`](Self::max_width).
+    pub fn with_max_width(mut self, max_width: u64) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Set [`max_height`](Self::max_height).
+    pub fn with_max_height(mut self, max_height: u64) -> Self {
+        self.max_height = Some(max_height);
+        self
+    }
+
+    /// Set [`max_pixels`](Self::max_pixels).
+    pub fn with_max_pixels(mut self, max_pixels: u64) -> Self {
+        self.max_pixels = Some(max_pixels);
+        self
+    }
+
+    /// Set [`max_memory_bytes`](Self::max_memory_bytes).
+    pub fn with_max_memory_bytes(mut self, max_memory_bytes: u64) -> Self {
+        self.max_memory_bytes = Some(max_memory_bytes);
+        self
+    }
+
+    /// Set [`max_comment_bytes`](Self::max_comment_bytes).
+    pub fn with_max_comment_bytes(mut self, max_comment_bytes: u64) -> Self {
+        self.max_comment_bytes = Some(max_comment_bytes);
+        self
+    }
+
     /// Check dimensions against limits. Returns Ok(()) or LimitExceeded error.
     pub(crate) fn check(&self, width: u32, height: u32) -> Result<(), crate::PnmError> {
         if let Some(max_w) = self.max_width {
@@ -50,4 +82,16 @@ impl Limits {
         }
         Ok(())
     }
+
+    /// Check the total size of header comments against limits.
+    pub(crate) fn check_comments(&self, bytes: usize) -> Result<(), crate::PnmError> {
+        if let Some(max_comments) = self.max_comment_bytes {
+            if bytes as u64 > max_comments {
+                return Err(crate::PnmError::LimitExceeded(alloc::format!(
+                    "header comments of {bytes} bytes exceed limit {max_comments}"
+                )));
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,164 @@
+//! EXIF-style orientation applied inside decode and encode loops.
+//!
+//! Each output row of an oriented image is either a stored row (possibly
+//! reversed) or a stored column. Rows are passed through as one run; columns
+//! are gathered a few pixels at a time into a stack buffer. Either way the
+//! codec converts straight into its output row, so orienting never needs a
+//! second pass over the image.
+
+/// One of the eight EXIF orientations, as the transform that turns the
+/// stored image into the displayed one.
+///
+/// Rotations are clockwise. [`Orientation::from_exif`] maps the EXIF
+/// `Orientation` tag values 1-8 to their variants.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// Unchanged (EXIF 1).
+    #[default]
+    Identity,
+    /// Mirror left-right (EXIF 2).
+    FlipHorizontal,
+    /// Rotate 180° (EXIF 3).
+    Rotate180,
+    /// Mirror top-bottom (EXIF 4).
+    FlipVertical,
+    /// Mirror across the top-left to bottom-right diagonal (EXIF 5).
+    Transpose,
+    /// Rotate 90° clockwise (EXIF 6).
+    Rotate90,
+    /// Mirror across the top-right to bottom-left diagonal (EXIF 7).
+    Transverse,
+    /// Rotate 270° clockwise (EXIF 8).
+    Rotate270,
+}
+
+impl Orientation {
+    /// The orientation for an EXIF `Orientation` tag value, or `None`
+    /// outside 1-8.
+    pub fn from_exif(value: u16) -> Option<Self> {
+        Some(match value {
+            1 => Self::Identity,
+            2 => Self::FlipHorizontal,
+            3 => Self::Rotate180,
+            4 => Self::FlipVertical,
+            5 => Self::Transpose,
+            6 => Self::Rotate90,
+            7 => Self::Transverse,
+            8 => Self::Rotate270,
+            _ => return None,
+        })
+    }
+
+    /// The EXIF `Orientation` tag value (1-8).
+    pub fn to_exif(self) -> u16 {
+        match self {
+            Self::Identity => 1,
+            Self::FlipHorizontal => 2,
+            Self::Rotate180 => 3,
+            Self::FlipVertical => 4,
+            Self::Transpose => 5,
+            Self::Rotate90 => 6,
+            Self::Transverse => 7,
+            Self::Rotate270 => 8,
+        }
+    }
+
+    /// Whether width and height trade places.
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(
+            self,
+            Self::Transpose | Self::Rotate90 | Self::Transverse | Self::Rotate270
+        )
+    }
+
+    /// Size of a `width` x `height` image after this orientation.
+    pub fn output_size(self, width: u32, height: u32) -> (u32, u32) {
+        if self.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// Stored pixel rows: `height` rows of `width` pixels of `bpp` bytes,
+/// `stride` bytes apart, top to bottom unless `bottom_up`.
+///
+/// `data` must hold every row.
+#[derive(Clone, Copy)]
+pub(crate) struct StoredRows<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub bpp: usize,
+    pub stride: usize,
+    pub bottom_up: bool,
+}
+
+/// Size of the stack buffer column pixels are gathered into.
+const GATHER_BYTES: usize = 1024;
+
+impl StoredRows<'_> {
+    /// Pass the stored pixels of row `y` of the image oriented by
+    /// `orientation` to `f(run, first)`, in runs of whole pixels where
+    /// `first` is the output pixel index of the run's first pixel.
+    ///
+    /// Returns `true` when the run was passed in stored order but the
+    /// output row is mirrored: the caller then reverses its output row with
+    /// [`reverse_pixels`].
+    pub(crate) fn read_row(
+        &self,
+        orientation: Orientation,
+        y: usize,
+        mut f: impl FnMut(&[u8], usize),
+    ) -> bool {
+        let (w, h, bpp) = (self.width, self.height, self.bpp);
+        if w == 0 || h == 0 {
+            return false;
+        }
+        // Source pixel of output pixel 0, and the step to the next one
+        let (sx, sy, dx, dy) = match orientation {
+            Orientation::Identity => (0, y, 1, 0),
+            Orientation::FlipHorizontal => (w - 1, y, -1, 0),
+            Orientation::Rotate180 => (w - 1, h - 1 - y, -1, 0),
+            Orientation::FlipVertical => (0, h - 1 - y, 1, 0),
+            Orientation::Transpose => (y, 0, 0, 1),
+            Orientation::Rotate90 => (y, h - 1, 0, -1),
+            Orientation::Transverse => (w - 1 - y, h - 1, 0, -1),
+            Orientation::Rotate270 => (w - 1 - y, 0, 0, 1),
+        };
+        let stored_row = |sy: usize| if self.bottom_up { h - 1 - sy } else { sy };
+        if dy == 0 {
+            let start = stored_row(sy) * self.stride;
+            f(&self.data[start..start + w * bpp], 0);
+            return dx < 0;
+        }
+
+        // A stored column, `h` pixels long
+        let step = if self.bottom_up { -dy } else { dy } * self.stride as isize;
+        let mut pos = (stored_row(sy) * self.stride + sx * bpp) as isize;
+        let mut buf = [0u8; GATHER_BYTES];
+        let chunk = GATHER_BYTES / bpp;
+        let mut first = 0;
+        while first < h {
+            let n = chunk.min(h - first);
+            for px in buf[..n * bpp].chunks_exact_mut(bpp) {
+                let start = pos as usize;
+                px.copy_from_slice(&self.data[start..start + bpp]);
+                pos += step;
+            }
+            f(&buf[..n * bpp], first);
+            first += n;
+        }
+        false
+    }
+}
+
+/// Reverse the order of the `bpp`-byte pixels in `row`.
+pub(crate) fn reverse_pixels(row: &mut [u8], bpp: usize) {
+    row.reverse();
+    for px in row.chunks_exact_mut(bpp) {
+        px.reverse();
+    }
+}
@@ -0,0 +1,49 @@
+//! Row-band splitting for decode and encode loops.
+//!
+//! PNM and BMP rows sit at fixed offsets, so an image can be cut into bands
+//! of whole rows that are processed independently. With the `rayon` feature,
+//! images larger than one band run their bands on the rayon thread pool;
+//! otherwise (and for small images) the whole image is a single band on the
+//! calling thread. Each band does exactly the per-row work the sequential
+//! loop would, so output is byte-identical.
+
+use crate::error::PnmError;
+
+/// Target size of one band, in bytes of output rows.
+#[cfg(feature = "rayon")]
+const BAND_BYTES: usize = 256 * 1024;
+
+/// Call `f(first_row, band, band_rows)` for bands of consecutive rows of
+/// `data`, whose `rows` rows start `stride` bytes apart.
+///
+/// `band` starts at row `first_row` and holds `band_rows` rows (the last row
+/// of the image may be shorter than `stride`). The first error stops the
+/// remaining bands and is returned.
+#[cfg_attr(not(feature = "rayon"), allow(unused_variables))]
+pub(crate) fn for_each_band<F>(
+    data: &mut [u8],
+    stride: usize,
+    rows: usize,
+    f: F,
+) -> Result<(), PnmError>
+where
+    F: Fn(usize, &mut [u8], usize) -> Result<(), PnmError> + Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    {
+        // At least 16 rows, so the per-band `Stop` checks keep their spacing
+        let band_rows = (BAND_BYTES / stride.max(1)).max(16);
+        if stride > 0 && rows > band_rows {
+            use rayon::prelude::*;
+            let len = data.len().min(stride.saturating_mul(rows));
+            return data[..len]
+                .par_chunks_mut(band_rows.saturating_mul(stride))
+                .enumerate()
+                .try_for_each(|(i, band)| {
+                    let first = i * band_rows;
+                    f(first, band, band_rows.min(rows - first))
+                });
+        }
+    }
+    f(0, data, rows)
+}
@@ -1,3 +1,6 @@
+use crate::error::PnmError;
+use crate::orientation::StoredRows;
+
 /// Pixel memory layout.
 #[non_exhaustive]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -10,6 +13,10 @@ pub enum PixelLayout {
     Rgb8,
     /// 4 channels, 8-bit RGBA.
     Rgba8,
+    /// 3 channels, 16-bit RGB (native endian).
+    Rgb16,
+    /// 4 channels, 16-bit RGBA (native endian).
+    Rgba16,
     /// 3 channels, 8-bit BGR.
     Bgr8,
     /// 4 channels, 8-bit BGRA.
@@ -20,6 +27,22 @@ pub enum PixelLayout {
     GrayF32,
     /// 3 channels, 32-bit float RGB.
     RgbF32,
+    /// 4 channels, 32-bit float RGBA (straight alpha).
+    RgbaF32,
+    /// Single channel, 16-bit half-float grayscale (native endian bits).
+    GrayF16,
+    /// 3 channels, 16-bit half-float RGB (native endian bits).
+    RgbF16,
+    /// 4 channels, 16-bit half-float RGBA (native endian bits, straight alpha).
+    RgbaF16,
+    /// 4 channels, 8-bit RGBA with premultiplied alpha.
+    Rgba8Premul,
+    /// 4 channels, 8-bit BGRA with premultiplied alpha.
+    Bgra8Premul,
+    /// 4 channels, 16-bit RGBA with premultiplied alpha (native endian).
+    Rgba16Premul,
+    /// 4 channels, 32-bit float RGBA with premultiplied alpha.
+    RgbaF32Premul,
 }
 
 impl PixelLayout {
@@ -27,23 +50,118 @@ impl PixelLayout {
     pub fn bytes_per_pixel(&self) -> usize {
         match self {
             Self::Gray8 => 1,
-            Self::Gray16 => 2,
+            Self::Gray16 | Self::GrayF16 => 2,
             Self::Rgb8 | Self::Bgr8 => 3,
-            Self::Rgba8 | Self::Bgra8 | Self::Bgrx8 => 4,
+            Self::Rgba8 | Self::Bgra8 | Self::Bgrx8 | Self::Rgba8Premul | Self::Bgra8Premul => 4,
             Self::GrayF32 => 4,
+            Self::Rgb16 | Self::RgbF16 => 6,
+            Self::Rgba16 | Self::RgbaF16 | Self::Rgba16Premul => 8,
             Self::RgbF32 => 12,
+            Self::RgbaF32 | Self::RgbaF32Premul => 16,
         }
     }
 
     /// Number of channels.
     pub fn channels(&self) -> usize {
         match self {
-            Self::Gray8 | Self::Gray16 | Self::GrayF32 => 1,
-            Self::Rgb8 | Self::Bgr8 | Self::RgbF32 => 3,
-            Self::Rgba8 | Self::Bgra8 | Self::Bgrx8 => 4,
+            Self::Gray8 | Self::Gray16 | Self::GrayF32 | Self::GrayF16 => 1,
+            Self::Rgb8 | Self::Rgb16 | Self::Bgr8 | Self::RgbF32 | Self::RgbF16 => 3,
+            Self::Rgba8
+            | Self::Rgba16
+            | Self::Bgra8
+            | Self::Bgrx8
+            | Self::RgbaF32
+            | Self::RgbaF16
+            | Self::Rgba8Premul
+            | Self::Bgra8Premul
+            | Self::Rgba16Premul
+            | Self::RgbaF32Premul => 4,
+        }
+    }
+
+    /// Whether samples are floats (32-bit or half-precision).
+    pub(crate) fn is_float(&self) -> bool {
+        matches!(
+            self,
+            Self::GrayF32
+                | Self::RgbF32
+                | Self::RgbaF32
+                | Self::GrayF16
+                | Self::RgbF16
+                | Self::RgbaF16
+                | Self::RgbaF32Premul
+        )
+    }
+
+    /// Bytes per channel sample.
+    pub(crate) fn bytes_per_sample(&self) -> usize {
+        self.bytes_per_pixel() / self.channels()
+    }
+
+    /// Whether samples are 16-bit unsigned integers.
+    pub(crate) fn is_16bit(&self) -> bool {
+        matches!(
+            self,
+            Self::Gray16 | Self::Rgb16 | Self::Rgba16 | Self::Rgba16Premul
+        )
+    }
+
+    /// Whether this layout carries an alpha channel (`Bgrx8` padding is not alpha).
+    pub fn has_alpha(&self) -> bool {
+        matches!(
+            self,
+            Self::Rgba8
+                | Self::Rgba16
+                | Self::Bgra8
+                | Self::RgbaF32
+                | Self::RgbaF16
+                | Self::Rgba8Premul
+                | Self::Bgra8Premul
+                | Self::Rgba16Premul
+                | Self::RgbaF32Premul
+        )
+    }
+
+    /// Whether color samples are premultiplied by alpha.
+    pub fn is_premultiplied(&self) -> bool {
+        matches!(
+            self,
+            Self::Rgba8Premul | Self::Bgra8Premul | Self::Rgba16Premul | Self::RgbaF32Premul
+        )
+    }
+
+    /// The premultiplied-alpha counterpart of a straight-alpha layout.
+    ///
+    /// Returns `None` for layouts without one (no alpha, or `RgbaF16`).
+    /// Premultiplied layouts return themselves.
+    pub fn premultiplied(&self) -> Option<PixelLayout> {
+        match self {
+            Self::Rgba8 | Self::Rgba8Premul => Some(Self::Rgba8Premul),
+            Self::Bgra8 | Self::Bgra8Premul => Some(Self::Bgra8Premul),
+            Self::Rgba16 | Self::Rgba16Premul => Some(Self::Rgba16Premul),
+            Self::RgbaF32 | Self::RgbaF32Premul => Some(Self::RgbaF32Premul),
+            _ => None,
+        }
+    }
+
+    /// The straight-alpha counterpart of a premultiplied layout.
+    ///
+    /// All other layouts return themselves.
+    pub fn straight(&self) -> PixelLayout {
+        match self {
+            Self::Rgba8Premul => Self::Rgba8,
+            Self::Bgra8Premul => Self::Bgra8,
+            Self::Rgba16Premul => Self::Rgba16,
+            Self::RgbaF32Premul => Self::RgbaF32,
+            other => *other,
         }
     }
 
+    /// Whether this layout is single-channel grayscale.
+    pub fn is_gray(&self) -> bool {
+        self.channels() == 1
+    }
+
     /// Whether this layout has the same memory representation as `other`.
     ///
     /// For example, `Bgra8` and `Bgrx8` are compatible (same 4-byte B,G,R,X/A layout).
@@ -57,3 +175,176 @@ impl PixelLayout {
         )
     }
 }
+
+/// Borrowed input pixels: `height` rows of `width` pixels, `stride` bytes apart.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PixelSlice<'a> {
+    data: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+    stride: usize,
+    pub layout: PixelLayout,
+}
+
+impl<'a> PixelSlice<'a> {
+    /// Rows packed back to back (stride = row size).
+    pub(crate) fn contiguous(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        layout: PixelLayout,
+    ) -> Result<Self, PnmError> {
+        let row_bytes = (width as usize)
+            .checked_mul(layout.bytes_per_pixel())
+            .ok_or(PnmError::DimensionsTooLarge { width, height })?;
+        Self::strided(data, width, height, row_bytes, layout)
+    }
+
+    /// Rows `stride` bytes apart. The last row only needs `width` pixels, so
+    /// sub-images of a larger buffer are accepted as-is.
+    pub(crate) fn strided(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        layout: PixelLayout,
+    ) -> Result<Self, PnmError> {
+        check_strided(data.len(), width, height, stride, layout)?;
+        Ok(Self {
+            data,
+            width,
+            height,
+            stride,
+            layout,
+        })
+    }
+
+    /// The rows, for reading in any [`Orientation`](crate::Orientation).
+    pub(crate) fn stored_rows(&self) -> StoredRows<'a> {
+        StoredRows {
+            data: self.data,
+            width: self.width as usize,
+            height: self.height as usize,
+            bpp: self.layout.bytes_per_pixel(),
+            stride: self.stride,
+            bottom_up: false,
+        }
+    }
+}
+
+/// Borrowed output pixels: `height` rows of `width` pixels, `stride` bytes apart.
+#[derive(Debug)]
+pub(crate) struct PixelSliceMut<'a> {
+    data: &'a mut [u8],
+    pub width: u32,
+    pub height: u32,
+    stride: usize,
+    pub layout: PixelLayout,
+}
+
+impl<'a> PixelSliceMut<'a> {
+    /// Rows `stride` bytes apart; same size rules as [`PixelSlice::strided`].
+    pub(crate) fn strided(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        layout: PixelLayout,
+    ) -> Result<Self, PnmError> {
+        check_strided(data.len(), width, height, stride, layout)?;
+        Ok(Self {
+            data,
+            width,
+            height,
+            stride,
+            layout,
+        })
+    }
+
+    /// Row `y`, without stride padding.
+    pub(crate) fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let start = y * self.stride;
+        let row_bytes = self.width as usize * self.layout.bytes_per_pixel();
+        &mut self.data[start..start + row_bytes]
+    }
+
+    /// Call `f(first_row, band)` for bands of consecutive rows covering the
+    /// image, in parallel with the `rayon` feature.
+    pub(crate) fn for_each_band(
+        &mut self,
+        f: impl Fn(usize, &mut PixelSliceMut<'_>) -> Result<(), PnmError> + Send + Sync,
+    ) -> Result<(), PnmError> {
+        let (width, stride, layout) = (self.width, self.stride, self.layout);
+        crate::parallel::for_each_band(
+            self.data,
+            stride,
+            self.height as usize,
+            |first, data, rows| {
+                f(
+                    first,
+                    &mut PixelSliceMut {
+                        data,
+                        width,
+                        height: rows as u32,
+                        stride,
+                        layout,
+                    },
+                )
+            },
+        )
+    }
+}
+
+/// Check that the `width` x `height` region at (`x`, `y`) lies within an
+/// `image_width` x `image_height` image.
+pub(crate) fn check_region(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    image_width: u32,
+    image_height: u32,
+) -> Result<(), PnmError> {
+    let fits =
+        |start: u32, len: u32, full: u32| start.checked_add(len).is_some_and(|end| end <= full);
+    if fits(x, width, image_width) && fits(y, height, image_height) {
+        return Ok(());
+    }
+    Err(PnmError::InvalidData(alloc::format!(
+        "region {width}x{height} at ({x}, {y}) exceeds the {image_width}x{image_height} image"
+    )))
+}
+
+/// Check that `len` bytes hold `height` rows `stride` bytes apart, the last
+/// one needing only `width` pixels.
+fn check_strided(
+    len: usize,
+    width: u32,
+    height: u32,
+    stride: usize,
+    layout: PixelLayout,
+) -> Result<(), PnmError> {
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = (width as usize)
+        .checked_mul(layout.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    if stride < row_bytes {
+        return Err(PnmError::InvalidData(alloc::format!(
+            "stride {stride} is smaller than a row of {row_bytes} bytes"
+        )));
+    }
+    let needed = match (height as usize).checked_sub(1) {
+        Some(last) => stride
+            .checked_mul(last)
+            .and_then(|n| n.checked_add(row_bytes))
+            .ok_or_else(too_large)?,
+        None => 0,
+    };
+    if len < needed {
+        return Err(PnmError::BufferTooSmall {
+            needed,
+            actual: len,
+        });
+    }
+    Ok(())
+}
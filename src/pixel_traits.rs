@@ -1,19 +1,26 @@
 //! Sealed traits mapping `rgb` crate pixel types to [`PixelLayout`].
 
+use alloc::vec::Vec;
+
 use crate::PixelLayout;
+use crate::error::{PnmError, try_vec};
+use rgb::bytemuck;
 
 mod private {
     pub trait Sealed {}
 }
 
 /// Pixel type that can be decoded from PNM/BMP data.
-pub trait DecodePixel: Copy + 'static + private::Sealed {
+///
+/// Multi-byte samples (`u16`, `f32`) are native-endian, matching the byte
+/// layouts in [`PixelLayout`].
+pub trait DecodePixel: rgb::Pod + private::Sealed {
     /// The [`PixelLayout`] this pixel type corresponds to.
     fn layout() -> PixelLayout;
 }
 
 /// Pixel type that can be encoded to PNM/BMP data.
-pub trait EncodePixel: Copy + 'static + private::Sealed {
+pub trait EncodePixel: rgb::Pod + private::Sealed {
     /// The [`PixelLayout`] this pixel type corresponds to.
     fn layout() -> PixelLayout;
 }
@@ -38,3 +45,31 @@ impl_pixel!(rgb::RGB<u8>, PixelLayout::Rgb8);
 impl_pixel!(rgb::RGBA<u8>, PixelLayout::Rgba8);
 impl_pixel!(rgb::alt::BGR<u8>, PixelLayout::Bgr8);
 impl_pixel!(rgb::alt::BGRA<u8>, PixelLayout::Bgra8);
+impl_pixel!(rgb::Gray<u8>, PixelLayout::Gray8);
+impl_pixel!(rgb::Gray<u16>, PixelLayout::Gray16);
+impl_pixel!(rgb::RGB<u16>, PixelLayout::Rgb16);
+impl_pixel!(rgb::RGBA<u16>, PixelLayout::Rgba16);
+impl_pixel!(rgb::Gray<f32>, PixelLayout::GrayF32);
+impl_pixel!(rgb::RGB<f32>, PixelLayout::RgbF32);
+impl_pixel!(rgb::RGBA<f32>, PixelLayout::RgbaF32);
+
+/// Copy bytes into a new pixel vector.
+///
+/// Goes through the (always aligned) destination, so `bytes` may have any
+/// alignment. `bytes.len()` must be a multiple of the pixel size.
+pub(crate) fn pixels_from_bytes<P: DecodePixel>(bytes: &[u8]) -> Result<Vec<P>, PnmError> {
+    let mut out = try_vec(P::zeroed(), bytes.len() / core::mem::size_of::<P>())?;
+    bytemuck::cast_slice_mut::<P, u8>(&mut out).copy_from_slice(bytes);
+    Ok(out)
+}
+
+/// Copy bytes into existing pixels (any alignment).
+#[cfg(feature = "imgref")]
+pub(crate) fn copy_bytes_into<P: DecodePixel>(bytes: &[u8], dst: &mut [P]) {
+    bytemuck::cast_slice_mut::<P, u8>(dst).copy_from_slice(bytes);
+}
+
+/// View pixels as native-endian bytes.
+pub(crate) fn pixels_as_bytes<P: EncodePixel>(pixels: &[P]) -> &[u8] {
+    bytemuck::cast_slice(pixels)
+}
@@ -3,15 +3,30 @@
 //! Credits: Draws from zune-ppm by Caleb Etemesi (MIT/Apache-2.0/Zlib).
 
 use super::PnmHeader;
-use crate::error::PnmError;
-use crate::pixel::PixelLayout;
+use crate::convert::{ConvertOptions, convert_row};
+use crate::decode::Deviation;
+use crate::error::{PnmError, try_fill};
+use crate::half::{f16_to_f32, f32_to_f16};
+use crate::orientation::{Orientation, StoredRows, reverse_pixels};
+use crate::pixel::{PixelLayout, PixelSliceMut};
 use crate::pnm::PnmFormat;
+use crate::simd::{self, map_chunks};
+use alloc::borrow::Cow;
 use alloc::string::String;
 use alloc::vec::Vec;
 use enough::Stop;
 
 /// Parse header from raw data.
-pub(crate) fn parse_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
+#[cfg(feature = "zencodec")]
+pub(crate) fn parse_header(data: &[u8]) -> Result<PnmHeader<'_>, PnmError> {
+    let header = parse_header_any_maxval(data)?;
+    check_maxval(&header)?;
+    Ok(header)
+}
+
+/// Parse header from raw data, accepting any maxval so that
+/// [`deviations`] can report it.
+pub(crate) fn parse_header_any_maxval(data: &[u8]) -> Result<PnmHeader<'_>, PnmError> {
     if data.len() < 3 {
         return Err(PnmError::UnexpectedEof);
     }
@@ -20,12 +35,12 @@ pub(crate) fn parse_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
         b"P5" => parse_p5_p6_header(data, PnmFormat::Pgm),
         b"P6" => parse_p5_p6_header(data, PnmFormat::Ppm),
         b"P7" => parse_p7_header(data),
-        b"Pf" | b"PF" => parse_pfm_header(data),
+        b"Pf" | b"PF" | b"Ph" | b"PH" => parse_pfm_header(data),
         _ => Err(PnmError::UnrecognizedFormat),
     }
 }
 
-fn parse_p5_p6_header(data: &[u8], format: PnmFormat) -> Result<PnmHeader, PnmError> {
+fn parse_p5_p6_header(data: &[u8], format: PnmFormat) -> Result<PnmHeader<'_>, PnmError> {
     let mut pos = 2;
 
     pos = skip_whitespace_and_comments(data, pos)?;
@@ -40,26 +55,16 @@ fn parse_p5_p6_header(data: &[u8], format: PnmFormat) -> Result<PnmHeader, PnmEr
             "width and height must be non-zero".into(),
         ));
     }
-    if maxval == 0 || maxval > 65535 {
-        return Err(PnmError::InvalidHeader(alloc::format!(
-            "maxval must be 1-65535, got {maxval}"
-        )));
-    }
-
     if new_pos >= data.len() {
         return Err(PnmError::UnexpectedEof);
     }
     let data_offset = new_pos + 1;
 
-    let (depth, layout) = match format {
-        PnmFormat::Pgm => {
-            if maxval <= 255 {
-                (1, PixelLayout::Gray8)
-            } else {
-                (1, PixelLayout::Gray16)
-            }
-        }
-        PnmFormat::Ppm => (3, PixelLayout::Rgb8),
+    let layout = match format {
+        PnmFormat::Pgm if maxval <= 255 => PixelLayout::Gray8,
+        PnmFormat::Pgm => PixelLayout::Gray16,
+        PnmFormat::Ppm if maxval <= 255 => PixelLayout::Rgb8,
+        PnmFormat::Ppm => PixelLayout::Rgb16,
         _ => {
             return Err(PnmError::UnsupportedVariant(alloc::format!(
                 "unexpected format {:?} in P5/P6 parser",
@@ -73,22 +78,22 @@ fn parse_p5_p6_header(data: &[u8], format: PnmFormat) -> Result<PnmHeader, PnmEr
         width,
         height,
         maxval,
-        depth,
         layout,
         pfm_scale: 0.0,
+        stored_samples: false,
+        header: &data[..data_offset],
         data_offset,
     })
 }
 
-fn parse_p7_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
+fn parse_p7_header(data: &[u8]) -> Result<PnmHeader<'_>, PnmError> {
     let mut pos = 2;
     pos = skip_whitespace_and_comments(data, pos)?;
 
     let mut width: Option<u32> = None;
     let mut height: Option<u32> = None;
     let mut depth: Option<u32> = None;
-    let mut maxval: Option<u32> = None;
-    let mut tupltype: Option<String> = None;
+    let mut maxval: Option<&str> = None;
 
     loop {
         let line_end = data[pos..]
@@ -124,13 +129,8 @@ fn parse_p7_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
                     .map_err(|_| PnmError::InvalidHeader("bad DEPTH".into()))?,
             );
         } else if let Some(rest) = line.strip_prefix("MAXVAL ") {
-            maxval = Some(
-                rest.trim()
-                    .parse()
-                    .map_err(|_| PnmError::InvalidHeader("bad MAXVAL".into()))?,
-            );
-        } else if let Some(rest) = line.strip_prefix("TUPLTYPE ") {
-            tupltype = Some(rest.trim().into());
+            // Parsed once TUPLTYPE is known: float tuple types ignore MAXVAL.
+            maxval = Some(rest.trim());
         } else if line.starts_with('#') {
             // comment, skip
         }
@@ -148,7 +148,18 @@ fn parse_p7_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
     let width = width.ok_or_else(|| PnmError::InvalidHeader("missing WIDTH".into()))?;
     let height = height.ok_or_else(|| PnmError::InvalidHeader("missing HEIGHT".into()))?;
     let depth = depth.ok_or_else(|| PnmError::InvalidHeader("missing DEPTH".into()))?;
-    let maxval = maxval.ok_or_else(|| PnmError::InvalidHeader("missing MAXVAL".into()))?;
+    let header = data.get(..pos).unwrap_or(data);
+    let mut tuple_types = pam_tuple_types(header);
+    let tupltype = tuple_types.next();
+    let is_float = tupltype.is_some_and(|t| t.starts_with("FLOAT"));
+    let maxval = if is_float {
+        0
+    } else {
+        maxval
+            .ok_or_else(|| PnmError::InvalidHeader("missing MAXVAL".into()))?
+            .parse()
+            .map_err(|_| PnmError::InvalidHeader("bad MAXVAL".into()))?
+    };
 
     if width == 0 || height == 0 {
         return Err(PnmError::InvalidHeader(
@@ -159,37 +170,76 @@ fn parse_p7_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
         return Err(PnmError::InvalidHeader("DEPTH must be non-zero".into()));
     }
 
-    let layout = match (depth, maxval > 255) {
-        (1, false) => PixelLayout::Gray8,
-        (1, true) => PixelLayout::Gray16,
-        (3, false) => PixelLayout::Rgb8,
-        (3, true) => PixelLayout::Rgb8,
-        (4, false) => PixelLayout::Rgba8,
-        (4, true) => PixelLayout::Rgba8,
-        _ => {
-            return Err(PnmError::UnsupportedVariant(alloc::format!(
-                "PAM DEPTH={depth} not supported"
-            )));
+    let layout = match tupltype {
+        // Float tuple types are one word, so never span several lines
+        Some(_) if is_float && tuple_types.next().is_some() => {
+            let joined = pam_tuple_type(header)?.unwrap_or_default();
+            return Err(unsupported_tuple_type(&joined));
         }
+        Some(tupltype) if is_float => float_pam_layout(tupltype, depth)?,
+        _ => integer_pam_layout(depth, maxval)?,
     };
 
-    let _ = tupltype;
-
     Ok(PnmHeader {
         format: PnmFormat::Pam,
         width,
         height,
         maxval,
-        depth,
         layout,
         pfm_scale: 0.0,
+        stored_samples: false,
+        header,
         data_offset: pos,
     })
 }
 
-fn parse_pfm_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
-    let is_color = data[1] == b'F';
-    let mut pos = 2;
+fn integer_pam_layout(depth: u32, maxval: u32) -> Result<PixelLayout, PnmError> {
+    Ok(match (depth, maxval > 255) {
+        (1, false) => PixelLayout::Gray8,
+        (1, true) => PixelLayout::Gray16,
+        (3, false) => PixelLayout::Rgb8,
+        (3, true) => PixelLayout::Rgb16,
+        (4, false) => PixelLayout::Rgba8,
+        (4, true) => PixelLayout::Rgba16,
+        _ => {
+            return Err(PnmError::UnsupportedVariant(alloc::format!(
+                "PAM DEPTH={depth} not supported"
+            )));
+        }
+    })
+}
+
+/// Map a `FLOAT_*`/`FLOAT16_*` tuple type to a float layout, checking it against DEPTH.
+fn float_pam_layout(tupltype: &str, depth: u32) -> Result<PixelLayout, PnmError> {
+    let layout = match tupltype {
+        "FLOAT_GRAYSCALE" => PixelLayout::GrayF32,
+        "FLOAT_RGB" => PixelLayout::RgbF32,
+        "FLOAT_RGBA" | "FLOAT_RGB_ALPHA" => PixelLayout::RgbaF32,
+        "FLOAT16_GRAYSCALE" => PixelLayout::GrayF16,
+        "FLOAT16_RGB" => PixelLayout::RgbF16,
+        "FLOAT16_RGBA" | "FLOAT16_RGB_ALPHA" => PixelLayout::RgbaF16,
+        _ => return Err(unsupported_tuple_type(tupltype)),
+    };
+    if layout.channels() != depth as usize {
+        return Err(PnmError::InvalidHeader(alloc::format!(
+            "TUPLTYPE {tupltype} requires DEPTH {}, got {depth}",
+            layout.channels()
+        )));
+    }
+    Ok(layout)
+}
+
+fn unsupported_tuple_type(tupltype: &str) -> PnmError {
+    PnmError::UnsupportedVariant(alloc::format!("PAM TUPLTYPE {tupltype} not supported"))
+}
+
+fn parse_pfm_header(data: &[u8]) -> Result<PnmHeader<'_>, PnmError> {
+    let is_color = matches!(data[1], b'F' | b'H');
+    // "Ph"/"PH" are the half-precision counterparts of "Pf"/"PF".
+    let is_half = matches!(data[1], b'h' | b'H');
+    // "PF4"/"PH4" are the de facto 4-channel (RGBA) extension of color PFM.
+    let has_alpha = is_color && data.get(2) == Some(&b'4');
+    let mut pos = if has_alpha { 3 } else { 2 };
 
     pos = skip_whitespace_and_comments(data, pos)?;
     let (width, new_pos) = parse_u32(data, pos)?;
@@ -217,10 +267,13 @@ fn parse_pfm_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
 
     let data_offset = line_end + 1;
 
-    let (depth, layout) = if is_color {
-        (3, PixelLayout::RgbF32)
-    } else {
-        (1, PixelLayout::GrayF32)
+    let layout = match (has_alpha, is_color, is_half) {
+        (true, _, false) => PixelLayout::RgbaF32,
+        (true, _, true) => PixelLayout::RgbaF16,
+        (false, true, false) => PixelLayout::RgbF32,
+        (false, true, true) => PixelLayout::RgbF16,
+        (false, false, false) => PixelLayout::GrayF32,
+        (false, false, true) => PixelLayout::GrayF16,
     };
 
     Ok(PnmHeader {
@@ -228,148 +281,412 @@ fn parse_pfm_header(data: &[u8]) -> Result<PnmHeader, PnmError> {
         width,
         height,
         maxval: 0,
-        depth,
         layout,
         pfm_scale: scale,
+        stored_samples: false,
+        header: data.get(..data_offset).unwrap_or(data),
         data_offset,
     })
 }
 
-/// Decode integer data that needs transformation (non-255 maxval or 16-bit).
-pub(crate) fn decode_integer_transform(
+/// Decode pixel rows into `target` in a single pass, replacing the contents
+/// of `out` (its capacity is reused).
+///
+/// Stored rows are `src_stride` bytes apart; see [`decode_rows_into`].
+pub(crate) fn decode_rows(
     pixel_data: &[u8],
+    src_stride: usize,
     header: &PnmHeader,
-    expected_src: usize,
+    orientation: Orientation,
+    target: PixelLayout,
+    out: &mut Vec<u8>,
     stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let w = header.width as usize;
+) -> Result<(), PnmError> {
+    let (width, height) = orientation.output_size(header.width, header.height);
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let row_bytes = (width as usize)
+        .checked_mul(target.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    let out_size = row_bytes
+        .checked_mul(height as usize)
+        .ok_or_else(too_large)?;
+    try_fill(out, 0, out_size)?;
+    let mut rows = PixelSliceMut::strided(out, width, height, row_bytes, target)?;
+    decode_rows_into(pixel_data, src_stride, header, orientation, &mut rows, stop)
+}
+
+/// Decode pixel rows into `out` (the dimensions of `header` after
+/// `orientation`), without allocating.
+///
+/// Each stored row gets its byte-order fixup, maxval scaling and (for PFM)
+/// bottom-to-top flip, then is converted straight into its output row. Rows
+/// that need both steps go through a small stack buffer a few pixels at a
+/// time, so no intermediate image in the file's own layout exists. Rotated
+/// orientations read stored columns the same way.
+///
+/// Stored rows start `src_stride` bytes apart, which is more than a row of
+/// `header.width` pixels when decoding a region of a wider image.
+pub(crate) fn decode_rows_into(
+    pixel_data: &[u8],
+    src_stride: usize,
+    header: &PnmHeader,
+    orientation: Orientation,
+    out: &mut PixelSliceMut<'_>,
+    stop: &dyn Stop,
+) -> Result<(), PnmError> {
+    debug_assert_eq!(
+        (out.width, out.height),
+        orientation.output_size(header.width, header.height)
+    );
     let h = header.height as usize;
-    let depth = header.depth as usize;
-    let is_16bit = header.maxval > 255;
+    let src_bpp = header.layout.bytes_per_pixel();
+    let too_large = || PnmError::DimensionsTooLarge {
+        width: header.width,
+        height: header.height,
+    };
+    let src_row_bytes = (header.width as usize)
+        .checked_mul(src_bpp)
+        .ok_or_else(too_large)?;
+    debug_assert!(src_stride >= src_row_bytes);
+    // The last row needs no stride padding
+    let src_bytes = match h.checked_sub(1) {
+        Some(last) => src_stride
+            .checked_mul(last)
+            .and_then(|n| n.checked_add(src_row_bytes))
+            .ok_or_else(too_large)?,
+        None => 0,
+    };
+    if pixel_data.len() < src_bytes {
+        return Err(PnmError::UnexpectedEof);
+    }
 
-    if !is_16bit {
-        // Scale from maxval to 255
-        let scale = 255.0 / header.maxval as f32;
-        let mut out = Vec::with_capacity(expected_src);
-        let stop_interval = w.saturating_mul(depth).saturating_mul(16).max(1);
-        for (i, &b) in pixel_data[..expected_src].iter().enumerate() {
-            if i % stop_interval == 0 {
+    let stored = StoredRows {
+        data: pixel_data,
+        width: header.width as usize,
+        height: h,
+        bpp: src_bpp,
+        stride: src_stride,
+        // PFM stores rows bottom-to-top
+        bottom_up: header.format == PnmFormat::Pfm,
+    };
+    let dst_bpp = out.layout.bytes_per_pixel();
+    out.for_each_band(|first, band| {
+        let target = band.layout;
+        for i in 0..band.height as usize {
+            if i % 16 == 0 {
                 stop.check()?;
             }
-            out.push((b as f32 * scale + 0.5) as u8);
+            let dst_row = band.row_mut(i);
+            let reversed = stored.read_row(orientation, first + i, |src, first| {
+                let dst = &mut dst_row[first * dst_bpp..][..src.len() / src_bpp * dst_bpp];
+                decode_run(src, header, dst, target);
+            });
+            if reversed {
+                reverse_pixels(dst_row, dst_bpp);
+            }
         }
-        Ok(out)
+        Ok(())
+    })
+}
+
+/// Decode a run of whole stored pixels into `dst` as `target`.
+fn decode_run(src: &[u8], header: &PnmHeader, dst: &mut [u8], target: PixelLayout) {
+    let options = ConvertOptions::default();
+    if target == header.layout {
+        decode_row(src, header, dst);
+    } else if header.maxval == 255 {
+        // maxval 255 samples are already in `header.layout` and convert directly
+        convert_row(src, header.layout, dst, target, &options);
     } else {
-        match header.layout {
-            PixelLayout::Gray16 => Ok(pixel_data[..expected_src].to_vec()),
-            _ => {
-                let num_samples = w
-                    .checked_mul(h)
-                    .and_then(|wh| wh.checked_mul(depth))
-                    .ok_or(PnmError::DimensionsTooLarge {
-                        width: header.width,
-                        height: header.height,
-                    })?;
-                // Verify 2*num_samples fits and data is sufficient
-                let needed_bytes =
-                    num_samples
-                        .checked_mul(2)
-                        .ok_or(PnmError::DimensionsTooLarge {
-                            width: header.width,
-                            height: header.height,
-                        })?;
-                if pixel_data.len() < needed_bytes {
-                    return Err(PnmError::UnexpectedEof);
-                }
-                let scale = 255.0 / header.maxval as f32;
-                let stop_interval = w.saturating_mul(depth).saturating_mul(16).max(1);
-                let mut out = Vec::with_capacity(num_samples);
-                for i in 0..num_samples {
-                    if i % stop_interval == 0 {
-                        stop.check()?;
-                    }
-                    let hi = pixel_data[i * 2] as u16;
-                    let lo = pixel_data[i * 2 + 1] as u16;
-                    let val = (hi << 8) | lo;
-                    out.push((val as f32 * scale + 0.5) as u8);
+        let src_bpp = header.layout.bytes_per_pixel();
+        let chunk_pixels = SCRATCH_BYTES / src_bpp;
+        let mut scratch = [0u8; SCRATCH_BYTES];
+        for (src, dst) in src
+            .chunks(chunk_pixels * src_bpp)
+            .zip(dst.chunks_mut(chunk_pixels * target.bytes_per_pixel()))
+        {
+            let scratch = &mut scratch[..src.len()];
+            decode_row(src, header, scratch);
+            convert_row(scratch, header.layout, dst, target, &options);
+            // Stored samples are opaque at maxval, not at 65535
+            if header.stored_samples && target.has_alpha() && !header.layout.has_alpha() {
+                let opaque = (header.maxval as u16).to_ne_bytes();
+                let bpp = target.bytes_per_pixel();
+                for pixel in dst.chunks_exact_mut(bpp) {
+                    pixel[bpp - 2..].copy_from_slice(&opaque);
                 }
-                Ok(out)
             }
         }
     }
 }
 
-/// Decode PFM float pixel data.
-pub(crate) fn decode_pfm(
-    pixel_data: &[u8],
-    header: &PnmHeader,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let w = header.width as usize;
-    let h = header.height as usize;
-    let depth = header.depth as usize;
-    let num_floats = w
-        .checked_mul(h)
-        .and_then(|wh| wh.checked_mul(depth))
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: header.width,
-            height: header.height,
-        })?;
-    let expected_bytes = num_floats
-        .checked_mul(4)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: header.width,
-            height: header.height,
-        })?;
-
-    if pixel_data.len() < expected_bytes {
-        return Err(PnmError::UnexpectedEof);
+/// Size of the stack buffer rows are decoded through before conversion.
+const SCRATCH_BYTES: usize = 1024;
+
+/// Decode stored samples (a row, or a run of whole pixels from one) into
+/// native-endian samples of `header.layout`.
+pub(crate) fn decode_row(src: &[u8], header: &PnmHeader, dst: &mut [u8]) {
+    let sample_bytes = header.layout.bytes_per_sample();
+    if header.format == PnmFormat::Pfm {
+        // Negative scale = little-endian samples
+        let is_little_endian = header.pfm_scale < 0.0;
+        let scale = header.pfm_scale.abs();
+        if sample_bytes == 2 {
+            for (c, d) in src.chunks_exact(2).zip(dst.chunks_exact_mut(2)) {
+                let bits = if is_little_endian {
+                    u16::from_le_bytes([c[0], c[1]])
+                } else {
+                    u16::from_be_bytes([c[0], c[1]])
+                };
+                // Only round-trip through f32 when the scale changes values
+                let bits = if scale == 1.0 {
+                    bits
+                } else {
+                    f32_to_f16(f16_to_f32(bits) * scale)
+                };
+                d.copy_from_slice(&bits.to_ne_bytes());
+            }
+        } else {
+            for (c, d) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+                let raw = if is_little_endian {
+                    f32::from_le_bytes([c[0], c[1], c[2], c[3]])
+                } else {
+                    f32::from_be_bytes([c[0], c[1], c[2], c[3]])
+                };
+                d.copy_from_slice(&(raw * scale).to_ne_bytes());
+            }
+        }
+    } else if header.layout.is_float() || header.maxval == 65535 {
+        // Big-endian samples that need no scaling
+        simd::dispatch(|| match sample_bytes {
+            2 => map_chunks::<2, 2>(src, dst, |c| u16::from_be_bytes(c).to_ne_bytes()),
+            _ => map_chunks::<4, 4>(src, dst, |c| u32::from_be_bytes(c).to_ne_bytes()),
+        });
+    } else if header.stored_samples && header.maxval > 255 {
+        // Big-endian samples → native endian, clamped to maxval
+        let maxval = header.maxval as u16;
+        simd::dispatch(|| {
+            map_chunks::<2, 2>(src, dst, |c| {
+                u16::from_be_bytes(c).min(maxval).to_ne_bytes()
+            })
+        });
+    } else if header.maxval > 255 {
+        // Big-endian samples → native endian, scaled from maxval to 65535
+        let maxval = header.maxval;
+        simd::dispatch(|| {
+            map_chunks::<2, 2>(src, dst, |c| {
+                let v = u32::from(u16::from_be_bytes(c));
+                let scaled = ((v * 65535 + maxval / 2) / maxval).min(65535);
+                (scaled as u16).to_ne_bytes()
+            })
+        });
+    } else if header.maxval == 255 {
+        dst.copy_from_slice(src);
+    } else {
+        // Scale from maxval to 255
+        let scale = 255.0 / header.maxval as f32;
+        simd::dispatch(|| map_chunks::<1, 1>(src, dst, |[b]| [(b as f32 * scale + 0.5) as u8]));
     }
+}
+
+/// Keyword and trimmed value of each line of a PAM header, without the
+/// magic number, comments or `ENDHDR`.
+pub(crate) fn pam_lines(header: &[u8]) -> impl Iterator<Item = (&str, &str)> {
+    header.split(|&b| b == b'\n').skip(1).filter_map(|line| {
+        let line = core::str::from_utf8(line).ok()?.trim();
+        if line.is_empty() || line.starts_with('#') || line == "ENDHDR" {
+            return None;
+        }
+        Some(match line.split_once(|c: char| c.is_ascii_whitespace()) {
+            Some((keyword, value)) => (keyword, value.trim()),
+            None => (line, ""),
+        })
+    })
+}
 
-    let is_little_endian = header.pfm_scale < 0.0;
-    let scale = header.pfm_scale.abs();
+/// The values of the PAM `TUPLTYPE` lines, in header order.
+fn pam_tuple_types(header: &[u8]) -> impl Iterator<Item = &str> {
+    pam_lines(header)
+        .filter(|&(keyword, _)| keyword == "TUPLTYPE")
+        .map(|(_, value)| value)
+}
 
-    let mut out = Vec::with_capacity(expected_bytes);
-    let row_floats = w.checked_mul(depth).ok_or(PnmError::DimensionsTooLarge {
+/// The PAM `TUPLTYPE`: the values of every `TUPLTYPE` line, joined by
+/// spaces as the format specifies. Only several lines allocate.
+pub(crate) fn pam_tuple_type(header: &[u8]) -> Result<Option<Cow<'_, str>>, PnmError> {
+    let mut lines = pam_tuple_types(header);
+    let Some(first) = lines.next() else {
+        return Ok(None);
+    };
+    if lines.next().is_none() {
+        return Ok(Some(Cow::Borrowed(first)));
+    }
+    let len = pam_tuple_types(header)
+        .map(|value| value.len() + 1)
+        .sum::<usize>()
+        - 1;
+    let mut joined = String::new();
+    joined
+        .try_reserve_exact(len)
+        .map_err(|_| PnmError::AllocationFailed { bytes: len })?;
+    for (i, value) in pam_tuple_types(header).enumerate() {
+        if i > 0 {
+            joined.push(' ');
+        }
+        joined.push_str(value);
+    }
+    Ok(Some(Cow::Owned(joined)))
+}
+
+/// The `#` comments in PNM header bytes, without the `#`, one following
+/// space, or the line ending.
+///
+/// PAM comments are whole lines starting with `#`; in the other formats a
+/// comment runs from any `#` to the end of the line.
+pub(crate) fn header_comments(header: &[u8], pam: bool) -> impl Iterator<Item = &[u8]> {
+    // The last byte of a P5/P6/PFM header separates it from the pixel data,
+    // whatever it is
+    let header = match header.split_last() {
+        Some((_, rest)) if !pam => rest,
+        _ => header,
+    };
+    header.split(|&b| b == b'\n').filter_map(move |line| {
+        let start = if pam {
+            let trimmed = line.trim_ascii_start();
+            if trimmed.first() != Some(&b'#') {
+                return None;
+            }
+            line.len() - trimmed.len()
+        } else {
+            line.iter().position(|&b| b == b'#')?
+        };
+        let comment = &line[start + 1..];
+        let comment = comment.strip_prefix(b" ").unwrap_or(comment);
+        Some(comment.strip_suffix(b"\r").unwrap_or(comment))
+    })
+}
+
+/// Reject a maxval outside 1-65535; float formats have none.
+pub(crate) fn check_maxval(header: &PnmHeader) -> Result<(), PnmError> {
+    if maxval_in_range(header) {
+        return Ok(());
+    }
+    Err(PnmError::InvalidHeader(alloc::format!(
+        "maxval must be 1-65535, got {}",
+        header.maxval
+    )))
+}
+
+fn maxval_in_range(header: &PnmHeader) -> bool {
+    header.layout.is_float() || (1..=65535).contains(&header.maxval)
+}
+
+/// The [`Deviation`]s of a file with `header` followed by `pixel_data`: an
+/// out-of-range maxval first, the rest in file order.
+///
+/// Fails with [`PnmError::DimensionsTooLarge`] if the image size overflows
+/// `usize`.
+pub(crate) fn deviations(
+    header: &PnmHeader,
+    pixel_data: &[u8],
+) -> Result<Vec<Deviation>, PnmError> {
+    let too_large = || PnmError::DimensionsTooLarge {
         width: header.width,
         height: header.height,
-    })?;
-    let row_bytes = row_floats
-        .checked_mul(4)
-        .ok_or(PnmError::DimensionsTooLarge {
-            width: header.width,
-            height: header.height,
-        })?;
-
-    // PFM stores rows bottom-to-top
-    for row in (0..h).rev() {
-        if row % 16 == 0 {
-            stop.check()?;
+    };
+    let row_bytes = (header.width as usize)
+        .checked_mul(header.layout.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    let expected = row_bytes
+        .checked_mul(header.height as usize)
+        .ok_or_else(too_large)?;
+    let mut found = Vec::new();
+    let maxval_ok = maxval_in_range(header);
+    if !maxval_ok {
+        found.push(Deviation::MaxvalOutOfRange {
+            maxval: header.maxval,
+        });
+    }
+    match header.format {
+        PnmFormat::Pgm | PnmFormat::Ppm => {
+            let end = header.header.len() - 1;
+            if !matches!(header.header[end], b' ' | b'\t' | b'\n' | b'\r') {
+                found.push(Deviation::UnusualWhitespace { offset: end });
+            }
         }
-        let row_start = row * row_bytes;
-        for i in 0..row_floats {
-            let offset = row_start + i * 4;
-            let raw = if is_little_endian {
-                f32::from_le_bytes([
-                    pixel_data[offset],
-                    pixel_data[offset + 1],
-                    pixel_data[offset + 2],
-                    pixel_data[offset + 3],
-                ])
-            } else {
-                f32::from_be_bytes([
-                    pixel_data[offset],
-                    pixel_data[offset + 1],
-                    pixel_data[offset + 2],
-                    pixel_data[offset + 3],
-                ])
-            };
-            let val = raw * scale;
-            out.extend_from_slice(&val.to_ne_bytes());
+        PnmFormat::Pam => {
+            let mut offset = 0;
+            for (i, line) in header.header.split(|&b| b == b'\n').enumerate() {
+                let trimmed = line.trim_ascii();
+                if trimmed.len() != line.len() {
+                    found.push(Deviation::UnusualWhitespace { offset });
+                }
+                let keyword = trimmed.split(u8::is_ascii_whitespace).next();
+                let known = matches!(
+                    keyword,
+                    Some(
+                        b"" | b"WIDTH" | b"HEIGHT" | b"DEPTH" | b"MAXVAL" | b"TUPLTYPE" | b"ENDHDR"
+                    )
+                );
+                // The first line is the magic number
+                if i > 0 && !known && !trimmed.starts_with(b"#") {
+                    found.push(Deviation::UnknownPamField { offset });
+                }
+                offset += line.len() + 1;
+            }
+        }
+        PnmFormat::Pfm => {
+            if let Some(offset) = header.header.iter().position(|&b| b == b'\r') {
+                found.push(Deviation::UnusualWhitespace { offset });
+            }
+        }
+    }
+
+    let rows = pixel_data.len().min(expected) / row_bytes;
+    if maxval_ok {
+        if let Some(deviation) = first_sample_over_maxval(header, &pixel_data[..rows * row_bytes]) {
+            found.push(deviation);
         }
     }
+    if pixel_data.len() < expected {
+        found.push(Deviation::TruncatedRows {
+            rows: rows as u32,
+            expected: header.height,
+        });
+    } else if pixel_data.len() > expected {
+        found.push(Deviation::TrailingData {
+            bytes: pixel_data.len() - expected,
+        });
+    }
+    Ok(found)
+}
 
-    Ok(out)
+/// The first stored sample in `rows` larger than the header's maxval.
+pub(crate) fn first_sample_over_maxval(header: &PnmHeader, rows: &[u8]) -> Option<Deviation> {
+    let maxval = header.maxval;
+    if header.layout.is_float() || maxval == 255 || maxval == 65535 {
+        return None;
+    }
+    let (index, value) = if header.layout.bytes_per_sample() == 2 {
+        rows.chunks_exact(2)
+            .map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])))
+            .enumerate()
+            .find(|&(_, v)| v > maxval)?
+    } else {
+        rows.iter()
+            .map(|&b| u32::from(b))
+            .enumerate()
+            .find(|&(_, v)| v > maxval)?
+    };
+    let channels = header.layout.channels();
+    let pixel = index / channels;
+    let width = header.width as usize;
+    Some(Deviation::SampleExceedsMaxval {
+        x: (pixel % width) as u32,
+        y: (pixel / width) as u32,
+        channel: (index % channels) as u32,
+        value,
+    })
 }
 
 fn skip_whitespace_and_comments(data: &[u8], mut pos: usize) -> Result<usize, PnmError> {
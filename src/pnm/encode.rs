@@ -1,264 +1,294 @@
 //! PNM encoder: P5, P6, P7, PFM.
 //!
+//! Input in any [`PixelLayout`] is converted row by row (see [`crate::convert`])
+//! to the closest layout the target format can store.
+//!
 //! Credits: Draws from zune-ppm by Caleb Etemesi (MIT/Apache-2.0/Zlib).
 
 use super::PnmFormat;
-use crate::error::PnmError;
-use crate::pixel::PixelLayout;
-use alloc::format;
+use crate::convert::{ConvertOptions, convert_row};
+use crate::encode::{HeaderOptions, MAX_HEADER_BYTES};
+use crate::error::{PnmError, try_vec};
+use crate::orientation::{Orientation, reverse_pixels};
+use crate::parallel;
+use crate::pixel::{PixelLayout, PixelSlice};
 use alloc::vec::Vec;
+use core::fmt::{self, Write};
 use enough::Stop;
 
-/// Encode pixels to PNM format.
+/// Encode pixels to PNM format, with `orientation` applied while writing and
+/// `header` added to the header.
 pub(crate) fn encode_pnm(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    layout: PixelLayout,
+    src: &PixelSlice<'_>,
     fmt: PnmFormat,
+    options: &ConvertOptions,
+    orientation: Orientation,
+    header: &HeaderOptions<'_>,
     stop: &dyn Stop,
 ) -> Result<Vec<u8>, PnmError> {
-    let w = width as usize;
+    // Fail before allocating when the conversion or header is rejected
+    let target = output_layout(src.layout, fmt);
+    options.check(src.layout, target)?;
+    let (width, height) = orientation.output_size(src.width, src.height);
+    check_header(fmt, width, height, target, header)?;
+    let len = encoded_len(width, height, src.layout, fmt, header)?;
+    let mut out = try_vec(0u8, len)?;
+    encode_pnm_into(src, fmt, options, orientation, header, &mut out, stop)?;
+    Ok(out)
+}
+
+/// Encode pixels to PNM format into `out`, returning the bytes written.
+///
+/// Does not allocate; `out` must hold [`encoded_len`] bytes of the oriented
+/// size.
+pub(crate) fn encode_pnm_into(
+    src: &PixelSlice<'_>,
+    fmt: PnmFormat,
+    options: &ConvertOptions,
+    orientation: Orientation,
+    header: &HeaderOptions<'_>,
+    out: &mut [u8],
+    stop: &dyn Stop,
+) -> Result<usize, PnmError> {
+    let layout = src.layout;
+    let (width, height) = orientation.output_size(src.width, src.height);
     let h = height as usize;
-    let expected = w
-        .checked_mul(h)
-        .and_then(|wh| wh.checked_mul(layout.bytes_per_pixel()))
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    if pixels.len() < expected {
+
+    stop.check()?;
+
+    let target = output_layout(layout, fmt);
+    options.check(layout, target)?;
+    check_header(fmt, width, height, target, header)?;
+    let len = encoded_len(width, height, layout, fmt, header)?;
+    if out.len() < len {
         return Err(PnmError::BufferTooSmall {
-            needed: expected,
-            actual: pixels.len(),
+            needed: len,
+            actual: out.len(),
         });
     }
+    let actual = out.len();
+    let mut writer = SliceWriter { buf: out, pos: 0 };
+    write_header(&mut writer, fmt, width, height, target, header).map_err(|_| {
+        PnmError::BufferTooSmall {
+            needed: len,
+            actual,
+        }
+    })?;
+    let header_len = writer.pos;
 
-    stop.check()?;
+    // PNM/PAM samples are big-endian; PFM is written little-endian (scale -1.0)
+    let big_endian = fmt != PnmFormat::Pfm;
+    let (src_bpp, dst_bpp) = (layout.bytes_per_pixel(), target.bytes_per_pixel());
+    let row_bytes = width as usize * dst_bpp;
+    let stored = src.stored_rows();
+    let rows = &mut out[header_len..len];
+    parallel::for_each_band(rows, row_bytes, h, |first, band, band_rows| {
+        for j in 0..band_rows {
+            if j % 16 == 0 {
+                stop.check()?;
+            }
+            let i = first + j;
+            // PFM stores bottom-to-top
+            let row = if fmt == PnmFormat::Pfm { h - 1 - i } else { i };
+            let dst_row = &mut band[j * row_bytes..(j + 1) * row_bytes];
+            let reversed = stored.read_row(orientation, row, |run, first| {
+                let dst = &mut dst_row[first * dst_bpp..][..run.len() / src_bpp * dst_bpp];
+                write_row(dst, run, layout, target, options, big_endian);
+            });
+            if reversed {
+                reverse_pixels(dst_row, dst_bpp);
+            }
+        }
+        Ok(())
+    })?;
 
-    match fmt {
-        PnmFormat::Pgm => encode_pgm(pixels, width, height, w, h, layout, stop),
-        PnmFormat::Ppm => encode_ppm(pixels, width, height, w, h, layout, stop),
-        PnmFormat::Pam => encode_pam(pixels, width, height, w, h, layout, stop),
-        PnmFormat::Pfm => encode_pfm(pixels, width, height, w, h, layout, stop),
-    }
+    Ok(len)
 }
 
-fn encode_pgm(
-    pixels: &[u8],
+/// Exact encoded size (header and pixels) of a `width` x `height` image in
+/// `layout` written as `fmt` with `header` added.
+pub(crate) fn encoded_len(
     width: u32,
     height: u32,
-    w: usize,
-    h: usize,
     layout: PixelLayout,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let header = format!("P5\n{width} {height}\n255\n");
-    let mut out = Vec::with_capacity(header.len() + w * h);
-    out.extend_from_slice(header.as_bytes());
-
-    match layout {
-        PixelLayout::Gray8 => {
-            out.extend_from_slice(&pixels[..w * h]);
-        }
-        PixelLayout::Rgb8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 3;
-                let r = pixels[off] as u32;
-                let g = pixels[off + 1] as u32;
-                let b = pixels[off + 2] as u32;
-                out.push(((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8);
-            }
-        }
-        PixelLayout::Bgr8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 3;
-                let b = pixels[off] as u32;
-                let g = pixels[off + 1] as u32;
-                let r = pixels[off + 2] as u32;
-                out.push(((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8);
-            }
-        }
-        PixelLayout::Rgba8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 4;
-                let r = pixels[off] as u32;
-                let g = pixels[off + 1] as u32;
-                let b = pixels[off + 2] as u32;
-                out.push(((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8);
-            }
-        }
-        PixelLayout::Bgra8 | PixelLayout::Bgrx8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 4;
-                let b = pixels[off] as u32;
-                let g = pixels[off + 1] as u32;
-                let r = pixels[off + 2] as u32;
-                out.push(((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8);
-            }
-        }
-        _ => {
-            return Err(PnmError::UnsupportedVariant(format!(
-                "cannot encode {:?} as PGM",
-                layout
-            )));
-        }
-    }
-
-    Ok(out)
+    fmt: PnmFormat,
+    header: &HeaderOptions<'_>,
+) -> Result<usize, PnmError> {
+    let target = output_layout(layout, fmt);
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|wh| wh.checked_mul(target.bytes_per_pixel()))
+        .and_then(|n| n.checked_add(header_len(fmt, width, height, target, header)))
+        .ok_or(PnmError::DimensionsTooLarge { width, height })
 }
 
-fn encode_ppm(
-    pixels: &[u8],
+/// Size of the header [`write_header`] writes.
+fn header_len(
+    fmt: PnmFormat,
     width: u32,
     height: u32,
-    w: usize,
-    h: usize,
     layout: PixelLayout,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let header = format!("P6\n{width} {height}\n255\n");
-    let mut out = Vec::with_capacity(header.len() + w * h * 3);
-    out.extend_from_slice(header.as_bytes());
+    header: &HeaderOptions<'_>,
+) -> usize {
+    let mut counter = ByteCounter(0);
+    // Counting cannot fail
+    let _ = write_header(&mut counter, fmt, width, height, layout, header);
+    counter.0
+}
 
-    match layout {
-        PixelLayout::Rgb8 => {
-            out.extend_from_slice(&pixels[..w * h * 3]);
-        }
-        PixelLayout::Bgr8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 3;
-                out.push(pixels[off + 2]);
-                out.push(pixels[off + 1]);
-                out.push(pixels[off]);
-            }
-        }
-        PixelLayout::Rgba8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 4;
-                out.push(pixels[off]);
-                out.push(pixels[off + 1]);
-                out.push(pixels[off + 2]);
-            }
-        }
-        PixelLayout::Bgra8 | PixelLayout::Bgrx8 => {
-            for i in 0..(w * h) {
-                if i % w.saturating_mul(16).max(1) == 0 {
-                    stop.check()?;
-                }
-                let off = i * 4;
-                out.push(pixels[off + 2]);
-                out.push(pixels[off + 1]);
-                out.push(pixels[off]);
-            }
-        }
-        PixelLayout::Gray8 => {
-            for &g in &pixels[..w * h] {
-                out.push(g);
-                out.push(g);
-                out.push(g);
-            }
-        }
-        _ => {
-            return Err(PnmError::UnsupportedVariant(format!(
-                "cannot encode {:?} as PPM",
-                layout
-            )));
-        }
+/// Reject `header` content that would break the header or grow it past
+/// [`MAX_HEADER_BYTES`].
+fn check_header(
+    fmt: PnmFormat,
+    width: u32,
+    height: u32,
+    layout: PixelLayout,
+    header: &HeaderOptions<'_>,
+) -> Result<(), PnmError> {
+    header.check(fmt)?;
+    let len = header_len(fmt, width, height, layout, header);
+    if len > MAX_HEADER_BYTES {
+        return Err(PnmError::LimitExceeded(alloc::format!(
+            "header of {len} bytes exceeds the {MAX_HEADER_BYTES}-byte limit"
+        )));
     }
+    Ok(())
+}
 
-    Ok(out)
+/// The layout `fmt` stores for input in `layout`.
+///
+/// 16-bit input keeps 16-bit samples in PGM/PPM/PAM; float input keeps its
+/// precision in PAM and PFM. BGR orders become RGB, `Bgrx8` padding is dropped,
+/// and premultiplied alpha is stored straight (neither format has a
+/// premultiplied variant).
+fn output_layout(layout: PixelLayout, fmt: PnmFormat) -> PixelLayout {
+    match fmt {
+        PnmFormat::Pgm if layout.is_16bit() => PixelLayout::Gray16,
+        PnmFormat::Pgm => PixelLayout::Gray8,
+        PnmFormat::Ppm if layout.is_16bit() => PixelLayout::Rgb16,
+        PnmFormat::Ppm => PixelLayout::Rgb8,
+        PnmFormat::Pam => match layout {
+            PixelLayout::Bgr8 | PixelLayout::Bgrx8 => PixelLayout::Rgb8,
+            PixelLayout::Bgra8 | PixelLayout::Bgra8Premul => PixelLayout::Rgba8,
+            other => other.straight(),
+        },
+        PnmFormat::Pfm if layout.is_float() => layout.straight(),
+        PnmFormat::Pfm if layout.is_gray() => PixelLayout::GrayF32,
+        PnmFormat::Pfm if layout.has_alpha() => PixelLayout::RgbaF32,
+        PnmFormat::Pfm => PixelLayout::RgbF32,
+    }
 }
 
-fn encode_pam(
-    pixels: &[u8],
+/// Write the header of `fmt`, with the comments of `header` right after the
+/// magic number and its PAM fields before `ENDHDR`.
+fn write_header(
+    w: &mut impl Write,
+    fmt: PnmFormat,
     width: u32,
     height: u32,
-    w: usize,
-    h: usize,
     layout: PixelLayout,
-    _stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let (depth, tupltype, maxval) = match layout {
-        PixelLayout::Gray8 => (1, "GRAYSCALE", 255),
-        PixelLayout::Gray16 => (1, "GRAYSCALE", 65535),
-        PixelLayout::Rgb8 => (3, "RGB", 255),
-        PixelLayout::Rgba8 => (4, "RGB_ALPHA", 255),
-        _ => {
-            return Err(PnmError::UnsupportedVariant(format!(
-                "cannot encode {:?} as PAM directly; convert to RGB/RGBA first",
-                layout
-            )));
-        }
+    header: &HeaderOptions<'_>,
+) -> fmt::Result {
+    let magic = match fmt {
+        PnmFormat::Pgm => "P5",
+        PnmFormat::Ppm => "P6",
+        PnmFormat::Pam => "P7",
+        PnmFormat::Pfm => match layout {
+            PixelLayout::GrayF32 => "Pf",
+            PixelLayout::RgbaF32 => "PF4",
+            PixelLayout::GrayF16 => "Ph",
+            PixelLayout::RgbF16 => "PH",
+            PixelLayout::RgbaF16 => "PH4",
+            _ => "PF",
+        },
     };
+    writeln!(w, "{magic}")?;
+    for comment in header.comments {
+        if comment.is_empty() {
+            w.write_str("#\n")?;
+        } else {
+            writeln!(w, "# {comment}")?;
+        }
+    }
 
-    let header = format!(
-        "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH {depth}\nMAXVAL {maxval}\nTUPLTYPE {tupltype}\nENDHDR\n"
-    );
+    let maxval = if layout.is_16bit() { 65535 } else { 255 };
+    match fmt {
+        PnmFormat::Pgm | PnmFormat::Ppm => write!(w, "{width} {height}\n{maxval}\n"),
+        PnmFormat::Pam => {
+            let depth = layout.channels();
+            let (tupltype, maxval) = match layout {
+                PixelLayout::GrayF32 => ("FLOAT_GRAYSCALE", 1),
+                PixelLayout::RgbF32 => ("FLOAT_RGB", 1),
+                PixelLayout::RgbaF32 => ("FLOAT_RGBA", 1),
+                PixelLayout::GrayF16 => ("FLOAT16_GRAYSCALE", 1),
+                PixelLayout::RgbF16 => ("FLOAT16_RGB", 1),
+                PixelLayout::RgbaF16 => ("FLOAT16_RGBA", 1),
+                _ if layout.is_gray() => ("GRAYSCALE", maxval),
+                _ if layout.has_alpha() => ("RGB_ALPHA", maxval),
+                _ => ("RGB", maxval),
+            };
+            write!(
+                w,
+                "WIDTH {width}\nHEIGHT {height}\nDEPTH {depth}\nMAXVAL {maxval}\nTUPLTYPE {tupltype}\n"
+            )?;
+            for (keyword, value) in header.pam_fields {
+                if value.is_empty() {
+                    writeln!(w, "{keyword}")?;
+                } else {
+                    writeln!(w, "{keyword} {value}")?;
+                }
+            }
+            w.write_str("ENDHDR\n")
+        }
+        // Negative scale = little-endian samples
+        PnmFormat::Pfm => write!(w, "{width} {height}\n-1.0\n"),
+    }
+}
 
-    let pixel_bytes = w * h * layout.bytes_per_pixel();
-    let mut out = Vec::with_capacity(header.len() + pixel_bytes);
-    out.extend_from_slice(header.as_bytes());
-    // PAM is a direct copy of the pixel data — zero transformation
-    out.extend_from_slice(&pixels[..pixel_bytes]);
+/// Counts the bytes written to it.
+struct ByteCounter(usize);
 
-    Ok(out)
+impl Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
 }
 
-fn encode_pfm(
-    pixels: &[u8],
-    width: u32,
-    height: u32,
-    w: usize,
-    h: usize,
-    layout: PixelLayout,
-    stop: &dyn Stop,
-) -> Result<Vec<u8>, PnmError> {
-    let (magic, depth) = match layout {
-        PixelLayout::GrayF32 => ("Pf", 1),
-        PixelLayout::RgbF32 => ("PF", 3),
-        _ => {
-            return Err(PnmError::UnsupportedVariant(format!(
-                "PFM requires GrayF32 or RgbF32, got {:?}",
-                layout
-            )));
-        }
-    };
+/// Writes into a byte slice, failing once it is full.
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
 
-    let header = format!("{magic}\n{width} {height}\n-1.0\n");
-    let row_bytes = w
-        .checked_mul(depth)
-        .and_then(|wd| wd.checked_mul(4))
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let total_pixels = h
-        .checked_mul(row_bytes)
-        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
-    let mut out = Vec::with_capacity(header.len().saturating_add(total_pixels));
-    out.extend_from_slice(header.as_bytes());
+impl Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.pos + s.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.pos = end;
+        Ok(())
+    }
+}
+
+/// Write one row (or a run of whole pixels) converted to `dst_layout`, with
+/// multi-byte samples in big-endian or little-endian order.
+fn write_row(
+    dst_row: &mut [u8],
+    src_row: &[u8],
+    src_layout: PixelLayout,
+    dst_layout: PixelLayout,
+    options: &ConvertOptions,
+    big_endian: bool,
+) {
+    convert_row(src_row, src_layout, dst_row, dst_layout, options);
 
-    // PFM stores bottom-to-top
-    for row in (0..h).rev() {
-        if row % 16 == 0 {
-            stop.check()?;
+    let sample_bytes = dst_layout.bytes_per_sample();
+    if sample_bytes > 1 && big_endian != cfg!(target_endian = "big") {
+        for sample in dst_row.chunks_exact_mut(sample_bytes) {
+            sample.reverse();
         }
-        let start = row * row_bytes;
-        out.extend_from_slice(&pixels[start..start + row_bytes]);
     }
-
-    Ok(out)
 }
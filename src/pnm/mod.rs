@@ -6,10 +6,19 @@
 pub(crate) mod decode;
 mod encode;
 
-use crate::decode::DecodeOutput;
+use crate::convert::ConvertOptions;
+use crate::decode::{
+    DecodeOptions, DecodeOutput, Deviation, HeaderInfo, SamplePolicy, SourceFormat, SourceInfo,
+    Strictness,
+};
+use crate::downscale::{DownscaleFilter, downscale, downscaled_size};
+use crate::encode::HeaderOptions;
 use crate::error::PnmError;
 use crate::limits::Limits;
-use crate::pixel::PixelLayout;
+use crate::orientation::Orientation;
+use crate::pixel::{PixelLayout, PixelSlice, PixelSliceMut, check_region};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
 use enough::Stop;
 
 /// Which PNM sub-format to use (internal).
@@ -22,37 +31,583 @@ pub(crate) enum PnmFormat {
 }
 
 /// Parsed PNM header (internal).
-pub(crate) struct PnmHeader {
+pub(crate) struct PnmHeader<'a> {
     pub format: PnmFormat,
     pub width: u32,
     pub height: u32,
     pub maxval: u32,
-    pub depth: u32,
     pub layout: PixelLayout,
     pub pfm_scale: f32,
+    /// Decode 16-bit samples as stored (clamped to maxval) instead of
+    /// scaling them to the full 16-bit range.
+    pub stored_samples: bool,
+    /// Everything before the pixel data.
+    pub header: &'a [u8],
     pub data_offset: usize,
 }
 
+impl<'a> PnmHeader<'a> {
+    /// The layout [`decode`] returns: the stored one, except that 16-bit RGB
+    /// and RGBA narrow to 8 bits.
+    pub(crate) fn default_layout(&self) -> PixelLayout {
+        match self.layout {
+            PixelLayout::Rgb16 => PixelLayout::Rgb8,
+            PixelLayout::Rgba16 => PixelLayout::Rgba8,
+            layout => layout,
+        }
+    }
+
+    /// The header fields a [`DecodeOutput`] reports.
+    pub(crate) fn source(&self) -> Result<SourceInfo<'a>, PnmError> {
+        let (maxval, bit_depth) = if self.layout.is_float() {
+            (None, self.layout.bytes_per_sample() as u8 * 8)
+        } else {
+            (
+                Some(self.maxval),
+                (u32::BITS - self.maxval.leading_zeros()) as u8,
+            )
+        };
+        Ok(SourceInfo {
+            format: match self.format {
+                PnmFormat::Pgm => SourceFormat::Pgm,
+                PnmFormat::Ppm => SourceFormat::Ppm,
+                PnmFormat::Pam => SourceFormat::Pam,
+                PnmFormat::Pfm => SourceFormat::Pfm,
+            },
+            maxval,
+            bit_depth,
+            tuple_type: match self.format {
+                PnmFormat::Pam => decode::pam_tuple_type(self.header)?,
+                _ => None,
+            },
+            header: Cow::Borrowed(self.header),
+        })
+    }
+}
+
+/// The layout a decode produces (internal).
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Target {
+    /// What [`decode`] returns.
+    Default,
+    /// The stored layout when it is memory-compatible with this one, so
+    /// typed 16-bit decodes keep their precision; [`Target::Default`]
+    /// otherwise.
+    #[cfg(feature = "rgb")]
+    Typed(PixelLayout),
+    /// This layout.
+    Exact(PixelLayout),
+}
+
+impl Target {
+    /// The layout to decode `header` into, with `header` set to decode its
+    /// samples the one way every entry point shares: 16-bit samples stay
+    /// as stored when the target has 16-bit samples too, and are scaled
+    /// from maxval to the target's range otherwise.
+    fn resolve(self, header: &mut PnmHeader) -> PixelLayout {
+        let layout = match self {
+            Target::Exact(layout) => layout,
+            #[cfg(feature = "rgb")]
+            Target::Typed(layout) if header.layout.is_memory_compatible(layout) => header.layout,
+            #[cfg(feature = "rgb")]
+            Target::Typed(_) => header.default_layout(),
+            Target::Default => header.default_layout(),
+        };
+        header.stored_samples = header.maxval > 255
+            && !header.layout.is_float()
+            && !layout.is_float()
+            && layout.bytes_per_sample() == 2;
+        layout
+    }
+}
+
 /// Decode PNM data (called from top-level decode functions).
 pub(crate) fn decode<'a>(
     data: &'a [u8],
     limits: Option<&Limits>,
     stop: &dyn Stop,
 ) -> Result<DecodeOutput<'a>, PnmError> {
+    let options = DecodeOptions::default().with_limits(limits.cloned().unwrap_or_default());
+    decode_as(data, None, &options, stop)
+}
+
+/// Decode PNM data under `options` into `target`, or the layout [`decode`]
+/// returns when `None`.
+///
+/// Borrows the input when no transformation is needed; otherwise every row
+/// is decoded and converted in one pass.
+pub(crate) fn decode_as<'a>(
+    data: &'a [u8],
+    target: Option<PixelLayout>,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let parsed = parse_with_options(data, options, stop)?;
+    let target = target.map_or(Target::Default, Target::Exact);
+    decode_with_buffer(parsed, target, Orientation::Identity, None, options, stop)
+}
+
+/// Decode PNM data for typed pixels of `layout`: in the stored layout when
+/// `layout` matches it, or as [`decode`] does otherwise.
+#[cfg(feature = "rgb")]
+pub(crate) fn decode_typed<'a>(
+    data: &'a [u8],
+    layout: PixelLayout,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let parsed = parse_with_options(data, options, stop)?;
+    let target = Target::Typed(layout);
+    decode_with_buffer(parsed, target, Orientation::Identity, None, options, stop)
+}
+
+/// Decode PNM data into `target` with `orientation` applied while decoding.
+pub(crate) fn decode_oriented<'a>(
+    data: &'a [u8],
+    target: PixelLayout,
+    orientation: Orientation,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let parsed = parse_with_options(data, options, stop)?;
+    let target = Target::Exact(target);
+    decode_with_buffer(parsed, target, orientation, None, options, stop)
+}
+
+/// Decode PNM data like [`decode_as`], decoding transformed pixels into
+/// `buf` (reusing its capacity) and borrowing them from there.
+pub(crate) fn decode_reusing<'a>(
+    data: &'a [u8],
+    target: Option<PixelLayout>,
+    buf: &'a mut Vec<u8>,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let parsed = parse_with_options(data, options, stop)?;
+    let target = target.map_or(Target::Default, Target::Exact);
+    let identity = Orientation::Identity;
+    decode_with_buffer(parsed, target, identity, Some(buf), options, stop)
+}
+
+/// Shared body of [`decode_as`], [`decode_oriented`] and [`decode_reusing`],
+/// decoding an already [`parse`]d header and its pixel data under
+/// `options`: transformed pixels go into `buf` when given, or a new vector
+/// otherwise.
+fn decode_with_buffer<'a>(
+    (mut header, pixel_data): (PnmHeader<'a>, &'a [u8]),
+    target: Target,
+    orientation: Orientation,
+    buf: Option<&'a mut Vec<u8>>,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let source = header.source()?;
+    let target = target.resolve(&mut header);
+    let (w, h) = (header.width as usize, header.height as usize);
+    let (width, height) = (header.width, header.height);
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let src_stride = w
+        .checked_mul(header.layout.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    let expected_src = src_stride.checked_mul(h).ok_or_else(too_large)?;
+
+    if pixel_data.len() < expected_src {
+        return Err(PnmError::UnexpectedEof);
+    }
+    let image = (0, 0, width, height);
+    apply_sample_policy(&mut header, options.samples, pixel_data, src_stride, image)?;
+
+    // 8-bit maxval=255 samples are stored exactly as the layout expects
+    if target == header.layout && header.maxval == 255 && orientation == Orientation::Identity {
+        return Ok(DecodeOutput::borrowed(
+            &pixel_data[..expected_src],
+            header.width,
+            header.height,
+            header.layout,
+            source,
+        ));
+    }
+
+    let out_bytes = w
+        .checked_mul(h)
+        .and_then(|wh| wh.checked_mul(target.bytes_per_pixel()))
+        .ok_or_else(too_large)?;
+    options.limits.check_memory(out_bytes)?;
+    let (width, height) = orientation.output_size(header.width, header.height);
+    let rows = |out: &mut Vec<u8>| {
+        decode::decode_rows(
+            pixel_data,
+            src_stride,
+            &header,
+            orientation,
+            target,
+            out,
+            stop,
+        )
+    };
+    match buf {
+        Some(buf) => {
+            rows(buf)?;
+            Ok(DecodeOutput::borrowed(buf, width, height, target, source))
+        }
+        None => {
+            let mut pixels = Vec::new();
+            rows(&mut pixels)?;
+            Ok(DecodeOutput::owned(pixels, width, height, target, source))
+        }
+    }
+}
+
+/// Handle the samples above maxval in the `width` x `height` block at
+/// (`x`, `y`) as `samples` says, before it is decoded. The block's stored
+/// rows start `stride` bytes apart in `rows`.
+///
+/// [`SamplePolicy::Error`] fails at the first one, giving its position in
+/// the image; [`SamplePolicy::PassThrough`] sets `header` to decode as if
+/// maxval filled the sample type, which leaves samples as stored.
+fn apply_sample_policy(
+    header: &mut PnmHeader,
+    samples: SamplePolicy,
+    rows: &[u8],
+    stride: usize,
+    (x, y, width, height): (u32, u32, u32, u32),
+) -> Result<(), PnmError> {
+    match samples {
+        SamplePolicy::Clamp => {}
+        SamplePolicy::Error => {
+            let row_header = PnmHeader {
+                width,
+                height: 1,
+                ..*header
+            };
+            let row_bytes = width as usize * header.layout.bytes_per_pixel();
+            for row in 0..height {
+                let stored = (row as usize * stride)
+                    .checked_add(row_bytes)
+                    .and_then(|end| rows.get(end - row_bytes..end))
+                    .ok_or(PnmError::UnexpectedEof)?;
+                if let Some(Deviation::SampleExceedsMaxval {
+                    x: column,
+                    channel,
+                    value,
+                    ..
+                }) = decode::first_sample_over_maxval(&row_header, stored)
+                {
+                    return Err(PnmError::SampleOutOfRange {
+                        x: x + column,
+                        y: y + row,
+                        channel,
+                        value,
+                        maxval: header.maxval,
+                    });
+                }
+            }
+        }
+        SamplePolicy::PassThrough if !header.layout.is_float() => {
+            header.maxval = match header.layout.bytes_per_sample() {
+                1 => 255,
+                _ => 65535,
+            };
+        }
+        SamplePolicy::PassThrough => {}
+    }
+    Ok(())
+}
+
+/// Decode PNM data under `options` in the file's own layout, borrowing
+/// native-endian PFM rows in their stored bottom-up order instead of
+/// flipping them.
+///
+/// Other files decode as [`decode`] does.
+pub(crate) fn decode_view<'a>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let (header, pixel_data) = parse_with_options(data, options, stop)?;
+    // Negative scale = little-endian samples; a scale other than 1 changes
+    // the values
+    let native = (header.pfm_scale < 0.0) == cfg!(target_endian = "little");
+    if header.format != PnmFormat::Pfm || !native || header.pfm_scale.abs() != 1.0 {
+        let (parsed, identity) = ((header, pixel_data), Orientation::Identity);
+        return decode_with_buffer(parsed, Target::Default, identity, None, options, stop);
+    }
+    let len = (header.width as usize)
+        .checked_mul(header.height as usize)
+        .and_then(|wh| wh.checked_mul(header.layout.bytes_per_pixel()))
+        .ok_or(PnmError::DimensionsTooLarge {
+            width: header.width,
+            height: header.height,
+        })?;
+    let rows = pixel_data.get(..len).ok_or(PnmError::UnexpectedEof)?;
+    let (width, height, layout) = (header.width, header.height, header.layout);
+    Ok(DecodeOutput::borrowed(rows, width, height, layout, header.source()?).bottom_up())
+}
+
+/// Decode PNM data under `options` into `buf` as `target`, rows `stride`
+/// bytes apart.
+///
+/// Returns the image dimensions. Never allocates.
+pub(crate) fn decode_into(
+    data: &[u8],
+    buf: &mut [u8],
+    stride: usize,
+    target: PixelLayout,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<(u32, u32), PnmError> {
+    let parsed = parse_with_options(data, options, stop)?;
+    decode_parsed_into(parsed, buf, stride, target, options.samples, stop)
+}
+
+/// Decode an already [`parse`]d header and its pixel data like
+/// [`decode_into`].
+pub(crate) fn decode_parsed_into(
+    (mut header, pixel_data): (PnmHeader<'_>, &[u8]),
+    buf: &mut [u8],
+    stride: usize,
+    target: PixelLayout,
+    samples: SamplePolicy,
+    stop: &dyn Stop,
+) -> Result<(u32, u32), PnmError> {
+    let target = Target::Exact(target).resolve(&mut header);
+    let mut out = PixelSliceMut::strided(buf, header.width, header.height, stride, target)?;
+    let src_stride = header.width as usize * header.layout.bytes_per_pixel();
+    let image = (0, 0, header.width, header.height);
+    apply_sample_policy(&mut header, samples, pixel_data, src_stride, image)?;
+    let identity = Orientation::Identity;
+    decode::decode_rows_into(pixel_data, src_stride, &header, identity, &mut out, stop)?;
+    Ok((header.width, header.height))
+}
+
+/// Decode the `width` x `height` region at (`x`, `y`) under `options` in
+/// the layout [`decode`] returns, reading only the stored rows it covers.
+///
+/// The limits apply to the region, not the full image; samples outside it
+/// are not checked against maxval. maxval=255 files borrow the region's
+/// rows from the input, a full stored row apart.
+pub(crate) fn decode_region<'a>(
+    data: &'a [u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'a>, PnmError> {
+    let (mut header, pixel_data) = parse_with_strictness(data, options.strictness, None, stop)?;
+    check_region(x, y, width, height, header.width, header.height)?;
+    options.limits.check(width, height)?;
+    let source = header.source()?;
+    let target = Target::Default.resolve(&mut header);
+
+    let too_large = || PnmError::DimensionsTooLarge { width, height };
+    let layout = header.layout;
+    let bpp = layout.bytes_per_pixel();
+    let stride = (header.width as usize)
+        .checked_mul(bpp)
+        .ok_or_else(too_large)?;
+    // PFM stores rows bottom-to-top, so the region's rows end at stored row H-y
+    let first_row = if header.format == PnmFormat::Pfm {
+        header.height - y - height
+    } else {
+        y
+    };
+    let start = (first_row as usize)
+        .checked_mul(stride)
+        .and_then(|n| n.checked_add(x as usize * bpp))
+        .ok_or_else(too_large)?;
+    let region_data = pixel_data.get(start..).ok_or(PnmError::UnexpectedEof)?;
+    let block = (x, y, width, height);
+    apply_sample_policy(&mut header, options.samples, region_data, stride, block)?;
+    let region = PnmHeader {
+        width,
+        height,
+        ..header
+    };
+
+    // 8-bit maxval=255 rows are stored exactly as the layout expects
+    if header.maxval == 255 {
+        let row_bytes = width as usize * bpp;
+        let len = match (height as usize).checked_sub(1) {
+            Some(last) => stride
+                .checked_mul(last)
+                .and_then(|n| n.checked_add(row_bytes))
+                .ok_or_else(too_large)?,
+            None => 0,
+        };
+        let rows = region_data.get(..len).ok_or(PnmError::UnexpectedEof)?;
+        return Ok(DecodeOutput::borrowed_strided(
+            rows, width, height, stride, layout, source,
+        ));
+    }
+
+    let out_bytes = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|wh| wh.checked_mul(target.bytes_per_pixel()))
+        .ok_or_else(too_large)?;
+    options.limits.check_memory(out_bytes)?;
+    let mut pixels = Vec::new();
+    let identity = Orientation::Identity;
+    decode::decode_rows(
+        region_data,
+        stride,
+        &region,
+        identity,
+        target,
+        &mut pixels,
+        stop,
+    )?;
+    Ok(DecodeOutput::owned(pixels, width, height, target, source))
+}
+
+/// Decode PNM data under `options` downscaled by `factor`, in the layout
+/// [`decode`] returns, one stored row at a time.
+///
+/// Blocks are reduced in the stored layout before any narrowing. The limits
+/// apply to the downscaled image.
+pub(crate) fn decode_downscaled(
+    data: &[u8],
+    factor: u32,
+    filter: DownscaleFilter,
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<DecodeOutput<'static>, PnmError> {
+    let (mut header, pixel_data) = parse_with_strictness(data, options.strictness, None, stop)?;
+    let (width, height) = downscaled_size(header.width, header.height, factor)?;
+    let source = header.source()?.into_owned();
+    let target = Target::Default.resolve(&mut header);
+    let layout = header.layout;
+    options.limits.check(width, height)?;
+    let out_bytes = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|wh| wh.checked_mul(layout.bytes_per_pixel()))
+        .ok_or(PnmError::DimensionsTooLarge { width, height })?;
+    options.limits.check_memory(out_bytes)?;
+
+    let h = header.height as usize;
+    let too_large = || PnmError::DimensionsTooLarge {
+        width: header.width,
+        height: header.height,
+    };
+    let row_bytes = (header.width as usize)
+        .checked_mul(layout.bytes_per_pixel())
+        .ok_or_else(too_large)?;
+    if pixel_data.len() < row_bytes.checked_mul(h).ok_or_else(too_large)? {
+        return Err(PnmError::UnexpectedEof);
+    }
+    let image = (0, 0, header.width, header.height);
+    apply_sample_policy(&mut header, options.samples, pixel_data, row_bytes, image)?;
+    let pixels = downscale(
+        header.width,
+        header.height,
+        layout,
+        factor,
+        filter,
+        stop,
+        |y, row| {
+            // PFM stores rows bottom-to-top
+            let stored = if header.format == PnmFormat::Pfm {
+                h - 1 - y
+            } else {
+                y
+            };
+            decode::decode_row(&pixel_data[stored * row_bytes..][..row_bytes], &header, row);
+        },
+    )?;
+    let pixels = if target == layout {
+        pixels
+    } else {
+        crate::convert::convert(&pixels, layout, target)?
+    };
+    Ok(DecodeOutput::owned(pixels, width, height, target, source))
+}
+
+/// [`parse`] under `options`: their limits apply, and the file is checked
+/// against its spec first when they ask for strictness.
+fn parse_with_options<'a>(
+    data: &'a [u8],
+    options: &DecodeOptions,
+    stop: &dyn Stop,
+) -> Result<(PnmHeader<'a>, &'a [u8]), PnmError> {
+    parse_with_strictness(data, options.strictness, Some(&options.limits), stop)
+}
+
+/// [`parse`], first checking the file against its spec under
+/// [`Strictness::Strict`].
+fn parse_with_strictness<'a>(
+    data: &'a [u8],
+    strictness: Strictness,
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<(PnmHeader<'a>, &'a [u8]), PnmError> {
+    if strictness != Strictness::Strict {
+        return parse(data, limits, stop);
+    }
+    // A file without deviations has its maxval in range
+    let (header, pixel_data) = parse_any_maxval(data, limits, stop)?;
+    if let Some(deviation) = decode::deviations(&header, pixel_data)?.into_iter().next() {
+        return Err(PnmError::Nonconforming(deviation));
+    }
+    Ok((header, pixel_data))
+}
+
+/// List the ways PNM data departs from its format spec.
+pub(crate) fn validate(data: &[u8]) -> Result<Vec<Deviation>, PnmError> {
+    let (header, pixel_data) = parse_any_maxval(data, None, &enough::Unstoppable)?;
+    decode::deviations(&header, pixel_data)
+}
+
+/// Read the header of PNM data without decoding any pixels.
+pub(crate) fn probe<'a>(
+    data: &'a [u8],
+    limits: Option<&Limits>,
+) -> Result<HeaderInfo<'a>, PnmError> {
+    let (header, _) = parse(data, limits, &enough::Unstoppable)?;
+    Ok(HeaderInfo {
+        width: header.width,
+        height: header.height,
+        layout: header.default_layout(),
+        source: header.source()?,
+    })
+}
+
+/// Parse and check the header, returning it with the pixel data after it.
+pub(crate) fn parse<'a>(
+    data: &'a [u8],
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<(PnmHeader<'a>, &'a [u8]), PnmError> {
+    let (header, pixel_data) = parse_any_maxval(data, limits, stop)?;
+    decode::check_maxval(&header)?;
+    Ok((header, pixel_data))
+}
+
+/// [`parse`] without the maxval range check.
+fn parse_any_maxval<'a>(
+    data: &'a [u8],
+    limits: Option<&Limits>,
+    stop: &dyn Stop,
+) -> Result<(PnmHeader<'a>, &'a [u8]), PnmError> {
     if data.len() < 3 {
         return Err(PnmError::UnexpectedEof);
     }
 
     // Verify magic bytes
     match &data[..2] {
-        b"P5" | b"P6" | b"P7" | b"Pf" | b"PF" => {}
+        b"P5" | b"P6" | b"P7" | b"Pf" | b"PF" | b"Ph" | b"PH" => {}
         _ => return Err(PnmError::UnrecognizedFormat),
     }
 
-    let header = decode::parse_header(data)?;
+    let header = decode::parse_header_any_maxval(data)?;
 
     if let Some(limits) = limits {
         limits.check(header.width, header.height)?;
+        let pam = header.format == PnmFormat::Pam;
+        let comment_bytes = decode::header_comments(header.header, pam)
+            .map(<[u8]>::len)
+            .sum();
+        limits.check_comments(comment_bytes)?;
     }
 
     stop.check()?;
@@ -60,87 +615,84 @@ pub(crate) fn decode<'a>(
     let pixel_data = data
         .get(header.data_offset..)
         .ok_or(PnmError::UnexpectedEof)?;
-
-    let w = header.width as usize;
-    let h = header.height as usize;
-    let depth = header.depth as usize;
-
-    match header.format {
-        PnmFormat::Pfm => {
-            let out_bytes = w
-                .checked_mul(h)
-                .and_then(|wh| wh.checked_mul(depth))
-                .and_then(|whd| whd.checked_mul(4))
-                .ok_or(PnmError::DimensionsTooLarge {
-                    width: header.width,
-                    height: header.height,
-                })?;
-            if let Some(limits) = limits {
-                limits.check_memory(out_bytes)?;
-            }
-            let pixels = decode::decode_pfm(pixel_data, &header, stop)?;
-            Ok(DecodeOutput::owned(
-                pixels,
-                header.width,
-                header.height,
-                header.layout,
-            ))
-        }
-        _ => {
-            let is_16bit = header.maxval > 255;
-            let src_bps = if is_16bit { 2 } else { 1 };
-            let expected_src = w
-                .checked_mul(h)
-                .and_then(|wh| wh.checked_mul(depth))
-                .and_then(|whd| whd.checked_mul(src_bps))
-                .ok_or(PnmError::DimensionsTooLarge {
-                    width: header.width,
-                    height: header.height,
-                })?;
-
-            if pixel_data.len() < expected_src {
-                return Err(PnmError::UnexpectedEof);
-            }
-
-            if !is_16bit && header.maxval == 255 {
-                Ok(DecodeOutput::borrowed(
-                    &pixel_data[..expected_src],
-                    header.width,
-                    header.height,
-                    header.layout,
-                ))
-            } else {
-                let out_bytes = w
-                    .checked_mul(h)
-                    .and_then(|wh| wh.checked_mul(depth))
-                    .ok_or(PnmError::DimensionsTooLarge {
-                        width: header.width,
-                        height: header.height,
-                    })?;
-                if let Some(limits) = limits {
-                    limits.check_memory(out_bytes)?;
-                }
-                let pixels =
-                    decode::decode_integer_transform(pixel_data, &header, expected_src, stop)?;
-                Ok(DecodeOutput::owned(
-                    pixels,
-                    header.width,
-                    header.height,
-                    header.layout,
-                ))
-            }
-        }
-    }
+    Ok((header, pixel_data))
 }
 
 /// Encode to PNM.
 pub(crate) fn encode(
-    pixels: &[u8],
+    pixels: &PixelSlice<'_>,
+    format: PnmFormat,
+    stop: &dyn Stop,
+) -> Result<Vec<u8>, PnmError> {
+    encode_with_options(pixels, format, &ConvertOptions::default(), stop)
+}
+
+/// Encode to PNM format with explicit conversion options.
+pub(crate) fn encode_with_options(
+    pixels: &PixelSlice<'_>,
+    format: PnmFormat,
+    options: &ConvertOptions,
+    stop: &dyn Stop,
+) -> Result<Vec<u8>, PnmError> {
+    let header = HeaderOptions::default();
+    encode::encode_pnm(
+        pixels,
+        format,
+        options,
+        Orientation::Identity,
+        &header,
+        stop,
+    )
+}
+
+/// Encode to PNM with `orientation` applied while writing.
+pub(crate) fn encode_oriented(
+    pixels: &PixelSlice<'_>,
+    format: PnmFormat,
+    orientation: Orientation,
+    stop: &dyn Stop,
+) -> Result<Vec<u8>, PnmError> {
+    let (options, header) = (ConvertOptions::default(), HeaderOptions::default());
+    encode::encode_pnm(pixels, format, &options, orientation, &header, stop)
+}
+
+/// Encode to PNM with `header` added to the header.
+pub(crate) fn encode_with_header(
+    pixels: &PixelSlice<'_>,
+    format: PnmFormat,
+    header: &HeaderOptions<'_>,
+    stop: &dyn Stop,
+) -> Result<Vec<u8>, PnmError> {
+    let options = ConvertOptions::default();
+    encode::encode_pnm(
+        pixels,
+        format,
+        &options,
+        Orientation::Identity,
+        header,
+        stop,
+    )
+}
+
+/// Encode to PNM into a caller buffer, returning the bytes written.
+pub(crate) fn encode_into(
+    pixels: &PixelSlice<'_>,
+    format: PnmFormat,
+    options: &ConvertOptions,
+    out: &mut [u8],
+    stop: &dyn Stop,
+) -> Result<usize, PnmError> {
+    let header = HeaderOptions::default();
+    let identity = Orientation::Identity;
+    encode::encode_pnm_into(pixels, format, options, identity, &header, out, stop)
+}
+
+/// Exact encoded PNM size.
+pub(crate) fn encoded_len(
     width: u32,
     height: u32,
     layout: PixelLayout,
     format: PnmFormat,
-    stop: &dyn Stop,
-) -> Result<alloc::vec::Vec<u8>, PnmError> {
-    encode::encode_pnm(pixels, width, height, layout, format, stop)
+) -> Result<usize, PnmError> {
+    encode::encoded_len(width, height, layout, format, &HeaderOptions::default())
 }
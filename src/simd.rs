@@ -0,0 +1,71 @@
+//! Kernel helpers and runtime SIMD dispatch.
+//!
+//! Hot loops (swizzles, luma, sample scaling and byte swapping) are written
+//! as maps over fixed-size pixels with [`map_chunks`], which LLVM vectorizes.
+//! With the `simd` feature, [`dispatch`] runs them inside a function compiled
+//! for the widest instruction set the CPU supports (AVX2, SSE4.2 or NEON),
+//! chosen at runtime through `archmage` tokens. Without it, or on other
+//! CPUs, the same loops run at the baseline target.
+//!
+//! Every tier executes the same integer and `f32` operations (Rust never
+//! fuses multiply-adds on its own), so output is bit-identical across tiers.
+
+/// Run `kernel` with the best SIMD tier available on this CPU enabled.
+#[inline(always)]
+pub(crate) fn dispatch<R>(kernel: impl FnOnce() -> R) -> R {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        use archmage::SimdToken;
+        if let Some(token) = archmage::X64V3Token::summon() {
+            return x64_v3(token, kernel);
+        }
+        if let Some(token) = archmage::X64V2Token::summon() {
+            return x64_v2(token, kernel);
+        }
+    }
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    {
+        use archmage::SimdToken;
+        if let Some(token) = archmage::NeonToken::summon() {
+            return neon(token, kernel);
+        }
+    }
+    kernel()
+}
+
+/// AVX2 + FMA tier.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[archmage::arcane]
+fn x64_v3<R>(_token: archmage::X64V3Token, kernel: impl FnOnce() -> R) -> R {
+    kernel()
+}
+
+/// SSE4.2 tier.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[archmage::arcane]
+fn x64_v2<R>(_token: archmage::X64V2Token, kernel: impl FnOnce() -> R) -> R {
+    kernel()
+}
+
+/// NEON tier.
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+#[archmage::arcane]
+fn neon<R>(_token: archmage::NeonToken, kernel: impl FnOnce() -> R) -> R {
+    kernel()
+}
+
+/// Map each `S`-byte chunk of `src` to a `D`-byte chunk of `dst`, for as
+/// many chunks as both hold.
+///
+/// Fixed chunk sizes and an inlined `f` are what let LLVM turn this into
+/// shuffles and vector arithmetic.
+#[inline(always)]
+pub(crate) fn map_chunks<const S: usize, const D: usize>(
+    src: &[u8],
+    dst: &mut [u8],
+    f: impl Fn([u8; S]) -> [u8; D],
+) {
+    for (s, d) in src.chunks_exact(S).zip(dst.chunks_exact_mut(D)) {
+        d.copy_from_slice(&f(core::array::from_fn(|i| s[i])));
+    }
+}
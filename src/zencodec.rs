@@ -91,157 +91,57 @@ impl<'a> zencodec_types::EncodingJob<'a> for PnmEncodingJob<'a> {
         self,
         img: imgref::ImgRef<'_, rgb::Rgb<u8>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::Rgb8,
-            pnm::PnmFormat::Ppm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::Rgb8, pnm::PnmFormat::Ppm)
     }
 
     fn encode_rgba8(
         self,
         img: imgref::ImgRef<'_, rgb::Rgba<u8>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::Rgba8,
-            pnm::PnmFormat::Pam,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::Rgba8, pnm::PnmFormat::Pam)
     }
 
     fn encode_gray8(
         self,
         img: imgref::ImgRef<'_, rgb::Gray<u8>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::Gray8,
-            pnm::PnmFormat::Pgm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::Gray8, pnm::PnmFormat::Pgm)
     }
 
     fn encode_bgra8(
         self,
         img: imgref::ImgRef<'_, rgb::alt::BGRA<u8>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::Bgra8,
-            pnm::PnmFormat::Ppm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::Bgra8, pnm::PnmFormat::Ppm)
     }
 
     fn encode_bgrx8(
         self,
         img: imgref::ImgRef<'_, rgb::alt::BGRA<u8>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::Bgrx8,
-            pnm::PnmFormat::Ppm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::Bgrx8, pnm::PnmFormat::Ppm)
     }
 
     fn encode_rgb_f32(
         self,
         img: imgref::ImgRef<'_, rgb::Rgb<f32>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::RgbF32,
-            pnm::PnmFormat::Pfm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::RgbF32, pnm::PnmFormat::Pfm)
     }
 
     fn encode_rgba_f32(
         self,
         img: imgref::ImgRef<'_, rgb::Rgba<f32>>,
     ) -> Result<EncodeOutput, PnmError> {
-        // PFM has no alpha channel — drop alpha and write PFM color.
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let rgb_pixels: Vec<rgb::Rgb<f32>> = buf
-            .iter()
-            .map(|px| rgb::Rgb { r: px.r, g: px.g, b: px.b })
-            .collect();
-        let bytes = rgb::ComponentBytes::as_bytes(rgb_pixels.as_slice());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::RgbF32,
-            pnm::PnmFormat::Pfm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        // Standard PFM has no alpha channel — write the 4-channel "PF4" variant.
+        encode_img(img, crate::PixelLayout::RgbaF32, pnm::PnmFormat::Pfm)
     }
 
     fn encode_gray_f32(
         self,
         img: imgref::ImgRef<'_, rgb::Gray<f32>>,
     ) -> Result<EncodeOutput, PnmError> {
-        let w = img.width() as u32;
-        let h = img.height() as u32;
-        let (buf, _, _) = img.to_contiguous_buf();
-        let bytes = rgb::ComponentBytes::as_bytes(buf.as_ref());
-        let encoded = pnm::encode(
-            bytes,
-            w,
-            h,
-            crate::PixelLayout::GrayF32,
-            pnm::PnmFormat::Pfm,
-            &enough::Unstoppable,
-        )?;
-        Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+        encode_img(img, crate::PixelLayout::GrayF32, pnm::PnmFormat::Pfm)
     }
 }
 
@@ -316,10 +216,7 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
         let limits = self.limits.as_ref().or(self.config.limits.as_ref());
         let decoded = pnm::decode(data, limits, &enough::Unstoppable)?;
 
-        let has_alpha = matches!(
-            decoded.layout,
-            crate::PixelLayout::Rgba8 | crate::PixelLayout::Bgra8
-        );
+        let has_alpha = decoded.layout.has_alpha();
         let info = ImageInfo::new(decoded.width, decoded.height, ImageFormat::Pnm)
             .with_alpha(has_alpha);
 
@@ -330,78 +227,41 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
     fn decode_into_rgb8(
         self,
         data: &[u8],
-        mut dst: imgref::ImgRefMut<'_, rgb::Rgb<u8>>,
+        dst: imgref::ImgRefMut<'_, rgb::Rgb<u8>>,
     ) -> Result<ImageInfo, PnmError> {
-        let output = self.decode(data)?;
-        let info = output.info().clone();
-        let src = output.into_rgb8();
-        for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-            let n = src_row.len().min(dst_row.len());
-            dst_row[..n].copy_from_slice(&src_row[..n]);
-        }
-        Ok(info)
+        self.decode_as_into(data, crate::PixelLayout::Rgb8, dst)
     }
 
     fn decode_into_rgba8(
         self,
         data: &[u8],
-        mut dst: imgref::ImgRefMut<'_, rgb::Rgba<u8>>,
+        dst: imgref::ImgRefMut<'_, rgb::Rgba<u8>>,
     ) -> Result<ImageInfo, PnmError> {
-        let output = self.decode(data)?;
-        let info = output.info().clone();
-        let src = output.into_rgba8();
-        for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-            let n = src_row.len().min(dst_row.len());
-            dst_row[..n].copy_from_slice(&src_row[..n]);
-        }
-        Ok(info)
+        self.decode_as_into(data, crate::PixelLayout::Rgba8, dst)
     }
 
     fn decode_into_gray8(
         self,
         data: &[u8],
-        mut dst: imgref::ImgRefMut<'_, rgb::Gray<u8>>,
+        dst: imgref::ImgRefMut<'_, rgb::Gray<u8>>,
     ) -> Result<ImageInfo, PnmError> {
-        let output = self.decode(data)?;
-        let info = output.info().clone();
-        let src = output.into_gray8();
-        for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-            let n = src_row.len().min(dst_row.len());
-            dst_row[..n].copy_from_slice(&src_row[..n]);
-        }
-        Ok(info)
+        self.decode_as_into(data, crate::PixelLayout::Gray8, dst)
     }
 
     fn decode_into_bgra8(
         self,
         data: &[u8],
-        mut dst: imgref::ImgRefMut<'_, rgb::alt::BGRA<u8>>,
+        dst: imgref::ImgRefMut<'_, rgb::alt::BGRA<u8>>,
     ) -> Result<ImageInfo, PnmError> {
-        let output = self.decode(data)?;
-        let info = output.info().clone();
-        let src = output.into_bgra8();
-        for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-            let n = src_row.len().min(dst_row.len());
-            dst_row[..n].copy_from_slice(&src_row[..n]);
-        }
-        Ok(info)
+        self.decode_as_into(data, crate::PixelLayout::Bgra8, dst)
     }
 
     fn decode_into_bgrx8(
         self,
         data: &[u8],
-        mut dst: imgref::ImgRefMut<'_, rgb::alt::BGRA<u8>>,
+        dst: imgref::ImgRefMut<'_, rgb::alt::BGRA<u8>>,
     ) -> Result<ImageInfo, PnmError> {
-        let output = self.decode(data)?;
-        let info = output.info().clone();
-        let src = output.into_bgra8();
-        for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-            let n = src_row.len().min(dst_row.len());
-            for (s, d) in src_row[..n].iter().zip(dst_row[..n].iter_mut()) {
-                *d = rgb::alt::BGRA { b: s.b, g: s.g, r: s.r, a: 255 };
-            }
-        }
-        Ok(info)
+        self.decode_as_into(data, crate::PixelLayout::Bgrx8, dst)
     }
 
     fn decode_into_rgb_f32(
@@ -413,13 +273,13 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
 
         let output = self.decode(data)?;
         let info = output.info().clone();
+        check_dimensions(info.width, info.height, &dst)?;
         let is_float = matches!(output.pixels(), PixelData::RgbF32(_) | PixelData::RgbaF32(_) | PixelData::GrayF32(_));
         if is_float {
             // PFM is already linear — copy directly
             let src = output.into_rgb_f32();
             for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-                let n = src_row.len().min(dst_row.len());
-                dst_row[..n].copy_from_slice(&src_row[..n]);
+                dst_row.copy_from_slice(src_row);
             }
         } else {
             // Integer PNM (u8 or u16): preserve full precision via into_rgb_f32,
@@ -447,12 +307,12 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
 
         let output = self.decode(data)?;
         let info = output.info().clone();
+        check_dimensions(info.width, info.height, &dst)?;
         let is_float = matches!(output.pixels(), PixelData::RgbF32(_) | PixelData::RgbaF32(_) | PixelData::GrayF32(_));
         if is_float {
             let src = output.into_rgba_f32();
             for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-                let n = src_row.len().min(dst_row.len());
-                dst_row[..n].copy_from_slice(&src_row[..n]);
+                dst_row.copy_from_slice(src_row);
             }
         } else {
             // Preserve full precision (u16 → f32 via /65535), then linearize
@@ -480,12 +340,12 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
 
         let output = self.decode(data)?;
         let info = output.info().clone();
+        check_dimensions(info.width, info.height, &dst)?;
         let is_float = matches!(output.pixels(), PixelData::RgbF32(_) | PixelData::RgbaF32(_) | PixelData::GrayF32(_));
         if is_float {
             let src = output.into_gray_f32();
             for (src_row, dst_row) in src.as_ref().rows().zip(dst.rows_mut()) {
-                let n = src_row.len().min(dst_row.len());
-                dst_row[..n].copy_from_slice(&src_row[..n]);
+                dst_row.copy_from_slice(src_row);
             }
         } else {
             // Preserve full precision (u16 → f32 via /65535), then linearize
@@ -500,20 +360,85 @@ impl<'a> zencodec_types::DecodingJob<'a> for PnmDecodingJob<'a> {
     }
 }
 
+impl PnmDecodingJob<'_> {
+    /// Decode straight into `dst` as `layout`, row by row at its stride.
+    ///
+    /// `dst` must match the image dimensions.
+    fn decode_as_into<P: crate::DecodePixel>(
+        self,
+        data: &[u8],
+        layout: crate::PixelLayout,
+        mut dst: imgref::ImgRefMut<'_, P>,
+    ) -> Result<ImageInfo, PnmError> {
+        let limits = self.limits.as_ref().or(self.config.limits.as_ref());
+        let parsed = pnm::parse(data, limits, &enough::Unstoppable)?;
+        let header = &parsed.0;
+        check_dimensions(header.width, header.height, &dst)?;
+        let info = header_to_image_info(header);
+        let stride = dst.stride() * core::mem::size_of::<P>();
+        pnm::decode_parsed_into(
+            parsed,
+            rgb::bytemuck::cast_slice_mut::<P, u8>(dst.buf_mut()),
+            stride,
+            layout,
+            crate::SamplePolicy::Clamp,
+            &enough::Unstoppable,
+        )?;
+        Ok(info)
+    }
+}
+
 // ── Helpers ──────────────────────────────────────────────────────────
 
+/// Encode an image straight from its rows, at whatever stride it has.
+fn encode_img<P: crate::EncodePixel>(
+    img: imgref::ImgRef<'_, P>,
+    layout: crate::PixelLayout,
+    format: pnm::PnmFormat,
+) -> Result<EncodeOutput, PnmError> {
+    let (width, height) = (img.width() as u32, img.height() as u32);
+    let stride = img.stride() * core::mem::size_of::<P>();
+    let pixels = crate::pixel::PixelSlice::strided(
+        crate::pixel_traits::pixels_as_bytes(img.into_buf()),
+        width,
+        height,
+        stride,
+        layout,
+    )?;
+    let encoded = pnm::encode(&pixels, format, &enough::Unstoppable)?;
+    Ok(EncodeOutput::new(encoded, ImageFormat::Pnm))
+}
+
 fn convert_limits(limits: &ResourceLimits) -> Limits {
     Limits {
         max_width: limits.max_width.map(u64::from),
         max_height: limits.max_height.map(u64::from),
         max_pixels: limits.max_pixels,
         max_memory_bytes: limits.max_memory_bytes,
+        ..Default::default()
     }
 }
 
+/// Reject an output buffer whose dimensions differ from the image's.
+fn check_dimensions<P>(
+    width: u32,
+    height: u32,
+    dst: &imgref::ImgRefMut<'_, P>,
+) -> Result<(), PnmError> {
+    if (dst.width(), dst.height()) != (width as usize, height as usize) {
+        return Err(PnmError::InvalidData(alloc::format!(
+            "dimension mismatch: decoded {}x{}, output buffer {}x{}",
+            width,
+            height,
+            dst.width(),
+            dst.height()
+        )));
+    }
+    Ok(())
+}
+
 fn header_to_image_info(header: &pnm::PnmHeader) -> ImageInfo {
-    use crate::PixelLayout;
-    let has_alpha = matches!(header.layout, PixelLayout::Rgba8 | PixelLayout::Bgra8);
+    let has_alpha = header.layout.has_alpha();
     ImageInfo::new(header.width, header.height, ImageFormat::Pnm).with_alpha(has_alpha)
 }
 
@@ -582,17 +507,72 @@ fn layout_to_pixel_data(decoded: &crate::decode::DecodeOutput<'_>) -> Result<Pix
                 .collect();
             Ok(PixelData::RgbaF32(imgref::ImgVec::new(pixels, w, h)))
         }
-        PixelLayout::Bgr8 => {
-            // Swizzle BGR → RGB
-            let pixels: Vec<rgb::Rgb<u8>> = bytes
-                .chunks_exact(3)
-                .map(|c| rgb::Rgb {
-                    r: c[2],
-                    g: c[1],
-                    b: c[0],
+        PixelLayout::RgbaF32 => {
+            let pixels: Vec<rgb::Rgba<f32>> = bytes
+                .chunks_exact(16)
+                .map(|c| rgb::Rgba {
+                    r: f32::from_ne_bytes([c[0], c[1], c[2], c[3]]),
+                    g: f32::from_ne_bytes([c[4], c[5], c[6], c[7]]),
+                    b: f32::from_ne_bytes([c[8], c[9], c[10], c[11]]),
+                    a: f32::from_ne_bytes([c[12], c[13], c[14], c[15]]),
                 })
                 .collect();
-            Ok(PixelData::Rgb8(imgref::ImgVec::new(pixels, w, h)))
+            Ok(PixelData::RgbaF32(imgref::ImgVec::new(pixels, w, h)))
+        }
+        PixelLayout::GrayF16 | PixelLayout::RgbF16 | PixelLayout::RgbaF16 => {
+            // No half-float PixelData variant — widen to the f32 layout
+            let (widened, layout) = crate::half::widen_f16(bytes, decoded.layout)?;
+            let widened = crate::decode::DecodeOutput::owned(
+                widened,
+                decoded.width,
+                decoded.height,
+                layout,
+                decoded.source().clone(),
+            );
+            layout_to_pixel_data(&widened)
+        }
+        PixelLayout::Bgr8 => {
+            // Swizzle BGR → RGB
+            let rgb = crate::convert::convert(bytes, PixelLayout::Bgr8, PixelLayout::Rgb8)?;
+            let pixels: &[rgb::Rgb<u8>] = rgb.as_pixels();
+            Ok(PixelData::Rgb8(imgref::ImgVec::new(
+                pixels.to_vec(),
+                w,
+                h,
+            )))
+        }
+        PixelLayout::Rgb16 | PixelLayout::Rgba16 => {
+            // PixelData has no 16-bit color variant — reduce to 8-bit
+            let target = if decoded.layout.has_alpha() {
+                PixelLayout::Rgba8
+            } else {
+                PixelLayout::Rgb8
+            };
+            let reduced = crate::convert::convert(bytes, decoded.layout, target)?;
+            let reduced = crate::decode::DecodeOutput::owned(
+                reduced,
+                decoded.width,
+                decoded.height,
+                target,
+                decoded.source().clone(),
+            );
+            layout_to_pixel_data(&reduced)
+        }
+        PixelLayout::Rgba8Premul
+        | PixelLayout::Bgra8Premul
+        | PixelLayout::Rgba16Premul
+        | PixelLayout::RgbaF32Premul => {
+            // PixelData carries straight alpha
+            let target = decoded.layout.straight();
+            let straight = crate::convert::convert(bytes, decoded.layout, target)?;
+            let straight = crate::decode::DecodeOutput::owned(
+                straight,
+                decoded.width,
+                decoded.height,
+                target,
+                decoded.source().clone(),
+            );
+            layout_to_pixel_data(&straight)
         }
         PixelLayout::Bgra8 => {
             let pixels: &[rgb::alt::BGRA<u8>] = bytes.as_pixels();
@@ -821,6 +801,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_into_rejects_dimension_mismatch() {
+        let img = imgref::ImgVec::new(vec![rgb::Rgb { r: 1, g: 2, b: 3 }; 4], 2, 2);
+        let output = PnmEncoding::new().encode_rgb8(img.as_ref()).unwrap();
+
+        let dec = PnmDecoding::new();
+        let mut dst = imgref::ImgVec::new(vec![rgb::Rgb { r: 0, g: 0, b: 0 }; 2], 1, 2);
+        assert!(matches!(
+            dec.decode_into_rgb8(output.bytes(), dst.as_mut()),
+            Err(PnmError::InvalidData(_))
+        ));
+
+        let black = rgb::Rgb::new(0.0, 0.0, 0.0);
+        let mut dst = imgref::ImgVec::new(vec![black; 6], 3, 2);
+        assert!(matches!(
+            dec.decode_into_rgb_f32(output.bytes(), dst.as_mut()),
+            Err(PnmError::InvalidData(_))
+        ));
+    }
+
     #[test]
     fn encode_decode_rgb_f32_roundtrip() {
         let pixels = vec![
@@ -868,8 +868,8 @@ mod tests {
     }
 
     #[test]
-    fn encode_rgba_f32_drops_alpha() {
-        // RGBA f32 encodes to PFM (no alpha), verify RGB values survive
+    fn encode_rgba_f32_preserves_alpha() {
+        // RGBA f32 encodes to 4-channel PFM ("PF4"), verify all channels survive
         let pixels = vec![
             rgb::Rgba { r: 0.5f32, g: 0.25, b: 0.75, a: 0.1 },
             rgb::Rgba { r: 1.0, g: 0.0, b: 0.0, a: 0.5 },
@@ -880,15 +880,13 @@ mod tests {
         let enc = PnmEncoding::new();
         let output = enc.encode_rgba_f32(img.as_ref()).unwrap();
 
+        assert_eq!(&output.bytes()[..3], b"PF4");
+
         let dec = PnmDecoding::new();
         let decoded = dec.decode(output.bytes()).unwrap();
-        let rgb_img = decoded.into_rgb_f32();
-        let buf = rgb_img.buf();
-        for (orig, decoded) in pixels.iter().zip(buf.iter()) {
-            assert!((orig.r - decoded.r).abs() < 1e-6);
-            assert!((orig.g - decoded.g).abs() < 1e-6);
-            assert!((orig.b - decoded.b).abs() < 1e-6);
-        }
+        assert!(decoded.has_alpha());
+        let rgba_img = decoded.into_rgba_f32();
+        assert_eq!(rgba_img.buf().as_slice(), &pixels);
     }
 
     #[test]
@@ -0,0 +1,163 @@
+//! Checks that the caller-buffer APIs do not touch the heap, and that
+//! streaming decodes stay within their memory bounds.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use enough::Unstoppable;
+use zenpnm::*;
+
+/// Counts allocations made by the current thread, and the bytes it holds.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE_BYTES: Cell<usize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        let live = LIVE_BYTES.with(|n| {
+            n.set(n.get() + layout.size());
+            n.get()
+        });
+        PEAK_BYTES.with(|n| n.set(n.get().max(live)));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Memory may be freed on another thread than it was allocated on
+        LIVE_BYTES.with(|n| n.set(n.get().saturating_sub(layout.size())));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Peak bytes allocated by the current thread while `f` runs, above what
+/// was live before.
+fn peak_bytes_during(f: impl FnOnce()) -> usize {
+    let before = LIVE_BYTES.with(Cell::get);
+    PEAK_BYTES.with(|n| n.set(before));
+    f();
+    PEAK_BYTES.with(Cell::get) - before
+}
+
+#[test]
+fn decode_into_buffer_does_not_allocate() {
+    let rgb: Vec<u8> = (0..=255).cycle().take(7 * 5 * 3).collect();
+    let floats: Vec<u8> = (0..35).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let mut maxval_100 = b"P6\n7 5\n100\n".to_vec();
+    maxval_100.extend(rgb.iter().map(|v| v % 101));
+    let files = [
+        encode_ppm(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pam(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pgm(&rgb, 7, 5, PixelLayout::Gray16, Unstoppable).unwrap(),
+        encode_pfm(&floats, 7, 5, PixelLayout::GrayF32, Unstoppable).unwrap(),
+        maxval_100,
+    ];
+    let mut buf = vec![0u8; 7 * 5 * 16];
+    for file in &files {
+        for layout in [PixelLayout::Rgba8, PixelLayout::Gray8, PixelLayout::RgbF32] {
+            let n = allocations_during(|| {
+                decode_into_buffer(file, &mut buf, 7 * 16, layout, Unstoppable).unwrap();
+            });
+            assert_eq!(n, 0, "{layout:?}");
+        }
+    }
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_into_buffer_does_not_allocate() {
+    let rgb: Vec<u8> = (0..=255).cycle().take(7 * 5 * 3).collect();
+    let encoded = encode_bmp(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let mut buf = vec![0u8; 7 * 5 * 4];
+    let n = allocations_during(|| {
+        decode_bmp_into_buffer(&encoded, &mut buf, 7 * 4, PixelLayout::Bgra8, Unstoppable).unwrap();
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn encode_into_does_not_allocate() {
+    let pixels: Vec<u8> = (0..=255).cycle().take(7 * 5 * 16).collect();
+    let mut out = vec![0u8; 4096];
+    for layout in [PixelLayout::Rgb8, PixelLayout::Gray16, PixelLayout::RgbaF32] {
+        let pixels = &pixels[..7 * 5 * layout.bytes_per_pixel()];
+        let n = allocations_during(|| {
+            encode_ppm_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+            encode_pgm_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+            encode_pam_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+            encode_pfm_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+            #[cfg(feature = "basic-bmp")]
+            {
+                encode_bmp_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+                encode_bmp_rgba_into(pixels, 7, 5, layout, &mut out, Unstoppable).unwrap();
+            }
+        });
+        assert_eq!(n, 0, "{layout:?}");
+    }
+}
+
+#[test]
+fn decode_reusing_allocates_only_once() {
+    let samples: Vec<u8> = (0..=255).cycle().take(7 * 5 * 8).collect();
+    let files = [
+        encode_pgm(&samples[..70], 7, 5, PixelLayout::Gray16, Unstoppable).unwrap(),
+        // The TUPLTYPE is borrowed from the header, not copied
+        encode_pam(&samples, 7, 5, PixelLayout::Rgba16, Unstoppable).unwrap(),
+    ];
+    for encoded in &files {
+        let mut buf = Vec::new();
+        decode_reusing(encoded, &mut buf, Unstoppable).unwrap();
+        let n = allocations_during(|| {
+            for _ in 0..3 {
+                decode_reusing(encoded, &mut buf, Unstoppable).unwrap();
+            }
+        });
+        assert_eq!(n, 0);
+    }
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_reusing_allocates_only_once() {
+    let rgb: Vec<u8> = (0..=255).cycle().take(7 * 5 * 3).collect();
+    let encoded = encode_bmp(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let mut buf = Vec::new();
+    decode_bmp_reusing(&encoded, &mut buf, Unstoppable).unwrap();
+    let n = allocations_during(|| {
+        for _ in 0..3 {
+            decode_bmp_reusing(&encoded, &mut buf, Unstoppable).unwrap();
+        }
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn decode_downscaled_memory_is_proportional_to_output() {
+    // 4 MB of 16-bit gray; the thumbnail is 8 KB
+    let pixels: Vec<u8> = (0..=255).cycle().take(1024 * 1024 * 2).collect();
+    let encoded = encode_pgm(&pixels, 1024, 1024, PixelLayout::Gray16, Unstoppable).unwrap();
+    for filter in [DownscaleFilter::Box, DownscaleFilter::Nearest] {
+        let peak = peak_bytes_during(|| {
+            let thumb = decode_downscaled(&encoded, 16, filter, Unstoppable).unwrap();
+            assert_eq!((thumb.width, thumb.height), (64, 64));
+        });
+        // Output, one source row and one row of sums
+        assert!(
+            peak <= 64 * 64 * 2 + 1024 * 2 + 64 * 2 * 8 + 1024,
+            "{filter:?}: {peak}"
+        );
+    }
+}
@@ -114,6 +114,2523 @@ fn flat_pfm_roundtrip_rgbf32() {
     }
 }
 
+#[test]
+fn flat_pfm_roundtrip_rgbaf32() {
+    let floats: Vec<f32> = (0..32).map(|i| i as f32 / 31.0 - 0.25).collect();
+    let pixels: Vec<u8> = floats.iter().flat_map(|f| f.to_ne_bytes()).collect();
+    let encoded = encode_pfm(&pixels, 4, 2, PixelLayout::RgbaF32, Unstoppable).unwrap();
+    assert_eq!(&encoded[..4], b"PF4\n");
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::RgbaF32);
+    assert_eq!(decoded.pixels(), &pixels[..]);
+}
+
+#[test]
+fn pam_float_roundtrip_all_layouts() {
+    for (layout, tupltype) in [
+        (PixelLayout::GrayF32, "FLOAT_GRAYSCALE"),
+        (PixelLayout::RgbF32, "FLOAT_RGB"),
+        (PixelLayout::RgbaF32, "FLOAT_RGBA"),
+    ] {
+        let n = 3 * 2 * layout.channels();
+        let floats: Vec<f32> = (0..n).map(|i| i as f32 * 1.5 - 2.0).collect();
+        let pixels: Vec<u8> = floats.iter().flat_map(|f| f.to_ne_bytes()).collect();
+        let encoded = encode_pam(&pixels, 3, 2, layout, Unstoppable).unwrap();
+        let header = core::str::from_utf8(&encoded[..encoded.len() - pixels.len()]).unwrap();
+        assert!(
+            header.contains(&format!("TUPLTYPE {tupltype}\n")),
+            "{header}"
+        );
+        let decoded = decode(&encoded, Unstoppable).unwrap();
+        assert_eq!(decoded.layout, layout);
+        assert_eq!(decoded.pixels(), &pixels[..], "{layout:?}");
+    }
+}
+
+#[test]
+fn pam_float_rgba_handwritten_big_endian() {
+    let mut data =
+        b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 4\nMAXVAL 1\nTUPLTYPE FLOAT_RGBA\nENDHDR\n".to_vec();
+    for v in [0.5f32, -1.0, 2.0, 0.25] {
+        data.extend_from_slice(&v.to_be_bytes());
+    }
+    let decoded = decode(&data, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::RgbaF32);
+    let out: Vec<f32> = decoded
+        .pixels()
+        .chunks_exact(4)
+        .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    assert_eq!(out, [0.5, -1.0, 2.0, 0.25]);
+}
+
+#[test]
+fn pam_float_depth_mismatch_rejected() {
+    let mut data =
+        b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 3\nMAXVAL 1\nTUPLTYPE FLOAT_RGBA\nENDHDR\n".to_vec();
+    data.extend_from_slice(&[0u8; 16]);
+    assert!(matches!(
+        decode(&data, Unstoppable),
+        Err(PnmError::InvalidHeader(_))
+    ));
+}
+
+#[test]
+fn half_pfm_roundtrip_all_layouts() {
+    for (layout, magic) in [
+        (PixelLayout::GrayF16, &b"Ph\n"[..]),
+        (PixelLayout::RgbF16, &b"PH\n"[..]),
+        (PixelLayout::RgbaF16, &b"PH4\n"[..]),
+    ] {
+        let n = 5 * 3 * layout.channels();
+        let pixels: Vec<u8> = (0..n)
+            .flat_map(|i| f32_to_f16(i as f32 * 0.125 - 1.0).to_ne_bytes())
+            .collect();
+        let encoded = encode_pfm(&pixels, 5, 3, layout, Unstoppable).unwrap();
+        assert!(encoded.starts_with(magic), "{layout:?}");
+        // Half the size of the f32 equivalent
+        assert!(encoded.len() < magic.len() + 16 + n * 2);
+        let decoded = decode(&encoded, Unstoppable).unwrap();
+        assert_eq!(decoded.layout, layout);
+        assert_eq!(decoded.pixels(), &pixels[..], "{layout:?}");
+    }
+}
+
+#[test]
+fn half_pam_roundtrip_all_layouts() {
+    for (layout, tupltype) in [
+        (PixelLayout::GrayF16, "FLOAT16_GRAYSCALE"),
+        (PixelLayout::RgbF16, "FLOAT16_RGB"),
+        (PixelLayout::RgbaF16, "FLOAT16_RGBA"),
+    ] {
+        let n = 2 * 4 * layout.channels();
+        let pixels: Vec<u8> = (0..n)
+            .flat_map(|i| f32_to_f16(i as f32 * 3.5).to_ne_bytes())
+            .collect();
+        let encoded = encode_pam(&pixels, 2, 4, layout, Unstoppable).unwrap();
+        let header = core::str::from_utf8(&encoded[..encoded.len() - pixels.len()]).unwrap();
+        assert!(
+            header.contains(&format!("TUPLTYPE {tupltype}\n")),
+            "{header}"
+        );
+        let decoded = decode(&encoded, Unstoppable).unwrap();
+        assert_eq!(decoded.layout, layout);
+        assert_eq!(decoded.pixels(), &pixels[..], "{layout:?}");
+    }
+}
+
+#[test]
+fn half_pfm_big_endian_with_scale() {
+    // Positive scale = big-endian; scale 2.0 doubles every sample
+    let mut data = b"Ph\n2 1\n2.0\n".to_vec();
+    for v in [0.5f32, -3.0] {
+        data.extend_from_slice(&f32_to_f16(v).to_be_bytes());
+    }
+    let decoded = decode(&data, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::GrayF16);
+    let out: Vec<f32> = decoded
+        .pixels()
+        .chunks_exact(2)
+        .map(|c| f16_to_f32(u16::from_ne_bytes([c[0], c[1]])))
+        .collect();
+    assert_eq!(out, [1.0, -6.0]);
+}
+
+#[test]
+fn widen_and_narrow_f16() {
+    let floats = [0.0f32, 1.0, -0.5, 1024.0, 0.333_333_34];
+    let f32_bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_ne_bytes()).collect();
+    let (half, layout) = narrow_to_f16(&f32_bytes[..4 * 3], PixelLayout::RgbF32).unwrap();
+    assert_eq!(layout, PixelLayout::RgbF16);
+    assert_eq!(half.len(), 6);
+    let (wide, layout) = widen_f16(&half, layout).unwrap();
+    assert_eq!(layout, PixelLayout::RgbF32);
+    assert_eq!(wide, &f32_bytes[..12]);
+
+    // Non-representable values round to the nearest half
+    let (half, _) = narrow_to_f16(&f32_bytes[16..], PixelLayout::GrayF32).unwrap();
+    let (wide, _) = widen_f16(&half, PixelLayout::GrayF16).unwrap();
+    let v = f32::from_ne_bytes([wide[0], wide[1], wide[2], wide[3]]);
+    assert!((v - 0.333_333_34).abs() < 1e-3);
+
+    assert!(widen_f16(&half, PixelLayout::Gray16).is_err());
+    assert!(narrow_to_f16(&f32_bytes, PixelLayout::Rgba8).is_err());
+}
+
+// ── Conversion ───────────────────────────────────────────────────────
+#[test]
+fn ppm_16bit_decodes_to_rgb8_unless_asked_for_rgb16() {
+    // maxval 1023: decode narrows to 8 bits as it always has
+    let mut data = b"P6\n2 1\n1023\n".to_vec();
+    for v in [0u16, 512, 1023, 1, 1022, 2000] {
+        data.extend_from_slice(&v.to_be_bytes());
+    }
+    let decoded = decode(&data, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Rgb8);
+    assert_eq!(decoded.pixels(), &[0, 128, 255, 0, 255, 255]);
+
+    // decode_as keeps samples as stored, clamped to maxval
+    let wide = decode_as(&data, PixelLayout::Rgb16, Unstoppable).unwrap();
+    assert_eq!(u16s(wide.pixels()), [0, 512, 1023, 1, 1022, 1023]);
+
+    // Added alpha is opaque at maxval, like the stored samples
+    let rgba = decode_as(&data, PixelLayout::Rgba16, Unstoppable).unwrap();
+    assert_eq!(
+        u16s(rgba.pixels()),
+        [0, 512, 1023, 1023, 1, 1022, 1023, 1023]
+    );
+
+    // Float output is scaled from maxval
+    let float = decode_as(&data, PixelLayout::RgbF32, Unstoppable).unwrap();
+    assert_eq!(f32s(float.pixels())[2], 1.0);
+}
+
+#[test]
+fn pgm_16bit_decodes_samples_as_stored() {
+    let mut data = b"P5\n3 1\n1000\n".to_vec();
+    for v in [0u16, 500, 1000] {
+        data.extend_from_slice(&v.to_be_bytes());
+    }
+    let decoded = decode(&data, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Gray16);
+    assert_eq!(u16s(decoded.pixels()), [0, 500, 1000]);
+
+    for layout in [PixelLayout::Gray16, PixelLayout::Rgb16] {
+        let wide = decode_as(&data, layout, Unstoppable).unwrap();
+        let gray: Vec<u16> = u16s(wide.pixels())
+            .into_iter()
+            .step_by(layout.channels())
+            .collect();
+        assert_eq!(gray, [0, 500, 1000]);
+    }
+}
+
+#[test]
+fn pam_16bit_rgba_roundtrip() {
+    let samples: Vec<u16> = (0..24u16).map(|i| i * 2731).collect();
+    let bytes: Vec<u8> = samples.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    let encoded = encode_pam(&bytes, 3, 2, PixelLayout::Rgba16, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Rgba8);
+    let narrow: Vec<u8> = samples
+        .iter()
+        .map(|&v| ((u32::from(v) + 128) / 257) as u8)
+        .collect();
+    assert_eq!(decoded.pixels(), &narrow[..]);
+
+    let wide = decode_as(&encoded, PixelLayout::Rgba16, Unstoppable).unwrap();
+    assert_eq!(u16s(wide.pixels()), samples);
+}
+
+fn f32s(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn u16s(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+        .collect()
+}
+
+#[test]
+fn convert_swizzles_and_alpha_fill() {
+    let rgb = [10u8, 20, 30, 40, 50, 60];
+    assert_eq!(
+        convert(&rgb, PixelLayout::Rgb8, PixelLayout::Bgr8).unwrap(),
+        [30, 20, 10, 60, 50, 40]
+    );
+    assert_eq!(
+        convert(&rgb, PixelLayout::Rgb8, PixelLayout::Rgba8).unwrap(),
+        [10, 20, 30, 255, 40, 50, 60, 255]
+    );
+    assert_eq!(
+        convert(&rgb, PixelLayout::Rgb8, PixelLayout::Bgrx8).unwrap(),
+        [30, 20, 10, 255, 60, 50, 40, 255]
+    );
+    let bgra = [1u8, 2, 3, 128];
+    assert_eq!(
+        convert(&bgra, PixelLayout::Bgra8, PixelLayout::Rgb8).unwrap(),
+        [3, 2, 1]
+    );
+    // Bgrx padding is not alpha
+    assert_eq!(
+        convert(&bgra, PixelLayout::Bgrx8, PixelLayout::Rgba8).unwrap(),
+        [3, 2, 1, 255]
+    );
+    assert_eq!(
+        convert(&[7u8], PixelLayout::Gray8, PixelLayout::Rgba8).unwrap(),
+        [7, 7, 7, 255]
+    );
+}
+
+#[test]
+fn convert_luma_rec601() {
+    let px = [255u8, 0, 0, 0, 255, 0, 0, 0, 255, 200, 200, 200];
+    let gray = convert(&px, PixelLayout::Rgb8, PixelLayout::Gray8).unwrap();
+    assert_eq!(gray, [76, 150, 29, 200]);
+
+    let wide = convert(&px, PixelLayout::Rgb8, PixelLayout::GrayF32).unwrap();
+    let wide = f32s(&wide);
+    assert!((wide[0] - 0.299).abs() < 1e-6);
+    assert!((wide[1] - 0.587).abs() < 1e-6);
+    assert!((wide[2] - 0.114).abs() < 1e-6);
+}
+
+#[test]
+fn convert_luma_weights() {
+    let px = [255u8, 0, 0, 0, 255, 0, 0, 0, 255];
+    let with = |luma| ConvertOptions {
+        luma,
+        ..Default::default()
+    };
+    let rec709 = convert_with(
+        &px,
+        PixelLayout::Rgb8,
+        PixelLayout::Gray8,
+        &with(LumaWeights::Rec709),
+    )
+    .unwrap();
+    assert_eq!(rec709, [54, 182, 18]);
+    let rec2020 = convert_with(
+        &px,
+        PixelLayout::Rgb8,
+        PixelLayout::Gray8,
+        &with(LumaWeights::Rec2020),
+    )
+    .unwrap();
+    assert_eq!(rec2020, [67, 173, 15]);
+
+    // Same weights on the float path
+    let wide = convert_with(
+        &px,
+        PixelLayout::Rgb8,
+        PixelLayout::Gray16,
+        &with(LumaWeights::Rec709),
+    )
+    .unwrap();
+    assert_eq!(u16s(&wide), [13933, 46871, 4732]);
+
+    // Gray input is never reweighted
+    let gray = [0u8, 77, 255];
+    let out = convert_with(
+        &gray,
+        PixelLayout::Gray8,
+        PixelLayout::Gray8,
+        &with(LumaWeights::Rec2020),
+    )
+    .unwrap();
+    assert_eq!(out, gray);
+}
+
+#[cfg(feature = "linear-light")]
+#[test]
+fn convert_luma_linear_light() {
+    let options = ConvertOptions {
+        luma: LumaWeights::Rec709,
+        linear_light: true,
+        ..Default::default()
+    };
+    // 50% gray stays put; pure red is darker gamma-weighted than linear-weighted
+    let px = [128u8, 128, 128, 255, 0, 0];
+    let out = convert_with(&px, PixelLayout::Rgb8, PixelLayout::Gray8, &options).unwrap();
+    assert_eq!(out[0], 128);
+    // linear_to_srgb(0.2126) ≈ 0.498
+    assert!((126..=128).contains(&out[1]), "{}", out[1]);
+
+    let rgb = checkerboard(4, 2, 3);
+    let expected = convert_with(&rgb, PixelLayout::Rgb8, PixelLayout::Gray8, &options).unwrap();
+    let encoded =
+        encode_pgm_with_options(&rgb, 4, 2, PixelLayout::Rgb8, &options, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &expected[..]);
+}
+
+#[cfg(not(feature = "linear-light"))]
+#[test]
+fn convert_luma_linear_light_requires_feature() {
+    let options = ConvertOptions {
+        linear_light: true,
+        ..Default::default()
+    };
+    let px = [1u8, 2, 3];
+    assert!(matches!(
+        convert_with(&px, PixelLayout::Rgb8, PixelLayout::Gray8, &options),
+        Err(PnmError::UnsupportedVariant(_))
+    ));
+    assert!(matches!(
+        encode_pgm_with_options(&px, 1, 1, PixelLayout::Rgb8, &options, Unstoppable),
+        Err(PnmError::UnsupportedVariant(_))
+    ));
+}
+
+#[test]
+fn encode_pgm_with_luma_weights() {
+    let rgb = noise_pattern(7, 5, 3);
+    let options = ConvertOptions {
+        luma: LumaWeights::Rec2020,
+        ..Default::default()
+    };
+    let expected = convert_with(&rgb, PixelLayout::Rgb8, PixelLayout::Gray8, &options).unwrap();
+    let encoded =
+        encode_pgm_with_options(&rgb, 7, 5, PixelLayout::Rgb8, &options, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &expected[..]);
+    assert_ne!(
+        encoded,
+        encode_pgm(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap()
+    );
+}
+
+#[test]
+fn convert_alpha_policy() {
+    // Opaque red, half-transparent green, fully transparent "garbage" blue
+    let rgba = [255u8, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0];
+    let discard = convert(&rgba, PixelLayout::Rgba8, PixelLayout::Rgb8).unwrap();
+    assert_eq!(discard, [255, 0, 0, 0, 255, 0, 0, 0, 255]);
+
+    let over_white = ConvertOptions {
+        alpha: AlphaPolicy::Composite([255, 255, 255]),
+        ..Default::default()
+    };
+    let out = convert_with(&rgba, PixelLayout::Rgba8, PixelLayout::Rgb8, &over_white).unwrap();
+    assert_eq!(out, [255, 0, 0, 127, 255, 127, 255, 255, 255]);
+
+    // Float path composites in the same way
+    let wide = convert_with(&rgba, PixelLayout::Rgba8, PixelLayout::Rgb16, &over_white).unwrap();
+    let narrow = convert(&wide, PixelLayout::Rgb16, PixelLayout::Rgb8).unwrap();
+    assert_eq!(narrow, out);
+
+    // Composite happens before luma, and Bgrx padding is not alpha
+    let gray = convert_with(&rgba, PixelLayout::Rgba8, PixelLayout::Gray8, &over_white).unwrap();
+    assert_eq!(gray[2], 255);
+    let bgrx = convert_with(&rgba, PixelLayout::Bgrx8, PixelLayout::Rgb8, &over_white).unwrap();
+    assert_eq!(bgrx[6..], [255, 0, 0][..]);
+
+    // Layouts that keep alpha are unaffected
+    let reject = ConvertOptions {
+        alpha: AlphaPolicy::Reject,
+        ..Default::default()
+    };
+    let kept = convert_with(&rgba, PixelLayout::Rgba8, PixelLayout::Bgra8, &reject).unwrap();
+    assert_eq!(kept[7], 128);
+    assert!(matches!(
+        convert_with(&rgba, PixelLayout::Rgba8, PixelLayout::Rgb8, &reject),
+        Err(PnmError::UnsupportedVariant(_))
+    ));
+}
+
+#[test]
+fn encode_alpha_policy() {
+    let rgba = [10u8, 20, 30, 0, 40, 50, 60, 255];
+    let over_black = ConvertOptions {
+        alpha: AlphaPolicy::Composite([0, 0, 0]),
+        ..Default::default()
+    };
+    let encoded =
+        encode_ppm_with_options(&rgba, 2, 1, PixelLayout::Rgba8, &over_black, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[0, 0, 0, 40, 50, 60]);
+
+    let encoded =
+        encode_pgm_with_options(&rgba, 2, 1, PixelLayout::Rgba8, &over_black, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels()[0], 0);
+
+    let reject = ConvertOptions {
+        alpha: AlphaPolicy::Reject,
+        ..Default::default()
+    };
+    for result in [
+        encode_ppm_with_options(&rgba, 2, 1, PixelLayout::Rgba8, &reject, Unstoppable),
+        encode_pgm_with_options(&rgba, 2, 1, PixelLayout::Bgra8, &reject, Unstoppable),
+    ] {
+        assert!(matches!(result, Err(PnmError::UnsupportedVariant(_))));
+    }
+    // Opaque-only input passes
+    encode_ppm_with_options(&rgba[..6], 2, 1, PixelLayout::Rgb8, &reject, Unstoppable).unwrap();
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_encode_alpha_policy() {
+    let rgba = [10u8, 20, 30, 0, 40, 50, 60, 255];
+    let over_white = ConvertOptions {
+        alpha: AlphaPolicy::Composite([255, 255, 255]),
+        ..Default::default()
+    };
+    let encoded =
+        encode_bmp_with_options(&rgba, 2, 1, PixelLayout::Rgba8, &over_white, Unstoppable).unwrap();
+    let decoded = decode_bmp(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[255, 255, 255, 40, 50, 60]);
+
+    let reject = ConvertOptions {
+        alpha: AlphaPolicy::Reject,
+        ..Default::default()
+    };
+    assert!(matches!(
+        encode_bmp_with_options(&rgba, 2, 1, PixelLayout::Rgba8, &reject, Unstoppable),
+        Err(PnmError::UnsupportedVariant(_))
+    ));
+    // Default still discards
+    let encoded = encode_bmp(&rgba, 2, 1, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let decoded = decode_bmp(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[10, 20, 30, 40, 50, 60]);
+}
+
+#[test]
+fn convert_premultiplied() {
+    let straight = [200u8, 100, 50, 128, 255, 255, 255, 0, 10, 20, 30, 255];
+    let premul = convert(&straight, PixelLayout::Rgba8, PixelLayout::Rgba8Premul).unwrap();
+    assert_eq!(premul, [100, 50, 25, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+    let back = convert(&premul, PixelLayout::Rgba8Premul, PixelLayout::Rgba8).unwrap();
+    // Fully transparent pixels lose their color; the rest survive within rounding
+    assert_eq!(back[4..], [0, 0, 0, 0, 10, 20, 30, 255][..]);
+    for (a, b) in back[..4].iter().zip(&straight[..4]) {
+        assert!(a.abs_diff(*b) <= 1, "{back:?}");
+    }
+
+    // Swizzling premultiplied data keeps it premultiplied
+    let bgra = convert(&premul, PixelLayout::Rgba8Premul, PixelLayout::Bgra8Premul).unwrap();
+    assert_eq!(bgra[..4], [25, 50, 100, 128]);
+
+    // 16-bit and f32 keep full precision
+    let wide = convert(&straight, PixelLayout::Rgba8, PixelLayout::Rgba16Premul).unwrap();
+    assert_eq!(u16s(&wide)[..4], [25801, 12900, 6450, 32896]);
+    let back = convert(&wide, PixelLayout::Rgba16Premul, PixelLayout::Rgba8).unwrap();
+    assert_eq!(back, [200, 100, 50, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+    let floats = convert(&straight, PixelLayout::Rgba8, PixelLayout::RgbaF32Premul).unwrap();
+    let f = f32s(&floats);
+    assert!((f[0] - 200.0 / 255.0 * 128.0 / 255.0).abs() < 1e-6);
+    let back = convert(&floats, PixelLayout::RgbaF32Premul, PixelLayout::Rgba8).unwrap();
+    assert_eq!(back, [200, 100, 50, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+
+    // In place between equal-sized layouts
+    let mut buf = premul.clone();
+    convert_in_place(&mut buf, PixelLayout::Rgba8Premul, PixelLayout::Rgba8).unwrap();
+    assert_eq!(
+        buf,
+        convert(&premul, PixelLayout::Rgba8Premul, PixelLayout::Rgba8).unwrap()
+    );
+
+    assert_eq!(
+        PixelLayout::Bgra8.premultiplied(),
+        Some(PixelLayout::Bgra8Premul)
+    );
+    assert_eq!(PixelLayout::Rgb8.premultiplied(), None);
+    assert_eq!(PixelLayout::RgbaF32Premul.straight(), PixelLayout::RgbaF32);
+}
+
+#[test]
+fn encode_premultiplied_stores_straight() {
+    let straight = [200u8, 100, 50, 128, 10, 20, 30, 255];
+    let premul = convert(&straight, PixelLayout::Rgba8, PixelLayout::Bgra8Premul).unwrap();
+    let expected = convert(&premul, PixelLayout::Bgra8Premul, PixelLayout::Rgba8).unwrap();
+
+    let encoded = encode_pam(&premul, 2, 1, PixelLayout::Bgra8Premul, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Rgba8);
+    assert_eq!(decoded.pixels(), &expected[..]);
+
+    let encoded = encode_ppm(&premul, 2, 1, PixelLayout::Bgra8Premul, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(
+        decoded.pixels(),
+        &[expected[0], expected[1], expected[2], 10, 20, 30]
+    );
+
+    let floats = convert(&straight, PixelLayout::Rgba8, PixelLayout::RgbaF32Premul).unwrap();
+    let encoded = encode_pfm(&floats, 2, 1, PixelLayout::RgbaF32Premul, Unstoppable).unwrap();
+    assert!(encoded.starts_with(b"PF4\n"));
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::RgbaF32);
+    let back = convert(decoded.pixels(), PixelLayout::RgbaF32, PixelLayout::Rgba8).unwrap();
+    assert_eq!(back, straight);
+}
+
+#[test]
+fn decode_into_premultiplied() {
+    let straight = [200u8, 100, 50, 128, 10, 20, 30, 255];
+    let encoded = encode_pam(&straight, 2, 1, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable)
+        .unwrap()
+        .into_premultiplied()
+        .unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Rgba8Premul);
+    assert_eq!(decoded.pixels(), &[100, 50, 25, 128, 10, 20, 30, 255]);
+
+    // No alpha: unchanged and still zero-copy
+    let encoded = encode_ppm(&straight[..6], 2, 1, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable)
+        .unwrap()
+        .into_premultiplied()
+        .unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Rgb8);
+    assert!(decoded.is_borrowed());
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_encode_premultiplied() {
+    let straight = [200u8, 100, 50, 128, 10, 20, 30, 255];
+    let premul = convert(&straight, PixelLayout::Rgba8, PixelLayout::Bgra8Premul).unwrap();
+    let expected = convert(&premul, PixelLayout::Bgra8Premul, PixelLayout::Bgra8).unwrap();
+    let encoded = encode_bmp_rgba(&premul, 2, 1, PixelLayout::Bgra8Premul, Unstoppable).unwrap();
+    let decoded = decode_bmp_native(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &expected[..]);
+}
+
+#[test]
+fn convert_range_mapping() {
+    let px = [0u8, 1, 128, 255];
+    let wide = convert(&px, PixelLayout::Gray8, PixelLayout::Gray16).unwrap();
+    assert_eq!(u16s(&wide), [0, 257, 32896, 65535]);
+    let back = convert(&wide, PixelLayout::Gray16, PixelLayout::Gray8).unwrap();
+    assert_eq!(back, px);
+
+    let floats: Vec<u8> = [-0.5f32, 0.5, 1.5, f32::NAN]
+        .iter()
+        .flat_map(|f| f.to_ne_bytes())
+        .collect();
+    let clamped = convert(&floats, PixelLayout::GrayF32, PixelLayout::Gray8).unwrap();
+    assert_eq!(clamped, [0, 128, 255, 0]);
+
+    // Float → float keeps out-of-range values
+    let half = convert(&floats, PixelLayout::GrayF32, PixelLayout::GrayF16).unwrap();
+    let back = f32s(&convert(&half, PixelLayout::GrayF16, PixelLayout::GrayF32).unwrap());
+    assert_eq!(&back[..3], [-0.5, 0.5, 1.5]);
+    assert!(back[3].is_nan());
+}
+
+#[test]
+fn convert_every_layout_pair_roundtrips_opaque_rgb8() {
+    let layouts = [
+        PixelLayout::Rgb8,
+        PixelLayout::Rgba8,
+        PixelLayout::Rgb16,
+        PixelLayout::Rgba16,
+        PixelLayout::Bgr8,
+        PixelLayout::Bgra8,
+        PixelLayout::Bgrx8,
+        PixelLayout::RgbF32,
+        PixelLayout::RgbaF32,
+        PixelLayout::RgbF16,
+        PixelLayout::RgbaF16,
+    ];
+    let src = noise_pattern(16, 1, 3);
+    for &a in &layouts {
+        let mid = convert(&src, PixelLayout::Rgb8, a).unwrap();
+        assert_eq!(mid.len(), 16 * a.bytes_per_pixel());
+        for &b in &layouts {
+            let there = convert(&mid, a, b).unwrap();
+            let back = convert(&there, b, PixelLayout::Rgb8).unwrap();
+            assert_eq!(back, src, "{a:?} -> {b:?}");
+        }
+    }
+}
+
+#[test]
+fn convert_into_and_in_place() {
+    let rgba = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let mut dst = [0u8; 8];
+    assert!(matches!(
+        convert_into(&rgba, PixelLayout::Rgba8, &mut dst[..5], PixelLayout::Rgb8),
+        Err(PnmError::BufferTooSmall {
+            needed: 6,
+            actual: 5
+        })
+    ));
+    convert_into(&rgba, PixelLayout::Rgba8, &mut dst, PixelLayout::Rgb8).unwrap();
+    assert_eq!(dst, [1, 2, 3, 5, 6, 7, 0, 0]);
+
+    let mut buf = rgba;
+    convert_in_place(&mut buf, PixelLayout::Rgba8, PixelLayout::Bgra8).unwrap();
+    assert_eq!(buf, [3, 2, 1, 4, 7, 6, 5, 8]);
+    convert_in_place(&mut buf, PixelLayout::Bgra8, PixelLayout::Bgrx8).unwrap();
+    assert_eq!(buf, [3, 2, 1, 255, 7, 6, 5, 255]);
+
+    assert!(convert_in_place(&mut buf, PixelLayout::Rgba8, PixelLayout::Rgb8).is_err());
+    assert!(convert(&rgba[..5], PixelLayout::Rgba8, PixelLayout::Rgb8).is_err());
+}
+
+#[test]
+fn encoders_accept_any_layout() {
+    let rgb = checkerboard(5, 3, 3);
+    let bgr = convert(&rgb, PixelLayout::Rgb8, PixelLayout::Bgr8).unwrap();
+    assert_eq!(
+        encode_ppm(&bgr, 5, 3, PixelLayout::Bgr8, Unstoppable).unwrap(),
+        encode_ppm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap()
+    );
+
+    let gray = convert(&rgb, PixelLayout::Rgb8, PixelLayout::Gray8).unwrap();
+    let encoded = encode_pgm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &gray[..]);
+
+    let floats = convert(&rgb, PixelLayout::Rgb8, PixelLayout::RgbF32).unwrap();
+    let encoded = encode_pfm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::RgbF32);
+    assert_eq!(decoded.pixels(), &floats[..]);
+}
+
+#[test]
+fn encode_pgm_gray16_roundtrip() {
+    let samples: Vec<u16> = (0..12u16).map(|i| i * 5000 + 7).collect();
+    let bytes: Vec<u8> = samples.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    let encoded = encode_pgm(&bytes, 4, 3, PixelLayout::Gray16, Unstoppable).unwrap();
+    assert!(encoded.starts_with(b"P5\n4 3\n65535\n"));
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Gray16);
+    assert_eq!(u16s(decoded.pixels()), samples);
+}
+
+// ── Fused decode ─────────────────────────────────────────────────────
+
+const DECODE_AS_TARGETS: [PixelLayout; 10] = [
+    PixelLayout::Gray8,
+    PixelLayout::Rgb8,
+    PixelLayout::Rgba8,
+    PixelLayout::Bgr8,
+    PixelLayout::Bgrx8,
+    PixelLayout::Gray16,
+    PixelLayout::Rgba16,
+    PixelLayout::RgbF32,
+    PixelLayout::RgbaF16,
+    PixelLayout::Rgba8Premul,
+];
+
+#[test]
+fn decode_as_matches_decode_then_convert() {
+    let rgb = noise_pattern(5, 3, 3);
+    let rgba = noise_pattern(5, 3, 4);
+    let floats: Vec<u8> = (0..45)
+        .flat_map(|i| (i as f32 / 44.0).to_ne_bytes())
+        .collect();
+    let mut maxval_100 = b"P6\n5 3\n100\n".to_vec();
+    maxval_100.extend(rgb.iter().map(|v| v % 101));
+    let mut maxval_1000 = b"P5\n5 3\n1000\n".to_vec();
+    for i in 0..15u16 {
+        maxval_1000.extend_from_slice(&(i * 66).to_be_bytes());
+    }
+    let files = [
+        encode_pgm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_ppm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pam(&rgba, 5, 3, PixelLayout::Rgba8, Unstoppable).unwrap(),
+        encode_pam(&floats, 5, 3, PixelLayout::RgbF32, Unstoppable).unwrap(),
+        encode_pfm(&floats, 5, 3, PixelLayout::RgbF32, Unstoppable).unwrap(),
+        maxval_100,
+        maxval_1000,
+    ];
+    for file in &files {
+        let info = probe(file).unwrap();
+        let decoded = decode_as(file, info.layout, Unstoppable).unwrap();
+        // 16-bit samples stay as stored in 16-bit targets, with added alpha
+        // opaque at maxval, and are scaled from maxval for the others
+        let maxval = info.source.maxval.filter(|&m| m > 255);
+        let full_range: Vec<u8> = match maxval {
+            Some(maxval) => u16s(decoded.pixels())
+                .into_iter()
+                .flat_map(|v| {
+                    let v = (u32::from(v) * 65535 + maxval / 2) / maxval;
+                    (v as u16).to_ne_bytes()
+                })
+                .collect(),
+            None => decoded.pixels().to_vec(),
+        };
+        for target in DECODE_AS_TARGETS {
+            let fused = decode_as(file, target, Unstoppable).unwrap();
+            assert_eq!(fused.layout, target);
+            assert_eq!((fused.width, fused.height), (5, 3));
+            let stored = maxval.is_some()
+                && matches!(
+                    target,
+                    PixelLayout::Gray16 | PixelLayout::Rgb16 | PixelLayout::Rgba16
+                );
+            let expected = if stored {
+                let mut expected = convert(decoded.pixels(), decoded.layout, target).unwrap();
+                if target.has_alpha() && !decoded.layout.has_alpha() {
+                    let bpp = target.bytes_per_pixel();
+                    for pixel in expected.chunks_exact_mut(bpp) {
+                        let opaque = (maxval.unwrap() as u16).to_ne_bytes();
+                        pixel[bpp - 2..].copy_from_slice(&opaque);
+                    }
+                }
+                expected
+            } else {
+                convert(&full_range, decoded.layout, target).unwrap()
+            };
+            assert_eq!(
+                fused.pixels(),
+                &expected[..],
+                "{:?} -> {target:?}",
+                decoded.layout
+            );
+        }
+    }
+}
+
+#[test]
+fn decode_as_borrows_when_layout_matches() {
+    let pixels = checkerboard(4, 4, 3);
+    let encoded = encode_ppm(&pixels, 4, 4, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let same = decode_as(&encoded, PixelLayout::Rgb8, Unstoppable).unwrap();
+    assert!(same.is_borrowed());
+    assert_eq!(same.pixels(), &pixels[..]);
+    assert!(
+        !decode_as(&encoded, PixelLayout::Rgba8, Unstoppable)
+            .unwrap()
+            .is_borrowed()
+    );
+
+    // PFM rows are stored bottom-up, so even a matching layout is owned
+    let floats: Vec<u8> = (0..16).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let pfm = encode_pfm(&floats, 4, 4, PixelLayout::GrayF32, Unstoppable).unwrap();
+    let decoded = decode_as(&pfm, PixelLayout::GrayF32, Unstoppable).unwrap();
+    assert!(!decoded.is_borrowed());
+    assert_eq!(decoded.pixels(), &floats[..]);
+}
+
+#[test]
+fn decode_as_limits_apply_to_target() {
+    let pixels = checkerboard(4, 4, 3);
+    let encoded = encode_ppm(&pixels, 4, 4, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_memory_bytes(4 * 4 * 3);
+    assert!(decode_as_with_limits(&encoded, PixelLayout::Gray8, &limits, Unstoppable).is_ok());
+    assert!(matches!(
+        decode_as_with_limits(&encoded, PixelLayout::Rgba8, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn decode_as_truncated_data() {
+    let pixels = checkerboard(4, 4, 3);
+    let encoded = encode_ppm(&pixels, 4, 4, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let truncated = &encoded[..encoded.len() - 1];
+    assert!(matches!(
+        decode_as(truncated, PixelLayout::Rgba8, Unstoppable),
+        Err(PnmError::UnexpectedEof)
+    ));
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_as_matches_decode_then_convert() {
+    let rgb = noise_pattern(5, 3, 3);
+    let rgba = noise_pattern(5, 3, 4);
+    let files = [
+        encode_bmp(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_bmp_rgba(&rgba, 5, 3, PixelLayout::Rgba8, Unstoppable).unwrap(),
+    ];
+    for file in &files {
+        let decoded = decode_bmp(file, Unstoppable).unwrap();
+        for target in DECODE_AS_TARGETS {
+            let fused = decode_bmp_as(file, target, Unstoppable).unwrap();
+            assert_eq!(fused.layout, target);
+            let expected = convert(decoded.pixels(), decoded.layout, target).unwrap();
+            assert_eq!(fused.pixels(), &expected[..], "-> {target:?}");
+        }
+    }
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_as_top_down() {
+    let pixels = checkerboard(3, 2, 3);
+    let mut encoded = encode_bmp(&pixels, 3, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
+    // Negate the height: the same stored rows now read top-to-bottom
+    encoded[22..26].copy_from_slice(&(-2i32).to_le_bytes());
+    let decoded = decode_bmp_as(&encoded, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let expected = convert(
+        &[&pixels[9..], &pixels[..9]].concat(),
+        PixelLayout::Rgb8,
+        PixelLayout::Rgba8,
+    )
+    .unwrap();
+    assert_eq!(decoded.pixels(), &expected[..]);
+}
+
+#[test]
+fn decode_reusing_matches_decode_and_keeps_capacity() {
+    let rgb = noise_pattern(6, 5, 3);
+    let floats: Vec<u8> = (0..30).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let mut maxval_100 = b"P6\n6 5\n100\n".to_vec();
+    maxval_100.extend(rgb.iter().map(|v| v % 101));
+    let files = [
+        encode_pgm(&rgb[..60], 6, 5, PixelLayout::Gray16, Unstoppable).unwrap(),
+        encode_pfm(&floats, 6, 5, PixelLayout::GrayF32, Unstoppable).unwrap(),
+        maxval_100,
+    ];
+
+    let mut buf = Vec::new();
+    for file in &files {
+        let expected = decode(file, Unstoppable).unwrap();
+        let decoded = decode_reusing(file, &mut buf, Unstoppable).unwrap();
+        assert!(decoded.is_borrowed());
+        assert_eq!(decoded.pixels(), expected.pixels());
+        assert_eq!(
+            (decoded.width, decoded.height, decoded.layout),
+            (expected.width, expected.height, expected.layout)
+        );
+    }
+
+    // A same-sized decode reuses the allocation
+    let ptr = buf.as_ptr();
+    let decoded = decode_reusing(&files[2], &mut buf, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels().as_ptr(), ptr);
+}
+
+#[test]
+fn decode_reusing_zero_copy_leaves_buffer_alone() {
+    let pixels = checkerboard(4, 4, 3);
+    let encoded = encode_ppm(&pixels, 4, 4, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let mut buf = vec![7u8; 3];
+    let decoded = decode_reusing(&encoded, &mut buf, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &pixels[..]);
+    assert!(core::ptr::eq(
+        decoded.pixels().as_ptr(),
+        encoded[encoded.len() - 48..].as_ptr()
+    ));
+    assert_eq!(buf, [7, 7, 7]);
+}
+
+#[test]
+fn decode_reusing_applies_limits() {
+    let encoded = encode_pgm(&[0u8; 32], 4, 4, PixelLayout::Gray16, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_memory_bytes(31);
+    let mut buf = Vec::new();
+    assert!(matches!(
+        decode_reusing_with_limits(&encoded, &mut buf, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_reusing_matches_decode() {
+    let mut buf = Vec::new();
+    for (w, h) in [(5, 3), (5, 3), (2, 7)] {
+        let pixels = noise_pattern(w, h, 4);
+        let encoded =
+            encode_bmp_rgba(&pixels, w as u32, h as u32, PixelLayout::Rgba8, Unstoppable).unwrap();
+        let decoded = decode_bmp_reusing(&encoded, &mut buf, Unstoppable).unwrap();
+        assert_eq!(decoded.pixels(), &pixels[..]);
+        assert_eq!(decoded.layout, PixelLayout::Rgba8);
+    }
+}
+
+#[test]
+fn decode_into_buffer_matches_decode_as_with_stride() {
+    let rgb = noise_pattern(5, 3, 3);
+    let floats: Vec<u8> = (0..15)
+        .flat_map(|i| (i as f32 / 14.0).to_ne_bytes())
+        .collect();
+    let mut maxval_1000 = b"P5\n5 3\n1000\n".to_vec();
+    for i in 0..15u16 {
+        maxval_1000.extend_from_slice(&(i * 66).to_be_bytes());
+    }
+    let files = [
+        encode_ppm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pfm(&floats, 5, 3, PixelLayout::GrayF32, Unstoppable).unwrap(),
+        maxval_1000,
+    ];
+    for file in &files {
+        for target in DECODE_AS_TARGETS {
+            let expected = decode_as(file, target, Unstoppable).unwrap();
+            let row_bytes = 5 * target.bytes_per_pixel();
+            let stride = row_bytes + 5;
+            let mut buf = vec![0xEE; stride * 3];
+            let dims = decode_into_buffer(file, &mut buf, stride, target, Unstoppable).unwrap();
+            assert_eq!(dims, (5, 3));
+            for (y, row) in buf.chunks(stride).enumerate() {
+                let want = &expected.pixels()[y * row_bytes..][..row_bytes];
+                assert_eq!(&row[..row_bytes], want, "{target:?} row {y}");
+                assert!(row[row_bytes..].iter().all(|&b| b == 0xEE));
+            }
+        }
+    }
+}
+
+#[test]
+fn decode_into_buffer_validates_size_first() {
+    let pixels = checkerboard(4, 2, 3);
+    let encoded = encode_ppm(&pixels, 4, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
+
+    // Last row needs no padding: 16 + 12 bytes
+    let mut exact = vec![0u8; 28];
+    assert!(decode_into_buffer(&encoded, &mut exact, 16, PixelLayout::Rgb8, Unstoppable).is_ok());
+
+    let mut short = vec![0xEE; 27];
+    assert!(matches!(
+        decode_into_buffer(&encoded, &mut short, 16, PixelLayout::Rgb8, Unstoppable),
+        Err(PnmError::BufferTooSmall {
+            needed: 28,
+            actual: 27
+        })
+    ));
+    assert!(short.iter().all(|&b| b == 0xEE));
+
+    let mut buf = vec![0u8; 64];
+    assert!(matches!(
+        decode_into_buffer(&encoded, &mut buf, 15, PixelLayout::Rgba8, Unstoppable),
+        Err(PnmError::InvalidData(_))
+    ));
+
+    let limits = Limits::default().with_max_width(3);
+    assert!(matches!(
+        decode_into_buffer_with_limits(
+            &encoded,
+            &mut buf,
+            16,
+            PixelLayout::Rgba8,
+            &limits,
+            Unstoppable
+        ),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_into_buffer_matches_decode_as() {
+    let rgba = noise_pattern(5, 3, 4);
+    let encoded = encode_bmp_rgba(&rgba, 5, 3, PixelLayout::Rgba8, Unstoppable).unwrap();
+    for target in DECODE_AS_TARGETS {
+        let expected = decode_bmp_as(&encoded, target, Unstoppable).unwrap();
+        let row_bytes = 5 * target.bytes_per_pixel();
+        let stride = row_bytes + 3;
+        let mut buf = vec![0xEE; stride * 3];
+        let dims = decode_bmp_into_buffer(&encoded, &mut buf, stride, target, Unstoppable).unwrap();
+        assert_eq!(dims, (5, 3));
+        for (y, row) in buf.chunks(stride).enumerate() {
+            assert_eq!(
+                &row[..row_bytes],
+                &expected.pixels()[y * row_bytes..][..row_bytes]
+            );
+            assert!(row[row_bytes..].iter().all(|&b| b == 0xEE));
+        }
+    }
+}
+
+// ── Region decode ────────────────────────────────────────────────────
+
+/// Rows `y..y + h`, pixels `x..x + w` of a packed image `width` pixels wide.
+fn crop(
+    pixels: &[u8],
+    width: usize,
+    bpp: usize,
+    (x, y, w, h): (usize, usize, usize, usize),
+) -> Vec<u8> {
+    (y..y + h)
+        .flat_map(|row| &pixels[(row * width + x) * bpp..(row * width + x + w) * bpp])
+        .copied()
+        .collect()
+}
+
+const REGIONS: [(usize, usize, usize, usize); 5] = [
+    (0, 0, 6, 5),
+    (1, 2, 3, 2),
+    (5, 0, 1, 5),
+    (0, 4, 6, 1),
+    (2, 3, 0, 2),
+];
+
+#[test]
+fn decode_region_matches_crop_of_full_decode() {
+    let rgb = noise_pattern(6, 5, 3);
+    let rgba = noise_pattern(6, 5, 4);
+    let floats: Vec<u8> = (0..90).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let mut maxval_100 = b"P6\n6 5\n100\n".to_vec();
+    maxval_100.extend(rgb.iter().map(|v| v % 101));
+    let files = [
+        encode_ppm(&rgb, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pgm(&rgb[..60], 6, 5, PixelLayout::Gray16, Unstoppable).unwrap(),
+        encode_pam(&rgba, 6, 5, PixelLayout::Rgba8, Unstoppable).unwrap(),
+        encode_pfm(&floats, 6, 5, PixelLayout::RgbF32, Unstoppable).unwrap(),
+        maxval_100,
+    ];
+    for file in &files {
+        let full = decode(file, Unstoppable).unwrap();
+        let bpp = full.layout.bytes_per_pixel();
+        for region in REGIONS {
+            let (x, y, w, h) = region;
+            let decoded =
+                decode_region(file, x as u32, y as u32, w as u32, h as u32, Unstoppable).unwrap();
+            assert_eq!(
+                (decoded.width, decoded.height, decoded.layout),
+                (w as u32, h as u32, full.layout)
+            );
+            assert_eq!(
+                decoded.into_owned().pixels(),
+                &crop(full.pixels(), 6, bpp, region)[..],
+                "{:?} {region:?}",
+                full.layout
+            );
+        }
+    }
+}
+
+#[test]
+fn decode_region_borrows_rows_and_reads_only_them() {
+    let pixels = noise_pattern(6, 5, 3);
+    let encoded = encode_ppm(&pixels, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let header_len = encoded.len() - pixels.len();
+
+    let decoded = decode_region(&encoded, 1, 1, 3, 2, Unstoppable).unwrap();
+    assert!(decoded.is_borrowed());
+    assert_eq!(decoded.stride(), 18);
+    assert!(core::ptr::eq(
+        decoded.pixels().as_ptr(),
+        encoded[header_len + 18 + 3..].as_ptr()
+    ));
+    // Rows in between are the source rows; packing drops the rest
+    assert_eq!(decoded.pixels().len(), 18 + 9);
+    let owned = decoded.into_owned();
+    assert_eq!(owned.stride(), 9);
+    assert_eq!(owned.pixels(), &crop(&pixels, 6, 3, (1, 1, 3, 2))[..]);
+
+    // Data past the region's last pixel is never read
+    let end = header_len + 2 * 18 + 4 * 3;
+    assert!(decode_region(&encoded[..end], 1, 1, 3, 2, Unstoppable).is_ok());
+    assert!(matches!(
+        decode_region(&encoded[..end - 1], 1, 1, 3, 2, Unstoppable),
+        Err(PnmError::UnexpectedEof)
+    ));
+
+    // Transformed formats read only the region's rows as well
+    let gray16 = encode_pgm(&pixels[..60], 6, 5, PixelLayout::Gray16, Unstoppable).unwrap();
+    let header_len = gray16.len() - 60;
+    let decoded = decode_region(&gray16[..header_len + 2 * 12], 0, 0, 6, 2, Unstoppable).unwrap();
+    assert!(!decoded.is_borrowed());
+    assert_eq!(decoded.stride(), 12);
+}
+
+#[test]
+fn decode_region_limits_apply_to_region() {
+    let pixels = checkerboard(6, 5, 3);
+    let encoded = encode_ppm(&pixels, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_width(2).with_max_height(2);
+    assert!(decode_with_limits(&encoded, &limits, Unstoppable).is_err());
+    assert!(decode_region_with_limits(&encoded, 4, 3, 2, 2, &limits, Unstoppable).is_ok());
+    assert!(matches!(
+        decode_region_with_limits(&encoded, 0, 0, 3, 2, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+
+    let gray16 = encode_pgm(&[0u8; 60], 6, 5, PixelLayout::Gray16, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_memory_bytes(8);
+    assert!(decode_region_with_limits(&gray16, 0, 0, 2, 2, &limits, Unstoppable).is_ok());
+    assert!(matches!(
+        decode_region_with_limits(&gray16, 0, 0, 3, 2, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn decode_region_rejects_out_of_bounds() {
+    let pixels = checkerboard(6, 5, 3);
+    let encoded = encode_ppm(&pixels, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    for (x, y, w, h) in [
+        (0, 0, 7, 1),
+        (5, 0, 2, 1),
+        (0, 5, 1, 1),
+        (u32::MAX, 0, 2, 1),
+    ] {
+        assert!(
+            matches!(
+                decode_region(&encoded, x, y, w, h, Unstoppable),
+                Err(PnmError::InvalidData(_))
+            ),
+            "{x} {y} {w} {h}"
+        );
+    }
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_region_matches_crop() {
+    let rgb = noise_pattern(6, 5, 3);
+    let rgba = noise_pattern(6, 5, 4);
+    let files = [
+        encode_bmp(&rgb, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_bmp_rgba(&rgba, 6, 5, PixelLayout::Rgba8, Unstoppable).unwrap(),
+    ];
+    for file in &files {
+        let full = decode_bmp(file, Unstoppable).unwrap();
+        let bpp = full.layout.bytes_per_pixel();
+        let mut top_down = file.clone();
+        top_down[22..26].copy_from_slice(&(-5i32).to_le_bytes());
+        let flipped = decode_bmp(&top_down, Unstoppable).unwrap();
+        for region in REGIONS {
+            let (x, y, w, h) = region;
+            let (x, y, w, h) = (x as u32, y as u32, w as u32, h as u32);
+            let decoded = decode_bmp_region(file, x, y, w, h, Unstoppable).unwrap();
+            assert_eq!(decoded.layout, full.layout);
+            assert_eq!(decoded.pixels(), &crop(full.pixels(), 6, bpp, region)[..]);
+            let decoded = decode_bmp_region(&top_down, x, y, w, h, Unstoppable).unwrap();
+            assert_eq!(
+                decoded.pixels(),
+                &crop(flipped.pixels(), 6, bpp, region)[..]
+            );
+        }
+    }
+
+    // Bottom-up files store the last rows first, so they are all that is read
+    let header_len = files[0].len() - 5 * 20;
+    let truncated = &files[0][..header_len + 2 * 20];
+    assert!(decode_bmp_region(truncated, 0, 3, 6, 2, Unstoppable).is_ok());
+    assert!(matches!(
+        decode_bmp_region(truncated, 0, 2, 6, 2, Unstoppable),
+        Err(PnmError::UnexpectedEof)
+    ));
+    assert!(matches!(
+        decode_bmp_region(&files[0], 0, 0, 6, 6, Unstoppable),
+        Err(PnmError::InvalidData(_))
+    ));
+}
+
+// ── Downscaled decode ────────────────────────────────────────────────
+
+/// Box-filter `samples` (`channels` per pixel, opaque) by `factor`, with
+/// partial blocks at the edges.
+fn box_reference(samples: &[f64], w: usize, h: usize, channels: usize, factor: usize) -> Vec<f64> {
+    let mut out = Vec::new();
+    for oy in 0..h.div_ceil(factor) {
+        for ox in 0..w.div_ceil(factor) {
+            let ys = oy * factor..((oy + 1) * factor).min(h);
+            let xs = ox * factor..((ox + 1) * factor).min(w);
+            let n = (ys.len() * xs.len()) as f64;
+            for c in 0..channels {
+                let sum: f64 = ys
+                    .clone()
+                    .flat_map(|y| xs.clone().map(move |x| (y * w + x) * channels + c))
+                    .map(|i| samples[i])
+                    .sum();
+                out.push(sum / n);
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn decode_downscaled_box_matches_reference() {
+    let rgb = noise_pattern(7, 5, 3);
+    let gray16 = noise_pattern(7, 5, 2);
+    let floats: Vec<f32> = (0..105).map(|i| i as f32 / 7.0 - 3.0).collect();
+    let float_bytes: Vec<u8> = floats.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    for factor in [1, 2, 3, 7, 10] {
+        let (ow, oh) = (7usize.div_ceil(factor), 5usize.div_ceil(factor));
+
+        let encoded = encode_ppm(&rgb, 7, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+        let thumb =
+            decode_downscaled(&encoded, factor as u32, DownscaleFilter::Box, Unstoppable).unwrap();
+        assert_eq!((thumb.width, thumb.height), (ow as u32, oh as u32));
+        assert_eq!(thumb.layout, PixelLayout::Rgb8);
+        let samples: Vec<f64> = rgb.iter().map(|&v| f64::from(v)).collect();
+        let expected: Vec<u8> = box_reference(&samples, 7, 5, 3, factor)
+            .iter()
+            .map(|v| (v + 0.5) as u8)
+            .collect();
+        assert_eq!(thumb.pixels(), &expected[..], "rgb8 / {factor}");
+
+        let encoded = encode_pgm(&gray16, 7, 5, PixelLayout::Gray16, Unstoppable).unwrap();
+        let thumb =
+            decode_downscaled(&encoded, factor as u32, DownscaleFilter::Box, Unstoppable).unwrap();
+        assert_eq!(thumb.layout, PixelLayout::Gray16);
+        let samples: Vec<f64> = u16s(&gray16).iter().map(|&v| f64::from(v)).collect();
+        let expected: Vec<u16> = box_reference(&samples, 7, 5, 1, factor)
+            .iter()
+            .map(|v| (v + 0.5) as u16)
+            .collect();
+        assert_eq!(u16s(thumb.pixels()), expected, "gray16 / {factor}");
+
+        // PFM rows are stored bottom-up; the thumbnail is top-down
+        let encoded = encode_pfm(&float_bytes, 7, 5, PixelLayout::RgbF32, Unstoppable).unwrap();
+        let thumb =
+            decode_downscaled(&encoded, factor as u32, DownscaleFilter::Box, Unstoppable).unwrap();
+        assert_eq!(thumb.layout, PixelLayout::RgbF32);
+        let samples: Vec<f64> = floats.iter().map(|&v| f64::from(v)).collect();
+        let expected: Vec<f32> = box_reference(&samples[..105], 7, 5, 3, factor)
+            .iter()
+            .map(|&v| v as f32)
+            .collect();
+        assert_eq!(f32s(thumb.pixels()), expected, "rgbf32 / {factor}");
+    }
+}
+
+#[test]
+fn decode_downscaled_box_weights_color_by_alpha() {
+    // 4x2 RGBA: the left block mixes opaque red with transparent green, the
+    // right block is transparent throughout
+    #[rustfmt::skip]
+    let pixels = [
+        255, 0, 0, 255,   0, 255, 0, 0,   9, 9, 9, 0,   9, 9, 9, 0,
+        255, 0, 0, 255,   0, 255, 0, 0,   9, 9, 9, 0,   9, 9, 9, 0,
+    ];
+    let encoded = encode_pam(&pixels, 4, 2, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let thumb = decode_downscaled(&encoded, 2, DownscaleFilter::Box, Unstoppable).unwrap();
+    assert_eq!(thumb.pixels(), &[255, 0, 0, 128, 0, 0, 0, 0]);
+
+    let to_16 = |v: &u8| u16::from(*v) * 257;
+    let wide: Vec<u8> = pixels.iter().flat_map(|v| to_16(v).to_ne_bytes()).collect();
+    let encoded = encode_pam(&wide, 4, 2, PixelLayout::Rgba16, Unstoppable).unwrap();
+    let thumb = decode_downscaled(&encoded, 2, DownscaleFilter::Box, Unstoppable).unwrap();
+    assert_eq!(thumb.layout, PixelLayout::Rgba8);
+    assert_eq!(thumb.pixels(), &[255, 0, 0, 128, 0, 0, 0, 0]);
+
+    let floats: Vec<u8> = pixels
+        .iter()
+        .flat_map(|&v| (f32::from(v) / 255.0).to_ne_bytes())
+        .collect();
+    let encoded = encode_pfm(&floats, 4, 2, PixelLayout::RgbaF32, Unstoppable).unwrap();
+    let thumb = decode_downscaled(&encoded, 2, DownscaleFilter::Box, Unstoppable).unwrap();
+    assert_eq!(
+        f32s(thumb.pixels()),
+        [1.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0]
+    );
+}
+
+#[test]
+fn decode_downscaled_nearest_samples_block_centers() {
+    let pixels: Vec<u8> = (0..35).collect();
+    let encoded = encode_pgm(&pixels, 7, 5, PixelLayout::Gray8, Unstoppable).unwrap();
+    let thumb = decode_downscaled(&encoded, 3, DownscaleFilter::Nearest, Unstoppable).unwrap();
+    assert_eq!((thumb.width, thumb.height), (3, 2));
+    // Centers: columns 1, 4, 6 (partial block); rows 1, 4 (partial block)
+    assert_eq!(thumb.pixels(), &[8, 11, 13, 29, 32, 34]);
+
+    let floats: Vec<u8> = (0..35).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let encoded = encode_pfm(&floats, 7, 5, PixelLayout::GrayF32, Unstoppable).unwrap();
+    let thumb = decode_downscaled(&encoded, 3, DownscaleFilter::Nearest, Unstoppable).unwrap();
+    assert_eq!(f32s(thumb.pixels()), [8.0, 11.0, 13.0, 29.0, 32.0, 34.0]);
+}
+
+#[test]
+fn decode_downscaled_limits_and_factor() {
+    let pixels = checkerboard(8, 8, 3);
+    let encoded = encode_ppm(&pixels, 8, 8, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_pixels(16);
+    let thumb =
+        decode_downscaled_with_limits(&encoded, 2, DownscaleFilter::Box, &limits, Unstoppable);
+    assert_eq!(thumb.unwrap().pixels().len(), 4 * 4 * 3);
+    assert!(matches!(
+        decode_downscaled_with_limits(&encoded, 1, DownscaleFilter::Box, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+    assert!(matches!(
+        decode_downscaled(&encoded, 0, DownscaleFilter::Box, Unstoppable),
+        Err(PnmError::InvalidData(_))
+    ));
+    assert!(matches!(
+        decode_downscaled(
+            &encoded[..encoded.len() - 1],
+            4,
+            DownscaleFilter::Box,
+            Unstoppable
+        ),
+        Err(PnmError::UnexpectedEof)
+    ));
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_downscaled_matches_pnm() {
+    let rgba = noise_pattern(7, 5, 4);
+    let bmp = encode_bmp_rgba(&rgba, 7, 5, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let pam = encode_pam(&rgba, 7, 5, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let mut top_down = bmp.clone();
+    top_down[22..26].copy_from_slice(&(-5i32).to_le_bytes());
+    let flipped = decode_bmp(&top_down, Unstoppable).unwrap();
+    let flipped_pam = encode_pam(flipped.pixels(), 7, 5, PixelLayout::Rgba8, Unstoppable).unwrap();
+    for filter in [DownscaleFilter::Box, DownscaleFilter::Nearest] {
+        for factor in [1, 2, 3] {
+            let thumb = decode_bmp_downscaled(&bmp, factor, filter, Unstoppable).unwrap();
+            let expected = decode_downscaled(&pam, factor, filter, Unstoppable).unwrap();
+            assert_eq!(thumb.layout, PixelLayout::Rgba8);
+            assert_eq!(thumb.pixels(), expected.pixels(), "{filter:?} / {factor}");
+            let thumb = decode_bmp_downscaled(&top_down, factor, filter, Unstoppable).unwrap();
+            let expected = decode_downscaled(&flipped_pam, factor, filter, Unstoppable).unwrap();
+            assert_eq!(thumb.pixels(), expected.pixels(), "{filter:?} / {factor}");
+        }
+    }
+}
+
+// ── Orientation ──────────────────────────────────────────────────────
+
+const ORIENTATIONS: [Orientation; 8] = [
+    Orientation::Identity,
+    Orientation::FlipHorizontal,
+    Orientation::Rotate180,
+    Orientation::FlipVertical,
+    Orientation::Transpose,
+    Orientation::Rotate90,
+    Orientation::Transverse,
+    Orientation::Rotate270,
+];
+
+/// Reference orientation: move every source pixel to where it lands.
+fn orient(pixels: &[u8], w: usize, h: usize, bpp: usize, o: Orientation) -> Vec<u8> {
+    let (ow, oh) = if o.swaps_dimensions() { (h, w) } else { (w, h) };
+    let mut out = vec![0u8; ow * oh * bpp];
+    for y in 0..h {
+        for x in 0..w {
+            let (dx, dy) = match o {
+                Orientation::Identity => (x, y),
+                Orientation::FlipHorizontal => (w - 1 - x, y),
+                Orientation::Rotate180 => (w - 1 - x, h - 1 - y),
+                Orientation::FlipVertical => (x, h - 1 - y),
+                Orientation::Transpose => (y, x),
+                Orientation::Rotate90 => (h - 1 - y, x),
+                Orientation::Transverse => (h - 1 - y, w - 1 - x),
+                Orientation::Rotate270 => (y, w - 1 - x),
+                _ => unreachable!(),
+            };
+            let (src, dst) = ((y * w + x) * bpp, (dy * ow + dx) * bpp);
+            out[dst..dst + bpp].copy_from_slice(&pixels[src..src + bpp]);
+        }
+    }
+    out
+}
+
+#[test]
+fn orientation_exif_values() {
+    for (i, o) in ORIENTATIONS.into_iter().enumerate() {
+        assert_eq!(o.to_exif(), i as u16 + 1);
+        assert_eq!(Orientation::from_exif(i as u16 + 1), Some(o));
+        let (w, h) = o.output_size(3, 2);
+        assert_eq!((w, h) == (2, 3), o.swaps_dimensions());
+    }
+    assert_eq!(Orientation::from_exif(0), None);
+    assert_eq!(Orientation::from_exif(9), None);
+    // A 2x1 image [a, b] rotated clockwise puts a on top
+    let rotated = orient(&[1, 2], 2, 1, 1, Orientation::Rotate90);
+    assert_eq!(rotated, [1, 2]);
+    let rotated = orient(&[1, 2, 3, 4], 2, 2, 1, Orientation::Rotate90);
+    assert_eq!(rotated, [3, 1, 4, 2]);
+}
+
+#[test]
+fn decode_oriented_matches_decode_as_then_orient() {
+    let rgb = noise_pattern(5, 3, 3);
+    let floats: Vec<u8> = (0..15)
+        .flat_map(|i| (i as f32 / 14.0).to_ne_bytes())
+        .collect();
+    let mut maxval_100 = b"P6\n5 3\n100\n".to_vec();
+    maxval_100.extend(rgb.iter().map(|v| v % 101));
+    let files = [
+        encode_ppm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pgm(&rgb[..30], 5, 3, PixelLayout::Gray16, Unstoppable).unwrap(),
+        encode_pfm(&floats, 5, 3, PixelLayout::GrayF32, Unstoppable).unwrap(),
+        maxval_100,
+    ];
+    for file in &files {
+        for target in DECODE_AS_TARGETS {
+            let plain = decode_as(file, target, Unstoppable).unwrap();
+            let bpp = target.bytes_per_pixel();
+            for o in ORIENTATIONS {
+                let decoded = decode_oriented(file, target, o, Unstoppable).unwrap();
+                assert_eq!((decoded.width, decoded.height), o.output_size(5, 3));
+                assert_eq!(decoded.layout, target);
+                assert_eq!(
+                    decoded.pixels(),
+                    &orient(plain.pixels(), 5, 3, bpp, o)[..],
+                    "{target:?} {o:?}"
+                );
+            }
+        }
+    }
+
+    // Identity keeps the zero-copy path
+    let decoded = decode_oriented(
+        &files[0],
+        PixelLayout::Rgb8,
+        Orientation::Identity,
+        Unstoppable,
+    )
+    .unwrap();
+    assert!(decoded.is_borrowed());
+}
+
+#[test]
+fn decode_oriented_long_columns() {
+    // Columns longer than the gather buffer are read in several runs
+    let pixels: Vec<u8> = (0..3 * 300 * 4)
+        .flat_map(|i| (i as f32).to_ne_bytes())
+        .collect();
+    let pfm = encode_pfm(&pixels, 3, 300, PixelLayout::RgbaF32, Unstoppable).unwrap();
+    for o in ORIENTATIONS {
+        let decoded = decode_oriented(&pfm, PixelLayout::RgbaF32, o, Unstoppable).unwrap();
+        assert_eq!(
+            decoded.pixels(),
+            &orient(&pixels, 3, 300, 16, o)[..],
+            "{o:?}"
+        );
+    }
+}
+
+#[test]
+fn decode_oriented_limits_use_oriented_size() {
+    let pixels = checkerboard(4, 2, 3);
+    let encoded = encode_ppm(&pixels, 4, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let limits = Limits::default().with_max_memory_bytes(4 * 2 * 3);
+    let decoded = decode_oriented_with_limits(
+        &encoded,
+        PixelLayout::Rgb8,
+        Orientation::Rotate90,
+        &limits,
+        Unstoppable,
+    )
+    .unwrap();
+    assert_eq!((decoded.width, decoded.height), (2, 4));
+    assert!(matches!(
+        decode_oriented_with_limits(
+            &encoded,
+            PixelLayout::Rgba8,
+            Orientation::Rotate90,
+            &limits,
+            Unstoppable
+        ),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn encode_oriented_matches_orient_then_encode() {
+    let rgba = noise_pattern(5, 3, 4);
+    #[cfg_attr(not(feature = "basic-bmp"), allow(unused_mut))]
+    let mut formats = vec![
+        EncodeFormat::Pgm,
+        EncodeFormat::Ppm,
+        EncodeFormat::Pam,
+        EncodeFormat::Pfm,
+    ];
+    #[cfg(feature = "basic-bmp")]
+    formats.extend([EncodeFormat::Bmp, EncodeFormat::BmpRgba]);
+    for format in formats {
+        for layout in [PixelLayout::Rgba8, PixelLayout::Bgr8, PixelLayout::Gray16] {
+            let pixels = &rgba[..15 * layout.bytes_per_pixel()];
+            let bpp = layout.bytes_per_pixel();
+            for o in ORIENTATIONS {
+                let encoded =
+                    encode_oriented(pixels, 5, 3, layout, format, o, Unstoppable).unwrap();
+                let (w, h) = o.output_size(5, 3);
+                let oriented = orient(pixels, 5, 3, bpp, o);
+                let expected = encode_oriented(
+                    &oriented,
+                    w,
+                    h,
+                    layout,
+                    format,
+                    Orientation::Identity,
+                    Unstoppable,
+                )
+                .unwrap();
+                assert_eq!(encoded, expected, "{format:?} {layout:?} {o:?}");
+                assert_eq!(encoded.len(), encoded_len(w, h, layout, format).unwrap());
+            }
+        }
+    }
+
+    // Identity is byte-identical to the plain encoder
+    let ppm = encode_ppm(&rgba, 5, 3, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let oriented = encode_oriented(
+        &rgba,
+        5,
+        3,
+        PixelLayout::Rgba8,
+        EncodeFormat::Ppm,
+        Orientation::Identity,
+        Unstoppable,
+    )
+    .unwrap();
+    assert_eq!(oriented, ppm);
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_decode_oriented_matches_decode_as_then_orient() {
+    let rgb = noise_pattern(5, 3, 3);
+    let bottom_up = encode_bmp(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let mut top_down = bottom_up.clone();
+    top_down[22..26].copy_from_slice(&(-3i32).to_le_bytes());
+    for file in [&bottom_up, &top_down] {
+        for target in [PixelLayout::Rgb8, PixelLayout::Bgra8, PixelLayout::Gray8] {
+            let plain = decode_bmp_as(file, target, Unstoppable).unwrap();
+            for o in ORIENTATIONS {
+                let decoded = decode_bmp_oriented(file, target, o, Unstoppable).unwrap();
+                assert_eq!((decoded.width, decoded.height), o.output_size(5, 3));
+                let expected = orient(plain.pixels(), 5, 3, target.bytes_per_pixel(), o);
+                assert_eq!(decoded.pixels(), &expected[..], "{target:?} {o:?}");
+            }
+        }
+    }
+}
+
+// ── Row-order views ──────────────────────────────────────────────────
+
+/// Rows of `output` read through `DecodeOutput::row`, packed top-down.
+fn rows_top_down(output: &DecodeOutput<'_>) -> Vec<u8> {
+    (0..output.height as usize)
+        .flat_map(|y| output.row(y).unwrap())
+        .copied()
+        .collect()
+}
+
+#[test]
+fn decode_view_borrows_native_pfm_rows_bottom_up() {
+    let floats: Vec<u8> = (0..90).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let halves: Vec<u8> = (0..90)
+        .flat_map(|i| f32_to_f16(i as f32).to_ne_bytes())
+        .collect();
+    let files = [
+        encode_pfm(&floats, 6, 5, PixelLayout::RgbF32, Unstoppable).unwrap(),
+        encode_pfm(&halves, 6, 5, PixelLayout::RgbF16, Unstoppable).unwrap(),
+        encode_pfm(&floats[..120], 6, 5, PixelLayout::GrayF32, Unstoppable).unwrap(),
+    ];
+    for file in &files {
+        let full = decode(file, Unstoppable).unwrap();
+        let view = decode_view(file, Unstoppable).unwrap();
+        assert!(view.is_borrowed());
+        assert_eq!(view.row_order(), RowOrder::BottomUp);
+        assert_eq!((view.width, view.height, view.layout), (6, 5, full.layout));
+        assert_eq!(view.stride(), 6 * full.layout.bytes_per_pixel());
+        // The stored rows, bottom row first
+        let last_row = &full.pixels()[4 * view.stride()..];
+        assert_eq!(&view.pixels()[..view.stride()], last_row);
+        assert_eq!(view.row(4), Some(last_row));
+        assert_eq!(view.row(5), None);
+        assert_eq!(rows_top_down(&view), full.pixels());
+        assert_eq!(view.into_owned().pixels(), full.pixels());
+    }
+}
+
+#[test]
+fn decode_view_decodes_everything_else_top_down() {
+    // Positive scale = big-endian; the sign is flipped for big-endian hosts
+    let foreign_scale = if cfg!(target_endian = "little") {
+        1.0f32
+    } else {
+        -1.0
+    };
+    let mut foreign = format!("Pf\n2 1\n{foreign_scale:.1}\n").into_bytes();
+    for v in [0.5f32, -3.0] {
+        foreign.extend_from_slice(&v.to_bits().swap_bytes().to_ne_bytes());
+    }
+    let native_scale = -foreign_scale;
+    let mut scaled = format!("Pf\n2 1\n{:.1}\n", native_scale * 2.0).into_bytes();
+    for v in [0.5f32, -3.0] {
+        scaled.extend_from_slice(&v.to_ne_bytes());
+    }
+    let rgb = noise_pattern(6, 5, 3);
+    let files = [
+        foreign,
+        scaled,
+        encode_ppm(&rgb, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pgm(&rgb[..60], 6, 5, PixelLayout::Gray16, Unstoppable).unwrap(),
+    ];
+    for file in &files {
+        let full = decode(file, Unstoppable).unwrap();
+        let view = decode_view(file, Unstoppable).unwrap();
+        assert_eq!(view.row_order(), RowOrder::TopDown);
+        assert_eq!(view.is_borrowed(), full.is_borrowed());
+        assert_eq!(view.pixels(), full.pixels());
+        assert_eq!(rows_top_down(&view), full.pixels());
+    }
+}
+
+#[test]
+fn decode_view_truncated_and_limits() {
+    let floats: Vec<u8> = (0..30).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let file = encode_pfm(&floats, 6, 5, PixelLayout::GrayF32, Unstoppable).unwrap();
+    assert!(matches!(
+        decode_view(&file[..file.len() - 1], Unstoppable),
+        Err(PnmError::UnexpectedEof)
+    ));
+    let limits = Limits::default().with_max_width(4);
+    assert!(matches!(
+        decode_view_with_limits(&file, &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn bmp_native_view_borrows_stored_rows() {
+    let rgb = noise_pattern(5, 3, 3);
+    let rgba = noise_pattern(5, 3, 4);
+    let files = [
+        encode_bmp(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_bmp_rgba(&rgba, 5, 3, PixelLayout::Rgba8, Unstoppable).unwrap(),
+    ];
+    for file in &files {
+        let mut top_down = file.clone();
+        top_down[22..26].copy_from_slice(&(-3i32).to_le_bytes());
+        for (file, order) in [(file, RowOrder::BottomUp), (&top_down, RowOrder::TopDown)] {
+            let full = decode_bmp_native(file, Unstoppable).unwrap();
+            let view = decode_bmp_native_view(file, Unstoppable).unwrap();
+            assert!(view.is_borrowed());
+            assert_eq!(view.row_order(), order);
+            assert_eq!((view.width, view.height, view.layout), (5, 3, full.layout));
+            // 24-bit rows are padded from 15 to 16 bytes
+            let row_bytes = 5 * full.layout.bytes_per_pixel();
+            assert_eq!(view.stride(), row_bytes.next_multiple_of(4));
+            assert_eq!(rows_top_down(&view), full.pixels());
+            assert_eq!(view.into_owned().pixels(), full.pixels());
+        }
+    }
+
+    // The padding after the last stored row is never read
+    let len = files[0].len();
+    assert!(decode_bmp_native_view(&files[0][..len - 1], Unstoppable).is_ok());
+    assert!(matches!(
+        decode_bmp_native_view(&files[0][..len - 2], Unstoppable),
+        Err(PnmError::UnexpectedEof)
+    ));
+    let limits = Limits::default().with_max_height(2);
+    assert!(matches!(
+        decode_bmp_native_view_with_limits(&files[0], &limits, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+// ── Source info ──────────────────────────────────────────────────────
+
+#[test]
+fn source_info_reports_the_file_header() {
+    let rgb = noise_pattern(2, 2, 3);
+    let rgba = noise_pattern(2, 2, 4);
+    let floats: Vec<u8> = (0..16).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let mut maxval_100 = b"P5\n2 2\n100\n".to_vec();
+    maxval_100.extend([0, 50, 99, 100]);
+    let cases = [
+        (
+            encode_ppm(&rgb, 2, 2, PixelLayout::Rgb8, Unstoppable).unwrap(),
+            SourceFormat::Ppm,
+            Some(255),
+            8,
+            None,
+        ),
+        (maxval_100, SourceFormat::Pgm, Some(100), 7, None),
+        (
+            encode_pgm(&rgb[..8], 2, 2, PixelLayout::Gray16, Unstoppable).unwrap(),
+            SourceFormat::Pgm,
+            Some(65535),
+            16,
+            None,
+        ),
+        (
+            encode_pam(&rgba, 2, 2, PixelLayout::Rgba8, Unstoppable).unwrap(),
+            SourceFormat::Pam,
+            Some(255),
+            8,
+            Some("RGB_ALPHA"),
+        ),
+        (
+            encode_pam(&floats, 2, 2, PixelLayout::RgbaF32, Unstoppable).unwrap(),
+            SourceFormat::Pam,
+            None,
+            32,
+            Some("FLOAT_RGBA"),
+        ),
+        (
+            encode_pfm(&floats[..16], 2, 2, PixelLayout::GrayF32, Unstoppable).unwrap(),
+            SourceFormat::Pfm,
+            None,
+            32,
+            None,
+        ),
+        (
+            encode_pfm(&floats[..8], 2, 2, PixelLayout::GrayF16, Unstoppable).unwrap(),
+            SourceFormat::Pfm,
+            None,
+            16,
+            None,
+        ),
+    ];
+    for (file, format, maxval, bit_depth, tuple_type) in &cases {
+        let decoded = decode(file, Unstoppable).unwrap();
+        let source = decoded.source();
+        assert_eq!(source.format, *format);
+        assert_eq!(source.maxval, *maxval, "{format:?}");
+        assert_eq!(source.bit_depth, *bit_depth, "{format:?}");
+        assert_eq!(source.tuple_type.as_deref(), *tuple_type);
+
+        // The source survives conversion and copying
+        let converted = decode_as(file, PixelLayout::RgbaF32, Unstoppable).unwrap();
+        assert_eq!(converted.source(), source);
+        assert_eq!(decoded.clone().into_owned().source(), source);
+        let thumbnail = decode_downscaled(file, 2, DownscaleFilter::Box, Unstoppable).unwrap();
+        assert_eq!(thumbnail.source(), source);
+    }
+}
+
+#[cfg(feature = "basic-bmp")]
+#[test]
+fn source_info_for_bmp() {
+    let rgb = noise_pattern(2, 2, 3);
+    let encoded = encode_bmp(&rgb, 2, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
+    for decoded in [
+        decode_bmp(&encoded, Unstoppable).unwrap(),
+        decode_bmp_native_view(&encoded, Unstoppable).unwrap(),
+        decode_bmp_region(&encoded, 0, 0, 1, 1, Unstoppable).unwrap(),
+    ] {
+        let source = decoded.source();
+        assert_eq!(source.format, SourceFormat::Bmp);
+        assert_eq!((source.maxval, source.bit_depth), (Some(255), 8));
+        assert_eq!(source.tuple_type, None);
+    }
+}
+
+#[test]
+fn rows_iterate_top_down_whatever_the_storage() {
+    let rgb = noise_pattern(6, 5, 3);
+    let floats: Vec<u8> = (0..30).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let ppm = encode_ppm(&rgb, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let pfm = encode_pfm(&floats, 6, 5, PixelLayout::GrayF32, Unstoppable).unwrap();
+    for output in [
+        decode(&ppm, Unstoppable).unwrap(),
+        decode_region(&ppm, 1, 1, 4, 3, Unstoppable).unwrap(),
+        decode_view(&pfm, Unstoppable).unwrap(),
+    ] {
+        let row_bytes = output.width as usize * output.layout.bytes_per_pixel();
+        let packed = output.clone().into_owned();
+        assert_eq!(
+            output.clone().try_into_owned().unwrap().pixels(),
+            packed.pixels()
+        );
+        let rows: Vec<&[u8]> = output.rows().collect();
+        assert_eq!(rows.len(), output.height as usize);
+        assert_eq!(output.rows().len(), output.height as usize);
+        for (y, row) in rows.iter().enumerate() {
+            assert_eq!(*row, &packed.pixels()[y * row_bytes..][..row_bytes]);
+        }
+        let last = output.rows().next_back().unwrap();
+        assert_eq!(Some(last), output.row(output.height as usize - 1));
+    }
+}
+
+#[test]
+fn into_vec_keeps_owned_packed_pixels() {
+    let gray16 = noise_pattern(6, 5, 2);
+    let pgm = encode_pgm(&gray16, 6, 5, PixelLayout::Gray16, Unstoppable).unwrap();
+    let decoded = decode(&pgm, Unstoppable).unwrap();
+    assert!(!decoded.is_borrowed());
+    let expected = decoded.pixels().to_vec();
+    let ptr = decoded.pixels().as_ptr();
+    let pixels = decoded.into_vec().unwrap();
+    assert_eq!(pixels.as_ptr(), ptr);
+    assert_eq!(pixels, expected);
+
+    // Borrowed and strided rows are packed into a new vector
+    let rgb = noise_pattern(6, 5, 3);
+    let ppm = encode_ppm(&rgb, 6, 5, PixelLayout::Rgb8, Unstoppable).unwrap();
+    assert_eq!(decode(&ppm, Unstoppable).unwrap().into_vec().unwrap(), rgb);
+    let region = decode_region(&ppm, 1, 1, 2, 2, Unstoppable).unwrap();
+    assert_eq!(region.into_vec().unwrap(), crop(&rgb, 6, 3, (1, 1, 2, 2)));
+}
+
+// ── Header comments ──────────────────────────────────────────────────
+
+const COMMENTS: [&str; 3] = ["exposure 12 ms", "", "  indented # with a hash"];
+
+#[test]
+fn comments_roundtrip_through_every_pnm_format() {
+    let rgb = noise_pattern(3, 2, 3);
+    let floats: Vec<u8> = (0..18).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    let header = HeaderOptions {
+        comments: &COMMENTS,
+        ..Default::default()
+    };
+    for (format, pixels, layout, magic) in [
+        (EncodeFormat::Pgm, &rgb, PixelLayout::Rgb8, "P5"),
+        (EncodeFormat::Ppm, &rgb, PixelLayout::Rgb8, "P6"),
+        (EncodeFormat::Pam, &rgb, PixelLayout::Rgb8, "P7"),
+        (EncodeFormat::Pfm, &floats, PixelLayout::RgbF32, "PF"),
+    ] {
+        let plain = encode_with_header(
+            pixels,
+            3,
+            2,
+            layout,
+            format,
+            &HeaderOptions::default(),
+            Unstoppable,
+        )
+        .unwrap();
+        let encoded =
+            encode_with_header(pixels, 3, 2, layout, format, &header, Unstoppable).unwrap();
+        let expected_start = format!("{magic}\n# exposure 12 ms\n#\n#   indented # with a hash\n");
+        assert!(encoded.starts_with(expected_start.as_bytes()), "{format:?}");
+
+        let decoded = decode(&encoded, Unstoppable).unwrap();
+        let comments: Vec<_> = decoded.source().comments().collect();
+        assert_eq!(comments, COMMENTS, "{format:?}");
+        assert_eq!(
+            decoded.pixels(),
+            decode(&plain, Unstoppable).unwrap().pixels()
+        );
+        assert_eq!(
+            decode(&plain, Unstoppable)
+                .unwrap()
+                .source()
+                .comments()
+                .count(),
+            0
+        );
+        // Comments survive copying out of the input
+        let owned = decoded.into_owned();
+        assert_eq!(owned.source().comments().collect::<Vec<_>>(), COMMENTS);
+    }
+}
+
+#[test]
+fn comments_read_from_handwritten_headers() {
+    // Bytes that are not UTF-8 are replaced
+    let mut p5 = b"P5 # after magic\n#second\r\n2 # width\n1\n# caf\xe9\n255\n".to_vec();
+    p5.extend([1, 2]);
+    let mut pam = b"P7\n# one\nWIDTH 1\n  #  two\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\n".to_vec();
+    pam.extend(b"TUPLTYPE A#B\n# last\nENDHDR\n\x07");
+    let mut pfm = b"Pf\n# scale follows\n1 1\n-1.0\n".to_vec();
+    pfm.extend(1.5f32.to_le_bytes());
+    let cases: [(&[u8], &[&str]); 3] = [
+        (&p5, &["after magic", "second", "width", "caf\u{fffd}"]),
+        (&pam, &["one", " two", "last"]),
+        (&pfm, &["scale follows"]),
+    ];
+    for (file, expected) in cases {
+        let decoded = decode(file, Unstoppable).unwrap();
+        let comments: Vec<_> = decoded.source().comments().collect();
+        assert_eq!(comments, expected);
+        let header = probe(file).unwrap();
+        assert_eq!(header.source.comments().collect::<Vec<_>>(), expected);
+        assert_eq!(&header.source, decoded.source());
+    }
+    // A maxval separator that happens to be `#` is not a comment
+    let mut odd = b"P5\n1 1\n255#".to_vec();
+    odd.push(9);
+    let decoded = decode(&odd, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), [9]);
+    assert_eq!(decoded.source().comments().count(), 0);
+}
+
+#[test]
+fn comment_limit() {
+    let mut file = b"P5\n# 0123456789\n# abc\n1 1\n255\n".to_vec();
+    file.push(0);
+    let limits = |bytes| Limits::default().with_max_comment_bytes(bytes);
+    assert!(decode_with_limits(&file, &limits(13), Unstoppable).is_ok());
+    assert!(probe_with_limits(&file, &limits(13)).is_ok());
+    assert!(matches!(
+        decode_with_limits(&file, &limits(12), Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+    assert!(matches!(
+        probe_with_limits(&file, &limits(12)),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn header_comments_reject_line_breaks() {
+    let pixels = [0u8; 3];
+    for comment in ["two\nlines", "carriage\rreturn"] {
+        let header = HeaderOptions {
+            comments: &[comment],
+            ..Default::default()
+        };
+        assert!(matches!(
+            encode_with_header(
+                &pixels,
+                1,
+                1,
+                PixelLayout::Rgb8,
+                EncodeFormat::Ppm,
+                &header,
+                Unstoppable
+            ),
+            Err(PnmError::InvalidData(_))
+        ));
+    }
+    #[cfg(feature = "basic-bmp")]
+    {
+        let header = HeaderOptions {
+            comments: &["no room"],
+            ..Default::default()
+        };
+        assert!(matches!(
+            encode_with_header(
+                &pixels,
+                1,
+                1,
+                PixelLayout::Rgb8,
+                EncodeFormat::Bmp,
+                &header,
+                Unstoppable
+            ),
+            Err(PnmError::UnsupportedVariant(_))
+        ));
+        let plain = encode_with_header(
+            &pixels,
+            1,
+            1,
+            PixelLayout::Rgb8,
+            EncodeFormat::Bmp,
+            &HeaderOptions::default(),
+            Unstoppable,
+        )
+        .unwrap();
+        assert_eq!(
+            plain,
+            encode_bmp(&pixels, 1, 1, PixelLayout::Rgb8, Unstoppable).unwrap()
+        );
+    }
+}
+
+#[test]
+fn probe_reads_only_the_header() {
+    let rgba = noise_pattern(5, 4, 4);
+    let encoded = encode_pam(&rgba, 5, 4, PixelLayout::Rgba8, Unstoppable).unwrap();
+    let header_len = encoded.len() - rgba.len();
+    let header = probe(&encoded[..header_len]).unwrap();
+    assert_eq!(
+        (header.width, header.height, header.layout),
+        (5, 4, PixelLayout::Rgba8)
+    );
+    assert_eq!(header.source.format, SourceFormat::Pam);
+    assert_eq!(header.source.tuple_type.as_deref(), Some("RGB_ALPHA"));
+    assert!(matches!(probe(b"P9\n"), Err(PnmError::UnrecognizedFormat)));
+}
+
+// ── PAM header fields ────────────────────────────────────────────────
+
+const PAM_FIELDS: [(&str, &str); 3] = [
+    ("X-EXPOSURE", "12 ms"),
+    ("X-FLAG", ""),
+    ("X-NOTE", "spaced   words"),
+];
+
+#[test]
+fn pam_fields_roundtrip() {
+    let pixels = noise_pattern(3, 2, 3);
+    let header = HeaderOptions {
+        pam_fields: &PAM_FIELDS,
+        ..Default::default()
+    };
+    let encoded = encode_with_header(
+        &pixels,
+        3,
+        2,
+        PixelLayout::Rgb8,
+        EncodeFormat::Pam,
+        &header,
+        Unstoppable,
+    )
+    .unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &pixels[..]);
+    let source = decoded.source();
+    assert_eq!(source.tuple_type.as_deref(), Some("RGB"));
+    assert_eq!(source.pam_fields().collect::<Vec<_>>(), PAM_FIELDS);
+}
+
+#[test]
+fn pam_keeps_unknown_lines_and_joins_tuple_types() {
+    let data = b"P7\nWIDTH 1\nHEIGHT 1\nTUPLTYPE GRAYSCALE\nDEPTH 1\nX-ORIGIN scanner 4\nMAXVAL 255\nTUPLTYPE _ALPHA\nENDHDR\n\x2a";
+    let decoded = decode(data, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[0x2a]);
+    let source = decoded.source();
+    assert!(matches!(
+        &source.tuple_type,
+        Some(std::borrow::Cow::Owned(t)) if t == "GRAYSCALE _ALPHA"
+    ));
+    assert_eq!(
+        source.pam_fields().collect::<Vec<_>>(),
+        [("X-ORIGIN", "scanner 4")]
+    );
+    let info = probe(data).unwrap();
+    assert_eq!(info.source.tuple_type, source.tuple_type);
+    assert!(info.source.pam_fields().eq(source.pam_fields()));
+
+    // Float tuple types are one word
+    let data = b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 3\nTUPLTYPE FLOAT_RGB\nTUPLTYPE _ALPHA\nENDHDR\n";
+    let err = probe(data).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        PnmError::UnsupportedVariant("PAM TUPLTYPE FLOAT_RGB _ALPHA not supported".into())
+            .to_string()
+    );
+}
+
+#[test]
+fn pam_fields_are_validated() {
+    let pixels = [0u8; 3];
+    let encode = |format, fields: &[(&str, &str)]| {
+        let header = HeaderOptions {
+            pam_fields: fields,
+            ..Default::default()
+        };
+        encode_with_header(
+            &pixels,
+            1,
+            1,
+            PixelLayout::Rgb8,
+            format,
+            &header,
+            Unstoppable,
+        )
+    };
+    for fields in [
+        [("WIDTH", "2")],
+        [("ENDHDR", "")],
+        [("", "empty")],
+        [("#NOTE", "comment")],
+        [("TWO WORDS", "x")],
+        [("X-NOTE", "two\nlines")],
+    ] {
+        assert!(
+            matches!(
+                encode(EncodeFormat::Pam, &fields),
+                Err(PnmError::InvalidData(_))
+            ),
+            "{fields:?}"
+        );
+    }
+    assert!(matches!(
+        encode(EncodeFormat::Ppm, &PAM_FIELDS),
+        Err(PnmError::UnsupportedVariant(_))
+    ));
+    let long = "x".repeat(MAX_HEADER_BYTES);
+    assert!(matches!(
+        encode(EncodeFormat::Pam, &[("X-LONG", &long)]),
+        Err(PnmError::LimitExceeded(_))
+    ));
+    let fits = "x".repeat(MAX_HEADER_BYTES / 2);
+    assert!(encode(EncodeFormat::Pam, &[("X-LONG", &fits)]).is_ok());
+}
+
+// ── Conformance ──────────────────────────────────────────────────────
+
+#[test]
+fn validate_accepts_encoder_output() {
+    let rgb = noise_pattern(5, 3, 3);
+    let rgb16: Vec<u8> = rgb
+        .iter()
+        .flat_map(|&b| u16::from(b).to_ne_bytes())
+        .collect();
+    let floats: Vec<u8> = (0..45).flat_map(|i| (i as f32).to_ne_bytes()).collect();
+    for encoded in [
+        encode_ppm(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pgm(&rgb16, 5, 3, PixelLayout::Rgb16, Unstoppable).unwrap(),
+        encode_pam(&rgb, 5, 3, PixelLayout::Rgb8, Unstoppable).unwrap(),
+        encode_pfm(&floats, 5, 3, PixelLayout::RgbF32, Unstoppable).unwrap(),
+    ] {
+        assert_eq!(validate(&encoded).unwrap(), []);
+    }
+}
+
+#[test]
+fn validate_reports_deviations_in_file_order() {
+    // Header ending in a non-whitespace byte, then one byte too many
+    let data = b"P5 2 1 255X\x01\x02\x03";
+    assert_eq!(
+        validate(data).unwrap(),
+        [
+            Deviation::UnusualWhitespace { offset: 10 },
+            Deviation::TrailingData { bytes: 1 },
+        ]
+    );
+
+    let pam = b"P7\r\nWIDTH 1\nHEIGHT 2\nDEPTH 1\nMAXVAL 255\nX-NOTE hi\n# fine\nENDHDR\n\x07";
+    assert_eq!(
+        validate(pam).unwrap(),
+        [
+            Deviation::UnusualWhitespace { offset: 0 },
+            Deviation::UnknownPamField { offset: 40 },
+            Deviation::TruncatedRows {
+                rows: 1,
+                expected: 2
+            },
+        ]
+    );
+
+    // Second pixel's green sample is over maxval 100
+    let data = b"P6 2 1 100\n\x0a\x14\x1e\x28\xc8\x32";
+    assert_eq!(
+        validate(data).unwrap(),
+        [Deviation::SampleExceedsMaxval {
+            x: 1,
+            y: 0,
+            channel: 1,
+            value: 200
+        }]
+    );
+    let data = b"P5 1 2 1000\n\x00\x01\x03\xe9";
+    assert_eq!(
+        validate(data).unwrap(),
+        [Deviation::SampleExceedsMaxval {
+            x: 0,
+            y: 1,
+            channel: 0,
+            value: 1001
+        }]
+    );
+
+    assert!(matches!(
+        validate(b"P5 0 1 255\n"),
+        Err(PnmError::InvalidHeader(_))
+    ));
+}
+
+#[test]
+fn strict_decode_rejects_what_lenient_accepts() {
+    let data = b"P5 2 1 255\n\x01\x02trailing";
+    let lenient = decode_with_options(data, &DecodeOptions::default(), Unstoppable).unwrap();
+    assert_eq!(lenient.pixels(), &[1, 2]);
+    assert!(lenient.is_borrowed());
+
+    let strict = DecodeOptions::default().with_strictness(Strictness::Strict);
+    let err = decode_with_options(data, &strict, Unstoppable).unwrap_err();
+    assert!(matches!(
+        err,
+        PnmError::Nonconforming(Deviation::TrailingData { bytes: 8 })
+    ));
+    assert_eq!(
+        err.to_string(),
+        "nonconforming file: 8 bytes of data after the last row"
+    );
+    let clean = &data[..13];
+    assert_eq!(
+        decode_with_options(clean, &strict, Unstoppable)
+            .unwrap()
+            .pixels(),
+        &[1, 2]
+    );
+
+    let limited = strict.with_limits(Limits::default().with_max_width(1));
+    assert!(matches!(
+        decode_with_options(clean, &limited, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn maxval_out_of_range_is_a_deviation() {
+    let mut pam =
+        b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 300000\nTUPLTYPE GRAYSCALE\nENDHDR\n".to_vec();
+    pam.extend_from_slice(&[0xff, 0xff]);
+    for (data, maxval) in [(&pam[..], 300000), (b"P5 1 1 0\n\x07", 0)] {
+        assert_eq!(
+            validate(data).unwrap(),
+            [Deviation::MaxvalOutOfRange { maxval }]
+        );
+        let strict = DecodeOptions::default().with_strictness(Strictness::Strict);
+        assert!(matches!(
+            decode_with_options(data, &strict, Unstoppable),
+            Err(PnmError::Nonconforming(Deviation::MaxvalOutOfRange { .. }))
+        ));
+        assert!(matches!(
+            decode_with_options(data, &DecodeOptions::default(), Unstoppable),
+            Err(PnmError::InvalidHeader(_))
+        ));
+    }
+}
+
+#[test]
+fn validate_rejects_unaddressable_images() {
+    let huge = b"P7\nWIDTH 4294967295\nHEIGHT 4294967295\nDEPTH 4\nMAXVAL 65535\nENDHDR\n";
+    assert!(matches!(
+        validate(huge),
+        Err(PnmError::DimensionsTooLarge { .. })
+    ));
+}
+
+// ── Out-of-range samples ─────────────────────────────────────────────
+
+fn sample_policy(samples: SamplePolicy) -> DecodeOptions {
+    DecodeOptions::default().with_samples(samples)
+}
+
+#[test]
+fn out_of_range_samples_clamp_by_default() {
+    // maxval 100: 50 is in range, 200 is not
+    let data = b"P5 2 1 100\n\x32\xc8";
+    let clamped = decode(data, Unstoppable).unwrap();
+    assert_eq!(clamped.pixels(), &[128, 255]);
+    let options = sample_policy(SamplePolicy::Clamp);
+    let explicit = decode_with_options(data, &options, Unstoppable).unwrap();
+    assert_eq!(explicit.pixels(), clamped.pixels());
+
+    // 16-bit grayscale decodes as stored, so clamps to maxval itself
+    let data = b"P5 1 2 1000\n\x01\xf4\x03\xe9";
+    let clamped = decode(data, Unstoppable).unwrap();
+    assert_eq!(u16s(clamped.pixels()), [500, 1000]);
+    let same = decode_as(data, clamped.layout, Unstoppable).unwrap();
+    assert_eq!(same.pixels(), clamped.pixels());
+}
+
+#[test]
+fn out_of_range_samples_error_with_position() {
+    let options = sample_policy(SamplePolicy::Error);
+    let data = b"P6 2 2 100\n\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x65\x00";
+    let err = decode_with_options(data, &options, Unstoppable).unwrap_err();
+    assert!(matches!(
+        err,
+        PnmError::SampleOutOfRange {
+            x: 1,
+            y: 1,
+            channel: 1,
+            value: 101,
+            maxval: 100
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "sample 101 at (1, 1) channel 1 exceeds maxval 100"
+    );
+
+    let wide = b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 4\nMAXVAL 4095\nTUPLTYPE RGB_ALPHA\nENDHDR\n\x0f\xff\x00\x00\x10\x00\x00\x00";
+    assert!(matches!(
+        decode_with_options(wide, &options, Unstoppable),
+        Err(PnmError::SampleOutOfRange {
+            x: 0,
+            y: 0,
+            channel: 2,
+            value: 4096,
+            maxval: 4095
+        })
+    ));
+
+    // In-range files and full-range maxvals decode as usual
+    let data = b"P5 2 1 100\n\x32\x64";
+    let decoded = decode_with_options(data, &options, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[128, 255]);
+    let rgb = noise_pattern(4, 4, 3);
+    let encoded = encode_ppm(&rgb, 4, 4, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let decoded = decode_with_options(&encoded, &options, Unstoppable).unwrap();
+    assert!(decoded.is_borrowed());
+}
+
+#[test]
+fn out_of_range_samples_pass_through_unscaled() {
+    let options = sample_policy(SamplePolicy::PassThrough);
+    let data = b"P5 2 1 100\n\x32\xc8";
+    let decoded = decode_with_options(data, &options, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[50, 200]);
+    assert!(decoded.is_borrowed());
+    assert_eq!(decoded.source().maxval, Some(100));
+
+    let data = b"P5 1 2 1000\n\x01\xf4\x03\xe9";
+    let decoded = decode_with_options(data, &options, Unstoppable).unwrap();
+    assert_eq!(decoded.layout, PixelLayout::Gray16);
+    let samples: Vec<u16> = decoded
+        .pixels()
+        .chunks_exact(2)
+        .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+        .collect();
+    assert_eq!(samples, [500, 1001]);
+    assert_eq!(decoded.source().maxval, Some(1000));
+}
+
+#[test]
+fn sample_policy_applies_to_every_decode_path() {
+    let error = sample_policy(SamplePolicy::Error);
+    // maxval 100, 3x2 gray: 200 at (2, 1)
+    let data = b"P5 3 2 100\n\x00\x32\x64\x00\x32\xc8";
+    fn out_of_range<T>(result: Result<T, PnmError>) -> bool {
+        matches!(
+            result,
+            Err(PnmError::SampleOutOfRange {
+                x: 2,
+                y: 1,
+                channel: 0,
+                value: 200,
+                maxval: 100
+            })
+        )
+    }
+    let rgb = PixelLayout::Rgb8;
+    let flip = Orientation::FlipHorizontal;
+    let result = decode_as_with_options(data, rgb, &error, Unstoppable);
+    assert!(out_of_range(result));
+    let result = decode_oriented_with_options(data, rgb, flip, &error, Unstoppable);
+    assert!(out_of_range(result));
+    let result = decode_view_with_options(data, &error, Unstoppable);
+    assert!(out_of_range(result));
+    let mut buf = Vec::new();
+    let result = decode_reusing_with_options(data, &mut buf, &error, Unstoppable);
+    assert!(out_of_range(result));
+    let result = decode_downscaled_with_options(data, 2, DownscaleFilter::Box, &error, Unstoppable);
+    assert!(out_of_range(result));
+
+    // Nothing is written into a caller buffer for a rejected file
+    let mut buf = [0xEE; 18];
+    let into = decode_into_buffer_with_options(data, &mut buf, 9, rgb, &error, Unstoppable);
+    assert!(out_of_range(into));
+    assert!(buf.iter().all(|&b| b == 0xEE));
+
+    // Regions report the position in the image, and only check their own
+    // samples
+    let result = decode_region_with_options(data, 1, 1, 2, 1, &error, Unstoppable);
+    assert!(out_of_range(result));
+    let left = decode_region_with_options(data, 0, 0, 2, 2, &error, Unstoppable).unwrap();
+    assert_eq!(left.pixels(), &[0, 128, 0, 128]);
+
+    // PassThrough and limits apply the same way
+    let pass = sample_policy(SamplePolicy::PassThrough);
+    let decoded = decode_as_with_options(data, PixelLayout::Gray8, &pass, Unstoppable).unwrap();
+    assert_eq!(decoded.pixels(), &[0, 50, 100, 0, 50, 200]);
+    let region = decode_region_with_options(data, 2, 0, 1, 2, &pass, Unstoppable).unwrap();
+    assert_eq!(region.into_owned().pixels(), &[100, 200]);
+    let limited = DecodeOptions::default().with_limits(Limits::default().with_max_pixels(5));
+    assert!(matches!(
+        decode_as_with_options(data, rgb, &limited, Unstoppable),
+        Err(PnmError::LimitExceeded(_))
+    ));
+
+    // So does strictness, even though a region reads only part of the file
+    let strict = DecodeOptions::default().with_strictness(Strictness::Strict);
+    let trailing = b"P5 1 1 255\n\x00\x00";
+    assert!(matches!(
+        decode_region_with_options(trailing, 0, 0, 1, 1, &strict, Unstoppable),
+        Err(PnmError::Nonconforming(Deviation::TrailingData { .. }))
+    ));
+}
+
+// ── Strided encode ───────────────────────────────────────────────────
+
+#[test]
+fn strided_encode_matches_contiguous() {
+    // 3x2 RGB rows padded to 12 bytes, last row without padding
+    let pixels = noise_pattern(3, 2, 3);
+    let mut padded = pixels[..9].to_vec();
+    padded.extend_from_slice(&[0xAA; 3]);
+    padded.extend_from_slice(&pixels[9..]);
+
+    type StridedEncoder =
+        fn(&[u8], u32, u32, usize, PixelLayout, Unstoppable) -> Result<Vec<u8>, PnmError>;
+    #[cfg_attr(not(feature = "basic-bmp"), allow(unused_mut))]
+    let mut encoders: Vec<(Encoder, StridedEncoder)> = vec![
+        (encode_ppm, encode_ppm_strided),
+        (encode_pgm, encode_pgm_strided),
+        (encode_pam, encode_pam_strided),
+        (encode_pfm, encode_pfm_strided),
+    ];
+    #[cfg(feature = "basic-bmp")]
+    encoders.extend([
+        (encode_bmp as Encoder, encode_bmp_strided as StridedEncoder),
+        (encode_bmp_rgba, encode_bmp_rgba_strided),
+    ]);
+    for (contiguous, strided) in encoders {
+        let expected = contiguous(&pixels, 3, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
+        let actual = strided(&padded, 3, 2, 12, PixelLayout::Rgb8, Unstoppable).unwrap();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn strided_encode_validates_stride_and_length() {
+    let pixels = vec![0u8; 21];
+    assert!(matches!(
+        encode_ppm_strided(&pixels, 3, 2, 8, PixelLayout::Rgb8, Unstoppable),
+        Err(PnmError::InvalidData(_))
+    ));
+    // Two rows at stride 12 need 12 + 9 bytes
+    assert!(encode_ppm_strided(&pixels, 3, 2, 12, PixelLayout::Rgb8, Unstoppable).is_ok());
+    assert!(matches!(
+        encode_ppm_strided(&pixels[..20], 3, 2, 12, PixelLayout::Rgb8, Unstoppable),
+        Err(PnmError::BufferTooSmall {
+            needed: 21,
+            actual: 20
+        })
+    ));
+}
+
+// ── Encode into a caller buffer ─────────────────────────────────────
+
+type Encoder = fn(&[u8], u32, u32, PixelLayout, Unstoppable) -> Result<Vec<u8>, PnmError>;
+type IntoEncoder =
+    fn(&[u8], u32, u32, PixelLayout, &mut [u8], Unstoppable) -> Result<usize, PnmError>;
+
+fn into_encoders() -> Vec<(EncodeFormat, Encoder, IntoEncoder)> {
+    #[cfg_attr(not(feature = "basic-bmp"), allow(unused_mut))]
+    let mut encoders: Vec<(EncodeFormat, Encoder, IntoEncoder)> = vec![
+        (EncodeFormat::Ppm, encode_ppm, encode_ppm_into),
+        (EncodeFormat::Pgm, encode_pgm, encode_pgm_into),
+        (EncodeFormat::Pam, encode_pam, encode_pam_into),
+        (EncodeFormat::Pfm, encode_pfm, encode_pfm_into),
+    ];
+    #[cfg(feature = "basic-bmp")]
+    encoders.extend([
+        (
+            EncodeFormat::Bmp,
+            encode_bmp as Encoder,
+            encode_bmp_into as IntoEncoder,
+        ),
+        (EncodeFormat::BmpRgba, encode_bmp_rgba, encode_bmp_rgba_into),
+    ]);
+    encoders
+}
+
+#[test]
+fn encoded_len_and_encode_into_match_encode() {
+    for (w, h) in [(1, 1), (3, 2), (5, 7), (10, 1)] {
+        for layout in [
+            PixelLayout::Gray8,
+            PixelLayout::Rgb8,
+            PixelLayout::Rgba16,
+            PixelLayout::Bgrx8,
+            PixelLayout::RgbaF32,
+            PixelLayout::RgbF16,
+        ] {
+            let pixels = noise_pattern(w, h, layout.bytes_per_pixel());
+            let (w, h) = (w as u32, h as u32);
+            for (format, encode, encode_into) in into_encoders() {
+                let expected = encode(&pixels, w, h, layout, Unstoppable).unwrap();
+                let len = encoded_len(w, h, layout, format).unwrap();
+                assert_eq!(len, expected.len(), "{format:?} {layout:?} {w}x{h}");
+
+                // Bytes past the returned length are left alone
+                let mut out = vec![0xEE; len + 4];
+                let written = encode_into(&pixels, w, h, layout, &mut out, Unstoppable).unwrap();
+                assert_eq!(written, len);
+                assert_eq!(&out[..len], &expected[..], "{format:?} {layout:?} {w}x{h}");
+                assert_eq!(&out[len..], &[0xEE; 4]);
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_into_rejects_short_buffer() {
+    let pixels = noise_pattern(3, 2, 3);
+    for (format, _, encode_into) in into_encoders() {
+        let len = encoded_len(3, 2, PixelLayout::Rgb8, format).unwrap();
+        let mut out = vec![0u8; len - 1];
+        let err = encode_into(&pixels, 3, 2, PixelLayout::Rgb8, &mut out, Unstoppable).unwrap_err();
+        assert!(
+            matches!(err, PnmError::BufferTooSmall { needed, actual } if needed == len && actual == len - 1),
+            "{format:?}: {err:?}"
+        );
+        assert!(
+            out.iter().all(|&b| b == 0),
+            "{format:?} wrote into a short buffer"
+        );
+    }
+}
+
+#[test]
+fn encoded_len_overflow() {
+    assert!(matches!(
+        encoded_len(u32::MAX, u32::MAX, PixelLayout::RgbaF32, EncodeFormat::Pam),
+        Err(PnmError::DimensionsTooLarge { .. })
+    ));
+}
+
 // ── BMP roundtrips ───────────────────────────────────────────────────
 
 #[cfg(feature = "basic-bmp")]
@@ -370,25 +2887,38 @@ fn bmp_width_1_padding() {
     assert_eq!(decoded.pixels(), &pixels[..]);
 }
 
+#[test]
+fn pam_maxval_out_of_range_is_rejected() {
+    // MAXVAL 300000 used to overflow the 16-bit scaling; MAXVAL 0 divided by zero
+    for maxval in [0, 65536, 300000] {
+        let mut data = format!(
+            "P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL {maxval}\nTUPLTYPE GRAYSCALE\nENDHDR\n"
+        )
+        .into_bytes();
+        data.extend_from_slice(&[0xff, 0xff]);
+        assert!(
+            matches!(decode(&data, Unstoppable), Err(PnmError::InvalidHeader(_))),
+            "MAXVAL {maxval}"
+        );
+    }
+    let data =
+        b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 65535\nTUPLTYPE GRAYSCALE\nENDHDR\n\xff\xff";
+    assert_eq!(decode(data, Unstoppable).unwrap().pixels(), &[0xff, 0xff]);
+}
+
 // ── Limits ───────────────────────────────────────────────────────────
 
 #[test]
 fn limits_max_width() {
     let encoded = encode_ppm(&[0u8; 12], 2, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
-    let limits = Limits {
-        max_width: Some(1),
-        ..Default::default()
-    };
+    let limits = Limits::default().with_max_width(1);
     assert!(decode_with_limits(&encoded, &limits, Unstoppable).is_err());
 }
 
 #[test]
 fn limits_max_height() {
     let encoded = encode_ppm(&[0u8; 12], 2, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
-    let limits = Limits {
-        max_height: Some(1),
-        ..Default::default()
-    };
+    let limits = Limits::default().with_max_height(1);
     assert!(decode_with_limits(&encoded, &limits, Unstoppable).is_err());
 }
 
@@ -396,13 +2926,176 @@ fn limits_max_height() {
 #[test]
 fn limits_max_memory_bmp() {
     let encoded = encode_bmp(&[0u8; 12], 2, 2, PixelLayout::Rgb8, Unstoppable).unwrap();
-    let limits = Limits {
-        max_memory_bytes: Some(1),
-        ..Default::default()
-    };
+    let limits = Limits::default().with_max_memory_bytes(1);
     assert!(decode_bmp_with_limits(&encoded, &limits, Unstoppable).is_err());
 }
 
+// ── Large images (row bands) ─────────────────────────────────────────
+
+// Tall enough to span several row bands with the `rayon` feature.
+const TALL_W: usize = 37;
+const TALL_H: usize = 4099;
+
+#[test]
+fn tall_image_decode_as_matches_convert() {
+    let pixels = noise_pattern(TALL_W, TALL_H, 3);
+    let (w, h) = (TALL_W as u32, TALL_H as u32);
+    let ppm = encode_ppm(&pixels, w, h, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let pfm = encode_pfm(&pixels, w, h, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let floats = convert(&pixels, PixelLayout::Rgb8, PixelLayout::RgbF32).unwrap();
+    for target in [PixelLayout::Bgra8, PixelLayout::Gray16, PixelLayout::RgbF32] {
+        let decoded = decode_as(&ppm, target, Unstoppable).unwrap();
+        assert_eq!(
+            decoded.pixels(),
+            &convert(&pixels, PixelLayout::Rgb8, target).unwrap()[..]
+        );
+        let decoded = decode_as(&pfm, target, Unstoppable).unwrap();
+        assert_eq!(
+            decoded.pixels(),
+            &convert(&floats, PixelLayout::RgbF32, target).unwrap()[..]
+        );
+    }
+}
+
+#[test]
+fn tall_image_encode_matches_row_by_row() {
+    let pixels = noise_pattern(TALL_W, TALL_H, 4);
+    let (w, h) = (TALL_W as u32, TALL_H as u32);
+    let row_bytes = TALL_W * 4;
+    #[cfg_attr(not(feature = "basic-bmp"), allow(unused_mut))]
+    let mut encoders: Vec<(EncodeFormat, Encoder, bool)> = vec![
+        (EncodeFormat::Ppm, encode_ppm, false),
+        (EncodeFormat::Pam, encode_pam, false),
+        (EncodeFormat::Pfm, encode_pfm, true),
+    ];
+    #[cfg(feature = "basic-bmp")]
+    encoders.extend([
+        (EncodeFormat::Bmp, encode_bmp as Encoder, true),
+        (EncodeFormat::BmpRgba, encode_bmp_rgba, true),
+    ]);
+    for (format, encode, bottom_up) in encoders {
+        let full = encode(&pixels, w, h, PixelLayout::Rgba8, Unstoppable).unwrap();
+        let stored_row = encoded_len(w, 2, PixelLayout::Rgba8, format).unwrap()
+            - encoded_len(w, 1, PixelLayout::Rgba8, format).unwrap();
+        // Each row encoded on its own gives the same stored row bytes
+        let rows: Vec<Vec<u8>> = pixels
+            .chunks_exact(row_bytes)
+            .map(|row| {
+                let one = encode(row, w, 1, PixelLayout::Rgba8, Unstoppable).unwrap();
+                one[one.len() - stored_row..].to_vec()
+            })
+            .collect();
+        let body: Vec<u8> = if bottom_up {
+            rows.iter().rev().flatten().copied().collect()
+        } else {
+            rows.iter().flatten().copied().collect()
+        };
+        assert_eq!(&full[full.len() - body.len()..], &body[..]);
+    }
+}
+
+/// Cancels once `checks` calls to `check` have passed.
+struct CancelAfter(core::sync::atomic::AtomicUsize);
+
+impl enough::Stop for CancelAfter {
+    fn check(&self) -> Result<(), enough::StopReason> {
+        use core::sync::atomic::Ordering;
+        match self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(enough::StopReason::Cancelled),
+        }
+    }
+}
+
+#[test]
+fn tall_image_honors_stop() {
+    let pixels = noise_pattern(TALL_W, TALL_H, 3);
+    let (w, h) = (TALL_W as u32, TALL_H as u32);
+    let ppm = encode_ppm(&pixels, w, h, PixelLayout::Rgb8, Unstoppable).unwrap();
+    let stop = CancelAfter(10.into());
+    assert!(matches!(
+        decode_as(&ppm, PixelLayout::Rgba8, &stop),
+        Err(PnmError::Cancelled(_))
+    ));
+    let stop = CancelAfter(10.into());
+    assert!(matches!(
+        encode_pam(&pixels, w, h, PixelLayout::Rgb8, &stop),
+        Err(PnmError::Cancelled(_))
+    ));
+}
+
+// ── SIMD tiers ───────────────────────────────────────────────────────
+
+/// Every kernel must give the same bytes whichever SIMD tier runs it
+/// (with the `simd` feature; without it every permutation is scalar).
+#[test]
+fn kernels_bit_identical_across_simd_tiers() {
+    use archmage::testing::{CompileTimePolicy, for_each_token_permutation};
+
+    const U8_LAYOUTS: [PixelLayout; 6] = [
+        PixelLayout::Gray8,
+        PixelLayout::Rgb8,
+        PixelLayout::Rgba8,
+        PixelLayout::Bgr8,
+        PixelLayout::Bgra8,
+        PixelLayout::Bgrx8,
+    ];
+    // 37 pixels leaves a remainder after any vector width
+    let noise = noise_pattern(37, 1, 4);
+    let mut maxval_100 = b"P6\n37 1\n100\n".to_vec();
+    maxval_100.extend(noise[..111].iter().map(|v| v % 101));
+    let mut maxval_1000 = b"P5\n37 1\n1000\n".to_vec();
+    maxval_1000.extend(
+        noise[..74]
+            .chunks_exact(2)
+            .flat_map(|c| (u16::from_ne_bytes([c[0], c[1]]) % 1001).to_be_bytes()),
+    );
+    let files = [
+        maxval_100,
+        maxval_1000,
+        encode_pam(&noise[..74], 37, 1, PixelLayout::Gray16, Unstoppable).unwrap(),
+        encode_pam(&noise[..148], 37, 1, PixelLayout::GrayF32, Unstoppable).unwrap(),
+    ];
+
+    let run = || {
+        let mut out = Vec::new();
+        for src in U8_LAYOUTS {
+            for dst in U8_LAYOUTS {
+                let pixels = &noise[..37 * src.bytes_per_pixel()];
+                out.push(convert(pixels, src, dst).unwrap());
+            }
+        }
+        for file in &files {
+            out.push(decode(file, Unstoppable).unwrap().pixels().to_vec());
+        }
+        out
+    };
+    let expected = run();
+    let report = for_each_token_permutation(CompileTimePolicy::Warn, |_perm| {
+        assert_eq!(run(), expected);
+    });
+    assert!(report.permutations_run >= 1);
+}
+
+// ── Allocation failure ───────────────────────────────────────────────
+
+/// A header whose output buffer no machine can provide: decoding without
+/// limits must fail with an error rather than abort.
+#[cfg(all(feature = "basic-bmp", target_pointer_width = "64"))]
+#[test]
+fn huge_bmp_header_without_limits_fails_to_allocate() {
+    let mut data = encode_bmp(&[0u8; 3], 1, 1, PixelLayout::Rgb8, Unstoppable).unwrap();
+    data[18..22].copy_from_slice(&(1i32 << 30).to_le_bytes());
+    data[22..26].copy_from_slice(&(1i32 << 30).to_le_bytes());
+    assert!(matches!(
+        decode_bmp(&data, Unstoppable),
+        Err(PnmError::AllocationFailed { .. })
+    ));
+}
+
 // ── External files ───────────────────────────────────────────────────
 
 #[test]
@@ -120,10 +120,11 @@ fn strided_decode_into() {
     // Create a 4x3 buffer and decode into a 2x2 sub_image
     let buf = vec![RGB8::new(0, 0, 0); 12];
     let mut full = imgref::ImgVec::new(buf, 4, 3);
-    let mut as_mut = full.as_mut();
-    let sub = as_mut.sub_image_mut(0, 0, 2, 2);
-    decode_into(&encoded, sub, Unstoppable).unwrap();
-    drop(as_mut);
+    {
+        let mut as_mut = full.as_mut();
+        let sub = as_mut.sub_image_mut(0, 0, 2, 2);
+        decode_into(&encoded, sub, Unstoppable).unwrap();
+    }
     let buf = full.into_buf();
 
     // Check that the 2x2 region was filled (stride=4)
@@ -155,3 +156,260 @@ fn decode_output_to_imgvec() {
     assert_eq!(img.height(), 1);
     assert_eq!(img.buf(), &pixels);
 }
+
+// ── Gray, 16-bit and float pixel types ───────────────────────────────
+
+#[test]
+fn gray8_pixels_roundtrip() {
+    let pixels = vec![GRAY8::new(0), GRAY8::new(77), GRAY8::new(255)];
+    let encoded = encode_pgm_pixels(&pixels, 3, 1, Unstoppable).unwrap();
+    let (decoded, w, h) = decode_pixels::<GRAY8>(&encoded, Unstoppable).unwrap();
+    assert_eq!((w, h), (3, 1));
+    assert_eq!(decoded, pixels);
+}
+
+#[test]
+fn gray16_pixels_roundtrip() {
+    let pixels = vec![GRAY16::new(0), GRAY16::new(1234), GRAY16::new(65535)];
+    let encoded = encode_pgm_pixels(&pixels, 3, 1, Unstoppable).unwrap();
+    assert!(encoded.starts_with(b"P5\n3 1\n65535\n"));
+    let (decoded, _, _) = decode_pixels::<GRAY16>(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded, pixels);
+}
+
+#[test]
+fn rgb16_and_rgba16_pixels_roundtrip() {
+    let rgb = vec![RGB16::new(1, 300, 65535), RGB16::new(40000, 0, 7)];
+    let encoded = encode_ppm_pixels(&rgb, 2, 1, Unstoppable).unwrap();
+    let (decoded, _, _) = decode_pixels::<RGB16>(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded, rgb);
+
+    let rgba = vec![RGBA16::new(1, 2, 3, 4), RGBA16::new(65535, 256, 0, 32768)];
+    let encoded = encode_pam_pixels(&rgba, 1, 2, Unstoppable).unwrap();
+    let decoded = decode_img::<RGBA16>(&encoded, Unstoppable).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (1, 2));
+    assert_eq!(decoded.buf(), &rgba);
+}
+
+#[test]
+fn float_pixels_roundtrip() {
+    let gray = vec![Gray::new(0.25f32), Gray::new(-1.5), Gray::new(100.0)];
+    let encoded = encode_pfm_pixels(&gray, 3, 1, Unstoppable).unwrap();
+    let (decoded, _, _) = decode_pixels::<Gray<f32>>(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded, gray);
+
+    let rgb = imgref::ImgVec::new(
+        vec![Rgb::new(0.1f32, 0.2, 0.3), Rgb::new(1.0, 2.0, 3.0)],
+        1,
+        2,
+    );
+    let encoded = encode_pfm_img(rgb.as_ref(), Unstoppable).unwrap();
+    let decoded = decode_img::<Rgb<f32>>(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.buf(), rgb.buf());
+
+    let rgba = vec![Rgba::new(0.5f32, 0.25, 0.125, 0.75)];
+    let encoded = encode_pam_pixels(&rgba, 1, 1, Unstoppable).unwrap();
+    let decoded = decode(&encoded, Unstoppable).unwrap();
+    assert_eq!(decoded.as_pixels::<Rgba<f32>>().unwrap(), &rgba[..]);
+}
+
+#[test]
+fn strided_gray16_decode_into_and_encode() {
+    let pixels: Vec<GRAY16> = (0..4).map(|i| GRAY16::new(i * 1000 + 1)).collect();
+    let encoded = encode_pgm_pixels(&pixels, 2, 2, Unstoppable).unwrap();
+
+    let mut full = imgref::ImgVec::new(vec![GRAY16::new(0); 12], 4, 3);
+    {
+        let mut as_mut = full.as_mut();
+        let sub = as_mut.sub_image_mut(1, 1, 2, 2);
+        decode_into(&encoded, sub, Unstoppable).unwrap();
+    }
+    let sub = full.as_ref().sub_image(1, 1, 2, 2);
+    assert_eq!(sub.pixels().collect::<Vec<_>>(), pixels);
+    assert_eq!(encode_pgm_img(sub, Unstoppable).unwrap(), encoded);
+}
+
+#[test]
+fn typed_16bit_decode_keeps_stored_samples() {
+    let mut data = b"P6\n1 1\n1000\n".to_vec();
+    for v in [0u16, 500, 1000] {
+        data.extend_from_slice(&v.to_be_bytes());
+    }
+    let (wide, _, _) = decode_pixels::<RGB16>(&data, Unstoppable).unwrap();
+    assert_eq!(wide, [RGB16::new(0, 500, 1000)]);
+    // The byte API reads the same samples
+    let bytes = decode_as(&data, PixelLayout::Rgb16, Unstoppable).unwrap();
+    let typed: Vec<u8> = [0u16, 500, 1000]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect();
+    assert_eq!(bytes.pixels(), &typed[..]);
+    let (narrow, _, _) = decode_pixels::<RGB8>(&data, Unstoppable).unwrap();
+    assert_eq!(narrow, [RGB8::new(0, 128, 255)]);
+}
+
+#[test]
+fn typed_layout_mismatch_across_depths() {
+    let pixels = vec![RGB8::new(1, 2, 3)];
+    let encoded = encode_ppm_pixels(&pixels, 1, 1, Unstoppable).unwrap();
+    assert!(matches!(
+        decode_pixels::<RGB16>(&encoded, Unstoppable),
+        Err(PnmError::LayoutMismatch { .. })
+    ));
+    assert!(matches!(
+        decode_pixels::<Rgb<f32>>(&encoded, Unstoppable),
+        Err(PnmError::LayoutMismatch { .. })
+    ));
+}
+
+#[test]
+fn decode_pixels_cow_borrows_zero_copy() {
+    let pixels = vec![RGB8::new(1, 2, 3), RGB8::new(4, 5, 6), RGB8::new(7, 8, 9)];
+    let encoded = encode_ppm_pixels(&pixels, 3, 1, Unstoppable).unwrap();
+    let (decoded, w, h) = decode_pixels_cow::<RGB8>(&encoded, Unstoppable).unwrap();
+    assert_eq!((w, h), (3, 1));
+    assert!(matches!(decoded, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(&decoded[..], &pixels[..]);
+
+    let limits = Limits::default();
+    let (decoded, _, _) =
+        decode_pixels_cow_with_limits::<RGB8>(&encoded, &limits, Unstoppable).unwrap();
+    assert!(matches!(decoded, std::borrow::Cow::Borrowed(_)));
+}
+
+#[test]
+fn decode_pixels_cow_owned_when_transformed() {
+    let pixels = vec![GRAY16::new(1000), GRAY16::new(65535)];
+    let encoded = encode_pgm_pixels(&pixels, 2, 1, Unstoppable).unwrap();
+    let (decoded, _, _) = decode_pixels_cow::<GRAY16>(&encoded, Unstoppable).unwrap();
+    assert!(matches!(decoded, std::borrow::Cow::Owned(_)));
+    assert_eq!(&decoded[..], &pixels[..]);
+
+    let floats = vec![Rgb::new(0.25f32, 0.5, 1.0)];
+    let encoded = encode_pfm_pixels(&floats, 1, 1, Unstoppable).unwrap();
+    let (decoded, _, _) = decode_pixels_cow::<Rgb<f32>>(&encoded, Unstoppable).unwrap();
+    assert_eq!(&decoded[..], &floats[..]);
+
+    assert!(matches!(
+        decode_pixels_cow::<RGBA8>(&encoded, Unstoppable),
+        Err(PnmError::LayoutMismatch { .. })
+    ));
+}
+
+#[test]
+fn decode_img_cow_borrows_zero_copy() {
+    use imgref::ImgExt as _;
+
+    let pixels: Vec<RGBA8> = (0..6u8).map(|i| RGBA8::new(i, i * 2, i * 3, 255)).collect();
+    let encoded = encode_pam_pixels(&pixels, 3, 2, Unstoppable).unwrap();
+    let img = decode_img_cow::<RGBA8>(&encoded, Unstoppable).unwrap();
+    assert!(matches!(img.buf(), std::borrow::Cow::Borrowed(_)));
+    assert_eq!((img.width(), img.height()), (3, 2));
+    let rows: Vec<&[RGBA8]> = img.as_ref().rows().collect();
+    assert_eq!(rows[1], &pixels[3..]);
+    assert_eq!(
+        img.into_owned().buf(),
+        decode_img::<RGBA8>(&encoded, Unstoppable).unwrap().buf()
+    );
+}
+
+#[test]
+fn decode_output_into_img() {
+    let pixels = vec![GRAY8::new(7), GRAY8::new(9)];
+    let encoded = encode_pgm_pixels(&pixels, 1, 2, Unstoppable).unwrap();
+    let img = decode(&encoded, Unstoppable)
+        .unwrap()
+        .into_img::<GRAY8>()
+        .unwrap();
+    assert!(matches!(img.buf(), std::borrow::Cow::Borrowed(_)));
+    assert_eq!((img.width(), img.height()), (1, 2));
+    assert_eq!(&img.buf()[..], &pixels[..]);
+}
+
+#[test]
+fn decode_region_into_img_borrows_with_source_stride() {
+    use imgref::ImgExt as _;
+
+    let pixels: Vec<RGB8> = (0..12u8).map(|i| RGB8::new(i, i * 2, i * 3)).collect();
+    let encoded = encode_ppm_pixels(&pixels, 4, 3, Unstoppable).unwrap();
+    let img = decode_region(&encoded, 1, 1, 2, 2, Unstoppable)
+        .unwrap()
+        .into_img::<RGB8>()
+        .unwrap();
+    assert!(matches!(img.buf(), std::borrow::Cow::Borrowed(_)));
+    assert_eq!((img.width(), img.height(), img.stride()), (2, 2, 4));
+    let rows: Vec<&[RGB8]> = img.as_ref().rows().collect();
+    assert_eq!(rows, [&pixels[5..7], &pixels[9..11]]);
+
+    let region = decode_region(&encoded, 1, 1, 2, 2, Unstoppable).unwrap();
+    assert!(region.as_pixels::<RGB8>().is_err());
+    assert_eq!(
+        &region.into_pixels::<RGB8>().unwrap()[..],
+        &[pixels[5], pixels[6], pixels[9], pixels[10]]
+    );
+}
+
+#[test]
+fn decode_view_bottom_up_copies_into_top_down_pixels() {
+    let pixels: Vec<Rgb<f32>> = (0..6).map(|i| Rgb::new(i as f32, 0.5, -1.0)).collect();
+    let encoded = encode_pfm_pixels(&pixels, 2, 3, Unstoppable).unwrap();
+    let view = decode_view(&encoded, Unstoppable).unwrap();
+    assert_eq!(view.row_order(), RowOrder::BottomUp);
+    assert!(view.as_pixels::<Rgb<f32>>().is_err());
+    let img = view.clone().into_img::<Rgb<f32>>().unwrap();
+    assert!(matches!(img.buf(), std::borrow::Cow::Owned(_)));
+    assert_eq!((img.width(), img.height()), (2, 3));
+    assert_eq!(&img.buf()[..], &pixels[..]);
+    assert_eq!(view.to_imgvec::<Rgb<f32>>().unwrap().buf(), &pixels);
+}
+
+#[test]
+fn sub_image_encode_matches_contiguous_for_every_encoder() {
+    // Sub-image away from the origin: stride 5, last row ends before the buffer
+    let buf: Vec<RGBA8> = (0..30u8)
+        .map(|i| RGBA8::new(i, i * 3, i * 7, 255 - i))
+        .collect();
+    let full = imgref::ImgVec::new(buf, 5, 6);
+    let sub = full.as_ref().sub_image(1, 2, 3, 2);
+    let flat = imgref::ImgVec::new(sub.pixels().collect::<Vec<_>>(), 3, 2);
+
+    type Encoder = fn(imgref::ImgRef<'_, RGBA8>, Unstoppable) -> Result<Vec<u8>, PnmError>;
+    #[cfg_attr(not(feature = "basic-bmp"), allow(unused_mut))]
+    let mut encoders: Vec<Encoder> = vec![
+        encode_ppm_img,
+        encode_pgm_img,
+        encode_pam_img,
+        encode_pfm_img,
+    ];
+    #[cfg(feature = "basic-bmp")]
+    encoders.extend([encode_bmp_img as Encoder, encode_bmp_rgba_img]);
+    for encode in encoders {
+        assert_eq!(
+            encode(sub, Unstoppable).unwrap(),
+            encode(flat.as_ref(), Unstoppable).unwrap()
+        );
+    }
+}
+
+#[test]
+fn typed_decodes_take_decode_options() {
+    let options = DecodeOptions::default().with_samples(SamplePolicy::Error);
+    let data = b"P6 1 1 100\n\x00\x65\x00";
+    let out_of_range = |err| matches!(err, PnmError::SampleOutOfRange { channel: 1, .. });
+    let err = decode_pixels_with_options::<RGB8>(data, &options, Unstoppable).unwrap_err();
+    assert!(out_of_range(err));
+    let err = decode_pixels_cow_with_options::<RGB8>(data, &options, Unstoppable).unwrap_err();
+    assert!(out_of_range(err));
+    let err = decode_img_with_options::<RGB8>(data, &options, Unstoppable).unwrap_err();
+    assert!(out_of_range(err));
+    let err = decode_img_cow_with_options::<RGB8>(data, &options, Unstoppable).unwrap_err();
+    assert!(out_of_range(err));
+    let mut buf = vec![RGB8::new(0, 0, 0)];
+    let output = imgref::ImgRefMut::new(&mut buf, 1, 1);
+    let err = decode_into_with_options(data, output, &options, Unstoppable).unwrap_err();
+    assert!(out_of_range(err));
+
+    let options = options.with_samples(SamplePolicy::PassThrough);
+    let (pixels, _, _) = decode_pixels_with_options::<RGB8>(data, &options, Unstoppable).unwrap();
+    assert_eq!(pixels, [RGB8::new(0, 101, 0)]);
+}